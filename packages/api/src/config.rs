@@ -1,12 +1,69 @@
+use crate::services::api_keys::ApiKeyConfig;
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+};
 
 pub(crate) const CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/config/");
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct RedisConfig {
     pub url: String,
+    /// Time to live of the stored simulations, in seconds. Zero is rejected, as
+    /// the simulations would expire as soon as they are stored.
+    #[serde(default = "default_simulation_ttl")]
+    pub simulation_ttl: NonZeroU64,
+}
+
+/// Stored simulations are kept for a week by default.
+const fn default_simulation_ttl() -> NonZeroU64 {
+    NonZeroU64::new(7 * 24 * 60 * 60).unwrap()
+}
+
+/// Storage of the cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackendKind {
+    /// Redis, falling back to the in-process cache while it is unreachable.
+    #[default]
+    Redis,
+    /// In-process LRU cache.
+    Memory,
+}
+
+/// Backend of the cache, and time to live of the cached responses of each
+/// endpoint, in seconds.
+///
+/// Only immutable responses are cached: traces of transactions in finalized
+/// blocks, and traces and simulations pinned to a finalized block number.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub backend: CacheBackendKind,
+    /// Maximum number of entries of the in-process cache.
+    pub memory_capacity: NonZeroUsize,
+    pub trace_transaction_ttl: i64,
+    pub trace_call_ttl: i64,
+    pub trace_call_many_ttl: i64,
+    pub simulate_ttl: i64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        const DAY: i64 = 24 * 60 * 60;
+        Self {
+            backend: CacheBackendKind::default(),
+            memory_capacity: NonZeroUsize::new(10_000).unwrap(),
+            trace_transaction_ttl: DAY,
+            trace_call_ttl: DAY,
+            trace_call_many_ttl: DAY,
+            simulate_ttl: DAY,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -17,11 +74,64 @@ pub(crate) struct ServerConfig {
 
 #[derive(Clone, Debug, Deserialize)]
 pub(crate) struct ApiConfig {
+    /// Key without restrictions, named `default`.
     pub auth_token: Option<String>,
+    /// Token of the admin endpoints, which are disabled if it is not set.
+    pub admin_token: Option<String>,
+    /// Keys with their scopes, by hash.
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
     pub base_url: Option<String>,
+    /// Requests allowed per client in a window of `rate_limit_duration` seconds.
+    pub rate_limit_requests: Option<u64>,
+    pub rate_limit_duration: Option<u64>,
+    /// Cost of a request to each endpoint (e.g. `"/trace/tx" = 5`), 1 by default.
+    #[serde(default)]
+    pub rate_limit_weights: HashMap<String, u64>,
+    /// Reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are
+    /// trusted for the IP address of the client. Clients are identified by the
+    /// peer address of the connection otherwise.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
     pub cors: CorsConfig,
 }
 
+impl ApiConfig {
+    /// Token of the admin endpoints, if they are enabled.
+    pub(crate) fn admin_token(&self) -> Option<&str> {
+        self.admin_token
+            .as_deref()
+            .filter(|token| !token.is_empty())
+    }
+
+    pub(crate) fn admin_enabled(&self) -> bool {
+        self.admin_token().is_some()
+    }
+
+    /// Rate limit of the API, `None` if it is not configured.
+    pub(crate) fn rate_limit(&self) -> Option<RateLimitConfig> {
+        match (self.rate_limit_requests, self.rate_limit_duration) {
+            (Some(requests), Some(duration)) if requests > 0 && duration > 0 => {
+                Some(RateLimitConfig {
+                    requests,
+                    duration,
+                    weights: self.rate_limit_weights.clone(),
+                    trusted_proxies: self.trusted_proxies.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct RateLimitConfig {
+    pub requests: u64,
+    pub duration: u64,
+    pub weights: HashMap<String, u64>,
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub(crate) struct CorsConfig {
     pub allowed_origins: Vec<String>,
@@ -31,10 +141,67 @@ pub(crate) struct CorsConfig {
     pub max_age: Option<u32>,
 }
 
+/// Prometheus metrics endpoint, `GET /metrics`.
+///
+/// The metrics are not authenticated, so they are served on a separate server,
+/// on the loopback interface by default, never on the port of the API.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct MetricsConfig {
+    pub enabled: bool,
+    /// Host of the metrics server.
+    pub host: String,
+    /// Port of the metrics server.
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: false, host: "127.0.0.1".to_string(), port: 9090 }
+    }
+}
+
+/// OpenTelemetry traces of the requests.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct TelemetryConfig {
+    /// OTLP/HTTP endpoint of the collector the traces are exported to (e.g.
+    /// `http://localhost:4318/v1/traces`). Traces are not exported if it is not
+    /// set.
+    pub otlp_endpoint: Option<String>,
+    /// Name of the service in the traces.
+    pub service_name: String,
+    /// Share of the traces started by the API that are sampled, from 0 to 1.
+    /// Requests continuing a trace follow the sampling decision of their
+    /// `traceparent` header.
+    pub sample_ratio: f64,
+}
+
+impl TelemetryConfig {
+    /// Endpoint of the collector, if traces are exported.
+    pub(crate) fn otlp_endpoint(&self) -> Option<&str> {
+        self.otlp_endpoint
+            .as_deref()
+            .filter(|endpoint| !endpoint.is_empty())
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { otlp_endpoint: None, service_name: "altitrace-api".to_string(), sample_ratio: 1.0 }
+    }
+}
+
 #[allow(unused)]
 #[derive(Clone, Debug, Deserialize)]
 pub(crate) struct AppConfig {
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
     pub server: ServerConfig,
     pub api: ApiConfig,
     pub environment: String,
@@ -103,4 +270,20 @@ mod tests {
         dotenvy::dotenv().ok();
         let _ = AppConfig::default();
     }
+
+    #[test]
+    fn test_simulation_ttl_rejects_zero() {
+        let redis_config = |toml: &str| {
+            Config::builder()
+                .add_source(File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize::<RedisConfig>()
+        };
+
+        let default = redis_config("url = 'redis://127.0.0.1'").unwrap();
+        assert_eq!(default.simulation_ttl, default_simulation_ttl());
+        assert!(redis_config("url = 'redis://127.0.0.1'\nsimulation_ttl = 0").is_err());
+        assert!(redis_config("url = 'redis://127.0.0.1'\nsimulation_ttl = -1").is_err());
+    }
 }
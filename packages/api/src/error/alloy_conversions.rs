@@ -6,6 +6,12 @@ use std::sync::LazyLock;
 // Convert alloy transport errors
 impl From<TransportError> for RpcError {
     fn from(err: TransportError) -> Self {
+        Self::from(&err)
+    }
+}
+
+impl From<&TransportError> for RpcError {
+    fn from(err: &TransportError) -> Self {
         match err {
             AlloyRpcError::Transport(transport_kind) => match transport_kind {
                 TransportErrorKind::MissingBatchResponse(id) => Self::InternalError {
@@ -1,8 +1,17 @@
 use super::{
-    cache::CacheError, conversion::ConversionError, provider::ProviderError,
-    response::ErrorResponse, rpc::RpcError, service::ServiceError, validation::ValidationError,
+    cache::CacheError,
+    conversion::ConversionError,
+    provider::ProviderError,
+    response::{ErrorResponse, ErrorResponseProvider},
+    rpc::RpcError,
+    service::ServiceError,
+    validation::ValidationError,
+};
+use crate::services::telemetry::current_request_id;
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
 };
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
@@ -147,8 +156,11 @@ impl ApiError {
 
 impl ResponseError for ApiError {
     fn error_response(&self) -> HttpResponse {
-        let status = self.status_code();
-        HttpResponse::build(status).json(self.to_error_response(None))
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(retry_after) = self.retry_after() {
+            response.insert_header((header::RETRY_AFTER, retry_after));
+        }
+        response.json(self.to_error_response(current_request_id()))
     }
 
     fn status_code(&self) -> StatusCode {
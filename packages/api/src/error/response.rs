@@ -152,7 +152,8 @@ impl ErrorResponseProvider for super::api::ApiError {
                     ServiceError::SimulationFailed { reason, .. } |
                     ServiceError::TraceFailed { reason, .. } |
                     ServiceError::InvalidBlockContext { reason } |
-                    ServiceError::AccessListFailed { reason } => {
+                    ServiceError::AccessListFailed { reason } |
+                    ServiceError::ReplayFailed { reason } => {
                         details.insert("reason".to_string(), Value::String(reason.clone()));
                     }
                     _ => return None,
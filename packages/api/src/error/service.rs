@@ -35,6 +35,9 @@ pub enum ServiceError {
     #[error("Gas estimation failed: {reason}")]
     GasEstimationFailed { reason: String },
 
+    #[error("Replay failed: {reason}")]
+    ReplayFailed { reason: String },
+
     #[error("Node communication error")]
     NodeCommunication(#[from] RpcError),
 
@@ -74,6 +77,10 @@ impl ServiceError {
         Self::GasEstimationFailed { reason: reason.into() }
     }
 
+    pub fn replay_failed(reason: impl Into<String>) -> Self {
+        Self::ReplayFailed { reason: reason.into() }
+    }
+
     pub fn resource_exhausted(resource: impl Into<String>) -> Self {
         Self::ResourceExhausted { resource: resource.into() }
     }
@@ -92,6 +99,7 @@ impl ServiceError {
             Self::AccessListFailed { .. } => "ACCESS_LIST_FAILED",
             Self::BundleValidationFailed { .. } => "BUNDLE_VALIDATION_FAILED",
             Self::GasEstimationFailed { .. } => "GAS_ESTIMATION_FAILED",
+            Self::ReplayFailed { .. } => "REPLAY_FAILED",
             Self::NodeCommunication(rpc) => rpc.error_code(),
             Self::ResourceExhausted { .. } => "RESOURCE_EXHAUSTED",
             Self::OperationTimeout { .. } => "OPERATION_TIMEOUT",
@@ -120,6 +128,9 @@ impl ServiceError {
             Self::GasEstimationFailed { .. } => {
                 Some("Transaction may fail or gas limit is too low")
             }
+            Self::ReplayFailed { .. } => {
+                Some("Ensure the transaction is mined and check the modifications")
+            }
             Self::NodeCommunication(rpc) => rpc.suggestion(),
             Self::ResourceExhausted { .. } => {
                 Some("Try reducing the request size or wait before retrying")
@@ -140,7 +151,8 @@ impl ServiceError {
             }
             Self::InvalidBlockContext { .. } |
             Self::InvalidStateOverride { .. } |
-            Self::BundleValidationFailed { .. } => StatusCode::BAD_REQUEST,
+            Self::BundleValidationFailed { .. } |
+            Self::ReplayFailed { .. } => StatusCode::BAD_REQUEST,
             Self::NodeCommunication(rpc) => rpc.status_code(),
             Self::ResourceExhausted { .. } => StatusCode::SERVICE_UNAVAILABLE,
             Self::OperationTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
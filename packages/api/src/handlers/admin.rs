@@ -0,0 +1,230 @@
+use crate::{
+    define_routes,
+    error::{ApiError, ApiResult},
+    handlers::{
+        common::{ApiResponse, Handler},
+        usage::usage_meter,
+    },
+    services::{
+        api_keys::{ApiKey, ApiKeyStore, KeyScopes, KeySource},
+        telemetry::request_id,
+        usage::{KeyUsage, UsageMeter, UsageQuery},
+    },
+};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        list_keys,
+        create_key,
+        rotate_key,
+        revoke_key,
+        all_usage,
+    ),
+    components(
+        schemas(
+            ApiKey,
+            KeyScopes,
+            KeySource,
+            CreateKeyRequest,
+            CreatedKey,
+            ApiResponse<Vec<ApiKey>>,
+            ApiResponse<CreatedKey>,
+            ApiResponse<Vec<KeyUsage>>,
+        ),
+    ),
+    tags(
+        (name = "admin", description = "Admin endpoints for managing the API keys, authenticated with the admin token")
+    )
+)]
+pub struct AdminApiDoc;
+
+/// Request to create an API key.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateKeyRequest {
+    /// Unique name of the key.
+    pub name: String,
+    #[serde(flatten)]
+    pub scopes: KeyScopes,
+}
+
+/// API key, created or rotated.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedKey {
+    /// The key itself. Only its hash is stored: it cannot be retrieved later.
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+pub struct AdminHandler {
+    store: Arc<ApiKeyStore>,
+    meter: Option<Arc<UsageMeter>>,
+}
+
+impl AdminHandler {
+    pub const fn new(store: Arc<ApiKeyStore>) -> Self {
+        Self { store, meter: None }
+    }
+
+    pub fn with_usage(mut self, meter: Option<Arc<UsageMeter>>) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    pub fn into_app_data(self) -> web::Data<Self> {
+        web::Data::new(self)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/keys",
+    tag = "admin",
+    summary = "List the API keys",
+    description = "List the API keys declared in the configuration or created at runtime, without the keys themselves",
+    responses(
+        (status = 200, description = "API keys", body = ApiResponse<Vec<ApiKey>>),
+        (status = 401, description = "Invalid admin token", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn list_keys(handler: web::Data<AdminHandler>) -> ApiResult<HttpResponse> {
+    let keys = handler.store.list().await?;
+    Ok(ApiResponse::success(keys, request_id()).into())
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/keys",
+    tag = "admin",
+    summary = "Create an API key",
+    description = "Create an API key with the given scopes. The key is only returned in this response.",
+    request_body = CreateKeyRequest,
+    responses(
+        (status = 200, description = "API key created", body = ApiResponse<CreatedKey>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 401, description = "Invalid admin token", body = ApiResponse<String>),
+        (status = 409, description = "A key with this name already exists", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn create_key(
+    handler: web::Data<AdminHandler>,
+    request: web::Json<CreateKeyRequest>,
+) -> ApiResult<HttpResponse> {
+    let CreateKeyRequest { name, scopes } = request.into_inner();
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::bad_request("The key name cannot be empty"));
+    }
+
+    let (api_key, key) = handler.store.create(name, scopes).await?;
+    Ok(ApiResponse::success(CreatedKey { key, api_key }, request_id()).into())
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/keys/{name}/rotate",
+    tag = "admin",
+    summary = "Rotate an API key",
+    description = "Replace an API key by a new one with the same scopes. The previous key stops working immediately.",
+    params(
+        ("name" = String, Path, description = "Name of the key")
+    ),
+    responses(
+        (status = 200, description = "API key rotated", body = ApiResponse<CreatedKey>),
+        (status = 401, description = "Invalid admin token", body = ApiResponse<String>),
+        (status = 404, description = "API key not found", body = ApiResponse<String>),
+        (status = 409, description = "The key is declared in the configuration, or was changed concurrently", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn rotate_key(
+    handler: web::Data<AdminHandler>,
+    name: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+    let (api_key, key) = handler.store.rotate(&name).await?;
+    Ok(ApiResponse::success(CreatedKey { key, api_key }, request_id()).into())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/keys/{name}",
+    tag = "admin",
+    summary = "Revoke an API key",
+    params(
+        ("name" = String, Path, description = "Name of the key")
+    ),
+    responses(
+        (status = 200, description = "API key revoked", body = ApiResponse<String>),
+        (status = 401, description = "Invalid admin token", body = ApiResponse<String>),
+        (status = 404, description = "API key not found", body = ApiResponse<String>),
+        (status = 409, description = "The key is declared in the configuration", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn revoke_key(
+    handler: web::Data<AdminHandler>,
+    name: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+    handler.store.revoke(&name).await?;
+    Ok(ApiResponse::success(format!("API key '{}' revoked", name), request_id()).into())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/keys/usage",
+    tag = "admin",
+    summary = "Get the usage of every API key",
+    description = "Get the usage of every API key with recorded usage, by hour or day (UTC)",
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Usage of the API keys", body = ApiResponse<Vec<KeyUsage>>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 401, description = "Invalid admin token", body = ApiResponse<String>),
+        (status = 503, description = "Usage metering is not available", body = ApiResponse<String>)
+    )
+)]
+async fn all_usage(
+    handler: web::Data<AdminHandler>,
+    query: web::Query<UsageQuery>,
+) -> ApiResult<HttpResponse> {
+    let periods = query.periods()?;
+    let usage = usage_meter(&handler.meter)?
+        .all_usage(query.granularity, periods)
+        .await?;
+    Ok(ApiResponse::success(usage, request_id()).into())
+}
+
+define_routes!(
+    AdminHandler,
+    "/keys",
+    "" => { method: get, handler: list_keys },
+    "/usage" => {
+        method: get,
+        handler: all_usage,
+        params: { query: web::Query<UsageQuery> }
+    },
+    "" => {
+        method: post,
+        handler: create_key,
+        params: { request: web::Json<CreateKeyRequest> }
+    },
+    "/{name}/rotate" => {
+        method: post,
+        handler: rotate_key,
+        params: { name: web::Path<String> }
+    },
+    "/{name}" => {
+        method: delete,
+        handler: revoke_key,
+        params: { name: web::Path<String> }
+    },
+);
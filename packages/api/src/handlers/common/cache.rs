@@ -0,0 +1,25 @@
+use crate::services::{Cache, CacheStatus};
+use std::future::Future;
+
+/// Runs a query through the response cache (see [`Cache::cache`]).
+///
+/// Requests without a cache key, such as those depending on the latest block,
+/// and handlers without a cache always run the query.
+pub async fn cached<T, E, F, Fut>(
+    cache: Option<&Cache>,
+    key: Option<String>,
+    ttl: i64,
+    query_fn: F,
+) -> Result<(T, CacheStatus), E>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(T, bool), E>>,
+{
+    match (cache, key) {
+        (Some(cache), Some(key)) => cache.cache(&key, ttl, query_fn).await,
+        _ => query_fn()
+            .await
+            .map(|(value, _)| (value, CacheStatus::Bypass)),
+    }
+}
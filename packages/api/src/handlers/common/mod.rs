@@ -1,5 +1,7 @@
+pub mod cache;
 pub mod response;
 use actix_web::web::ServiceConfig;
+pub use cache::*;
 pub use response::*;
 
 /// Trait for configuring handlers in the Actix web application.
@@ -1,4 +1,11 @@
-use actix_web::{http::StatusCode, HttpResponse};
+use crate::services::CacheStatus;
+use actix_web::{
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    HttpResponse,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoResponses, ToSchema};
@@ -56,8 +63,9 @@ pub struct ApiError {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseMetadata {
-    /// Unique identifier for request tracking and correlation.
-    #[schema(example = "req_01234567-89ab-cdef-0123-456789abcdef")]
+    /// Unique identifier for request tracking and correlation: the ID of the
+    /// trace of the request, also returned in the `X-Request-Id` header.
+    #[schema(example = "4bf92f3577b34da6a3ce929d0e0e4736")]
     pub request_id: String,
 
     /// UTC timestamp when the response was generated.
@@ -118,6 +126,19 @@ impl<T> ApiResponse<T> {
     }
 }
 
+impl<T: Serialize> ApiResponse<T> {
+    /// Converts the response to an HTTP response reporting whether it was served
+    /// from the cache.
+    pub fn with_cache_status(self, status: CacheStatus) -> HttpResponse {
+        let mut response = HttpResponse::from(self);
+        response.headers_mut().insert(
+            HeaderName::from_static(CacheStatus::HEADER),
+            HeaderValue::from_static(status.as_str()),
+        );
+        response
+    }
+}
+
 impl<T: Serialize> From<ApiResponse<T>> for HttpResponse {
     fn from(value: ApiResponse<T>) -> Self {
         // For simulation endpoints, we return 200 even for failed simulations
@@ -1,14 +1,16 @@
 use crate::{
     define_routes,
     handlers::common::{ApiResponse, Handler},
-    services::cache::RedisCache,
+    services::{
+        cache::{BackendHealth, Cache},
+        telemetry::request_id,
+    },
     ApiResult, START_TIME,
 };
 use actix_web::{web, HttpResponse};
 use serde::Serialize;
 use std::time::Instant;
 use utoipa::{OpenApi, ToSchema};
-use uuid::Uuid;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -38,17 +40,19 @@ pub struct HealthStatus {
 
 #[derive(Serialize, ToSchema)]
 struct CacheHealth {
+    /// Backend serving the cache requests, `memory` while Redis is unreachable.
+    backend: String,
     status: String,
     latency_ms: u64,
 }
 
 pub struct HealthHandler {
-    redis_cache: web::Data<RedisCache>,
+    cache: web::Data<Cache>,
 }
 
 impl HealthHandler {
-    pub const fn new(redis_cache: web::Data<RedisCache>) -> Self {
-        Self { redis_cache }
+    pub const fn new(cache: web::Data<Cache>) -> Self {
+        Self { cache }
     }
 
     pub fn into_app_data(self) -> web::Data<Self> {
@@ -57,23 +61,25 @@ impl HealthHandler {
 
     async fn check_health(&self) -> ApiResult<HttpResponse> {
         let cache_start = Instant::now();
-        let cache_status = match self.redis_cache.check_health().await {
-            Ok(_) => "healthy",
-            Err(_) => "unhealthy",
-        };
+        let cache_health = self.cache.health().await;
         let cache_latency = cache_start.elapsed().as_millis() as u64;
 
         let health = HealthStatus {
-            status: if cache_status == "healthy" { "healthy" } else { "degraded" }.to_string(),
+            status: if cache_health == BackendHealth::Healthy { "healthy" } else { "degraded" }
+                .to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             uptime: std::time::SystemTime::now()
                 .duration_since(*START_TIME)
                 .unwrap()
                 .as_secs(),
-            cache: CacheHealth { status: cache_status.to_string(), latency_ms: cache_latency },
+            cache: CacheHealth {
+                backend: self.cache.backend().to_string(),
+                status: cache_health.as_str().to_string(),
+                latency_ms: cache_latency,
+            },
         };
 
-        Ok(ApiResponse::success(health, Uuid::new_v4()).into())
+        Ok(ApiResponse::success(health, request_id()).into())
     }
 }
 
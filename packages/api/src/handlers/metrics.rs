@@ -0,0 +1,30 @@
+use crate::{define_routes, handlers::common::Handler, services::metrics::metrics, ApiResult};
+use actix_web::{web, HttpResponse};
+use prometheus::TEXT_FORMAT;
+
+/// Serves the metrics of the API in the Prometheus text format, see
+/// [`Metrics`](crate::services::metrics::Metrics).
+#[derive(Default)]
+pub struct MetricsHandler;
+
+impl MetricsHandler {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub fn into_app_data(self) -> web::Data<Self> {
+        web::Data::new(self)
+    }
+}
+
+async fn get_metrics(_handler: web::Data<MetricsHandler>) -> ApiResult<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .content_type(TEXT_FORMAT)
+        .body(metrics().encode()))
+}
+
+define_routes!(
+    MetricsHandler,
+    "/metrics",
+    "" => { method: get, handler: get_metrics }
+);
@@ -1,14 +1,20 @@
 //! API handlers
+pub mod admin;
 pub mod common;
 pub mod health;
+pub mod metrics;
 pub mod openapi;
 pub mod simulation;
 pub mod trace;
+pub mod usage;
 pub mod validation;
 
+pub use admin::AdminHandler;
 pub(crate) use common::*;
 pub use health::*;
+pub use metrics::MetricsHandler;
 pub use openapi::*;
 pub use simulation::{SimulationRequest, SimulationResult};
 pub use trace::*;
+pub use usage::UsageHandler;
 pub use validation::*;
@@ -8,7 +8,10 @@ use utoipa::{
 };
 use utoipa_swagger_ui::SwaggerUi;
 
-use super::{health::HealthApiDoc, simulation::SimulationApiDoc, trace::TraceApiDoc, Handler};
+use super::{
+    admin::AdminApiDoc, health::HealthApiDoc, simulation::SimulationApiDoc, trace::TraceApiDoc,
+    usage::UsageApiDoc, Handler,
+};
 //use super::trace::TraceApiDoc;
 use crate::{ApiResult, AppConfig};
 
@@ -71,6 +74,8 @@ impl OpenApiHandler {
         openapi.merge(HealthApiDoc::openapi());
         openapi.merge(SimulationApiDoc::openapi());
         openapi.merge(TraceApiDoc::openapi());
+        openapi.merge(UsageApiDoc::openapi());
+        openapi.merge(AdminApiDoc::openapi());
         openapi
     }
 }
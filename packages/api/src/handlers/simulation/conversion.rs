@@ -4,14 +4,24 @@
 //! types and the corresponding Alloy RPC types used for `HyperEVM` communication.
 
 use super::{dto::*, response::*};
-use crate::types::{shared::StateOverride, BlockOverrides, TransactionCall};
-use alloy_primitives::{map::B256HashMap, Address, Bytes, TxKind, B256, U256};
+use crate::{
+    services::{
+        decoder::{
+            decode_event, decode_revert, revert::undecoded_reason, revert_reason, AbiRegistry,
+        },
+        hyperevm::transfers::is_native_transfer_log,
+    },
+    types::{shared::StateOverride, BlockOverrides, TransactionCall},
+};
+use alloy_primitives::{map::B256HashMap, Address, Bytes, Log, TxKind, B256, U256};
+use alloy_rpc_types::{Bundle as AlloyBundle, TransactionReceipt};
 use alloy_rpc_types_eth::{
     simulate::{SimBlock, SimCallResult, SimulatePayload},
     state::StateOverride as AlloyStateOverride,
     BlockOverrides as AlloyBlockOverrides, TransactionInput,
     TransactionRequest as AlloyTransactionRequest,
 };
+use alloy_rpc_types_trace::geth::{CallFrame as AlloyCallFrame, CallLogFrame};
 use anyhow::{anyhow, Result};
 use std::str::FromStr;
 
@@ -55,6 +65,48 @@ impl TryFrom<SimulationRequest> for SimulatePayload<AlloyTransactionRequest> {
     }
 }
 
+/// Converts a [`MultiBlockSimulationRequest`] to a payload with one [`SimBlock`] per block.
+impl TryFrom<MultiBlockSimulationRequest> for SimulatePayload<AlloyTransactionRequest> {
+    type Error = anyhow::Error;
+
+    fn try_from(request: MultiBlockSimulationRequest) -> Result<Self> {
+        let block_state_calls = request
+            .blocks
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<SimBlock>>>()?;
+
+        Ok(Self {
+            block_state_calls,
+            trace_transfers: request.trace_transfers,
+            validation: request.validation,
+            return_full_transactions: false,
+        })
+    }
+}
+
+impl TryFrom<SimulationBlock> for SimBlock {
+    type Error = anyhow::Error;
+
+    fn try_from(block: SimulationBlock) -> Result<Self> {
+        Ok(Self {
+            calls: block
+                .calls
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+            block_overrides: block
+                .block_overrides
+                .map(convert_block_overrides)
+                .transpose()?,
+            state_overrides: block
+                .state_overrides
+                .map(convert_state_overrides)
+                .transpose()?,
+        })
+    }
+}
+
 /// Converts our API [`TransactionCall`] to Alloy's
 /// [`TransactionRequest`](alloy_primitives::TransactionRequest)
 impl TryFrom<TransactionCall> for AlloyTransactionRequest {
@@ -103,6 +155,14 @@ impl TryFrom<TransactionCall> for AlloyTransactionRequest {
             tx_request.access_list = Some(access_list.into());
         }
 
+        tx_request.nonce = call.nonce;
+
+        if let Some(max_fee_str) = call.max_fee_per_gas {
+            let max_fee = U256::from_str(&max_fee_str)
+                .map_err(|e| anyhow!("Invalid 'maxFeePerGas' '{}': {}", max_fee_str, e))?;
+            tx_request.max_fee_per_gas = Some(max_fee.saturating_to());
+        }
+
         Ok(tx_request)
     }
 }
@@ -224,10 +284,11 @@ impl From<(usize, SimCallResult)> for CallResult {
         let return_data = format!("0x{}", hex::encode(&alloy_result.return_data));
         let gas_used = format!("0x{:x}", alloy_result.gas_used);
 
-        // Convert logs
+        // Convert logs, native transfer logs are reported separately
         let logs = alloy_result
             .logs
             .into_iter()
+            .filter(|log| !is_native_transfer_log(log))
             .map(|log| EnhancedLog {
                 address: format!("0x{:x}", log.address()),
                 block_hash: None,
@@ -242,39 +303,363 @@ impl From<(usize, SimCallResult)> for CallResult {
                     .map(|topic| format!("0x{:x}", topic))
                     .collect(),
                 removed: false,
-                decoded: None, // Will be filled in by event decoder
+                decoded: decode_event(log.topics(), &log.data().data),
             })
             .collect();
 
-        // Convert error if present
-        let error = alloy_result.error.map(|sim_error| CallError {
-            reason: extract_revert_reason(&sim_error.message),
-            error_type: match sim_error.code {
-                -3200 => "execution-reverted".to_string(),
-                -32015 => "vm-execution-error".to_string(),
-                _ => "unknown-error".to_string(),
-            },
-            message: Some(sim_error.message),
-            contract_address: None,
+        // Convert error if present, decoding the revert data carried in the return data
+        let revert_data = &alloy_result.return_data;
+        let error = alloy_result.error.map(|sim_error| {
+            let decoded = decode_revert(revert_data);
+            let reason = match &decoded {
+                Some(decoded) => revert_reason(decoded),
+                None if !revert_data.is_empty() => undecoded_reason(revert_data),
+                None => sim_error.message.clone(),
+            };
+
+            CallError {
+                reason,
+                error_type: match sim_error.code {
+                    3 | -32000 => "execution-reverted".to_string(),
+                    -32015 => "vm-execution-error".to_string(),
+                    _ => "unknown-error".to_string(),
+                },
+                message: Some(sim_error.message),
+                contract_address: None,
+                data: (!revert_data.is_empty()).then(|| format!("0x{}", hex::encode(revert_data))),
+                decoded,
+            }
         });
 
-        Self { call_index: index as u32, status, return_data, gas_used, logs, error }
+        Self {
+            call_index: index as u32,
+            status,
+            return_data,
+            gas_used,
+            gas_breakdown: None,
+            logs,
+            decoded: None,
+            native_transfers: Vec::new(),
+            error,
+            trace: None,
+        }
     }
 }
 
-/// Helper function to extract clean revert reason from error message
-fn extract_revert_reason(message: &str) -> String {
-    // Common patterns in revert messages
-    if let Some(start) = message.find("execution reverted: ") {
-        message[start + 20..].to_string()
-    } else if let Some(start) = message.find("revert ") {
-        message[start + 7..].to_string()
-    } else if message.contains("out of gas") {
-        "Out of gas".to_string()
-    } else if message.contains("insufficient funds") {
-        "Insufficient funds".to_string()
-    } else {
-        message.to_string()
+impl CallResult {
+    /// Decodes the calldata, return data, logs and revert data of this call with
+    /// the supplied ABIs. Logs matching a supplied ABI take precedence over the
+    /// built-in event decoder.
+    pub fn apply_abis(&mut self, registry: &AbiRegistry<'_>, call: &AlloyTransactionRequest) {
+        let to = call.to.and_then(|kind| kind.to().copied());
+        let return_data = hex::decode(&self.return_data).unwrap_or_default();
+
+        if let Some(to) = to {
+            let input = call
+                .input
+                .input()
+                .map(|input| input.as_ref())
+                .unwrap_or_default();
+            let output = matches!(self.status, CallStatus::Success).then_some(&return_data[..]);
+            self.decoded = registry.decode_call(to, input, output);
+        }
+
+        // Built-in errors are already decoded, only custom errors need the ABIs
+        if let Some(error) = self.error.as_mut().filter(|error| error.decoded.is_none()) {
+            if let Some(decoded) = registry.decode_error(to, &return_data) {
+                error.reason = revert_reason(&decoded);
+                error.decoded = Some(decoded);
+            }
+        }
+
+        for log in &mut self.logs {
+            log.apply_abis(registry);
+        }
+    }
+}
+
+impl EnhancedLog {
+    /// Decodes this log with the supplied ABI of the emitting contract, if any.
+    pub fn apply_abis(&mut self, registry: &AbiRegistry<'_>) {
+        let Ok(address) = Address::from_str(&self.address) else { return };
+        let topics: Vec<B256> = self
+            .topics
+            .iter()
+            .filter_map(|topic| B256::from_str(topic).ok())
+            .collect();
+        let data = hex::decode(&self.data).unwrap_or_default();
+
+        if let Some(decoded) = registry.decode_event(address, &topics, &data) {
+            self.decoded = Some(decoded);
+        }
+    }
+}
+
+/// Converts the top-level frame of a call tracer trace to our API [`CallResult`].
+///
+/// The logs of the whole call tree are reported in execution order, the node
+/// already drops the logs of reverted frames.
+impl From<AlloyCallFrame> for CallResult {
+    fn from(frame: AlloyCallFrame) -> Self {
+        let status = if frame.error.is_none() { CallStatus::Success } else { CallStatus::Reverted };
+        let output = frame.output.clone().unwrap_or_default();
+
+        let mut logs = Vec::new();
+        collect_frame_logs(&frame, &mut logs);
+        let logs = logs
+            .into_iter()
+            .enumerate()
+            .map(|(index, log)| {
+                let topics = log.topics.unwrap_or_default();
+                let data = log.data.unwrap_or_default();
+                EnhancedLog {
+                    address: format!("0x{:x}", log.address.unwrap_or_default()),
+                    block_hash: None,
+                    block_number: None,
+                    data: format!("0x{}", hex::encode(&data)),
+                    log_index: Some(format!("0x{:x}", index)),
+                    transaction_hash: None,
+                    transaction_index: None,
+                    topics: topics
+                        .iter()
+                        .map(|topic| format!("0x{:x}", topic))
+                        .collect(),
+                    removed: false,
+                    decoded: decode_event(&topics, &data),
+                }
+            })
+            .collect();
+
+        let error = frame.error.map(|message| {
+            let decoded = decode_revert(&output);
+            let reason = match &decoded {
+                Some(decoded) => revert_reason(decoded),
+                None if !output.is_empty() => undecoded_reason(&output),
+                None => frame.revert_reason.unwrap_or_else(|| message.clone()),
+            };
+
+            CallError {
+                reason,
+                error_type: if message == "execution reverted" {
+                    "execution-reverted".to_string()
+                } else {
+                    "vm-execution-error".to_string()
+                },
+                message: Some(message),
+                contract_address: None,
+                data: (!output.is_empty()).then(|| format!("0x{}", hex::encode(&output))),
+                decoded,
+            }
+        });
+
+        Self {
+            call_index: 0,
+            status,
+            return_data: format!("0x{}", hex::encode(&output)),
+            gas_used: format!("0x{:x}", frame.gas_used),
+            gas_breakdown: None,
+            logs,
+            decoded: None,
+            native_transfers: Vec::new(),
+            error,
+            trace: None,
+        }
+    }
+}
+
+/// Collects the logs of a call frame and its sub-calls in execution order.
+///
+/// The position of a log is the number of sub-calls made by the frame before it.
+fn collect_frame_logs(frame: &AlloyCallFrame, logs: &mut Vec<CallLogFrame>) {
+    let mut frame_logs = frame.logs.iter().peekable();
+    for (index, call) in frame.calls.iter().enumerate() {
+        while let Some(log) =
+            frame_logs.next_if(|log| log.position.unwrap_or_default() <= index as u64)
+        {
+            logs.push(log.clone());
+        }
+        collect_frame_logs(call, logs);
+    }
+    logs.extend(frame_logs.cloned());
+}
+
+impl ReplayRequest {
+    /// Builds the bundle replaying the mined transaction with the modifications of
+    /// this request, and the state overrides to apply before it.
+    ///
+    /// The nonce is dropped when the sender is replaced, as it belongs to the
+    /// original sender.
+    pub fn try_into_bundle(
+        self,
+        mut transaction: AlloyTransactionRequest,
+    ) -> Result<(AlloyBundle, Option<AlloyStateOverride>)> {
+        let modifications = AlloyTransactionRequest::try_from(TransactionCall {
+            from: self.from,
+            to: None,
+            data: self.data,
+            value: self.value,
+            gas: self.gas,
+            access_list: None,
+            nonce: None,
+            max_fee_per_gas: None,
+        })?;
+
+        if let Some(from) = modifications.from {
+            transaction.from = Some(from);
+            transaction.nonce = None;
+        }
+        if modifications.input.input().is_some() {
+            transaction.input = modifications.input;
+        }
+        transaction.value = modifications.value.or(transaction.value);
+        transaction.gas = modifications.gas.or(transaction.gas);
+
+        let bundle = AlloyBundle {
+            transactions: vec![transaction],
+            block_override: self
+                .block_overrides
+                .map(convert_block_overrides)
+                .transpose()?,
+        };
+        let state_overrides = self
+            .state_overrides
+            .map(convert_state_overrides)
+            .transpose()?;
+
+        Ok((bundle, state_overrides))
+    }
+}
+
+/// Origin of a transaction executed in a re-executed block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReplayEntry {
+    /// Original transaction, with its index in the canonical block.
+    Original(usize),
+    /// Injected call, with the index of the original transaction it is executed before.
+    Injected(u64),
+}
+
+impl BlockReplayRequest {
+    /// Builds the bundle re-executing the transactions of the block, without the
+    /// removed ones and with the injected calls before the transaction at their
+    /// index, along with the origin of every transaction of the bundle.
+    pub fn try_into_bundle(
+        self,
+        transactions: Vec<AlloyTransactionRequest>,
+    ) -> Result<(AlloyBundle, Vec<BlockReplayEntry>)> {
+        let count = transactions.len() as u64;
+        if let Some(index) = self.removals.iter().find(|&&index| index >= count) {
+            return Err(anyhow!("Cannot remove transaction {}, the block has {}", index, count));
+        }
+        if let Some(insertion) = self
+            .insertions
+            .iter()
+            .find(|insertion| insertion.index > count)
+        {
+            return Err(anyhow!(
+                "Cannot insert a call at {}, the block has {} transactions",
+                insertion.index,
+                count
+            ));
+        }
+
+        let mut insertions = self
+            .insertions
+            .into_iter()
+            .map(|insertion| {
+                Ok((insertion.index, AlloyTransactionRequest::try_from(insertion.call)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Calls injected at the same index keep their order
+        insertions.sort_by_key(|(index, _)| *index);
+        let mut insertions = insertions.into_iter().peekable();
+
+        let mut bundle_transactions = Vec::new();
+        let mut entries = Vec::new();
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            while let Some((at, call)) = insertions.next_if(|(at, _)| *at <= index as u64) {
+                bundle_transactions.push(call);
+                entries.push(BlockReplayEntry::Injected(at));
+            }
+            if !self.removals.contains(&(index as u64)) {
+                bundle_transactions.push(transaction);
+                entries.push(BlockReplayEntry::Original(index));
+            }
+        }
+        for (at, call) in insertions {
+            bundle_transactions.push(call);
+            entries.push(BlockReplayEntry::Injected(at));
+        }
+
+        Ok((AlloyBundle { transactions: bundle_transactions, block_override: None }, entries))
+    }
+}
+
+impl ReplayComparison {
+    /// Compares the replayed call against the receipt of the original transaction.
+    pub fn new(original: &TransactionReceipt, replay: &CallResult) -> Self {
+        let logs: Vec<&Log> = original.inner.logs().iter().map(|log| &log.inner).collect();
+        Self::from_outcome(original.status(), original.gas_used, &logs, replay)
+    }
+
+    /// Compares the replayed call against the status, gas used and logs of the
+    /// original transaction.
+    fn from_outcome(status: bool, gas_used: u64, logs: &[&Log], replay: &CallResult) -> Self {
+        let replay_gas_used =
+            i64::from_str_radix(replay.gas_used.trim_start_matches("0x"), 16).unwrap_or_default();
+        let logs_changed = logs.len() != replay.logs.len() ||
+            logs.iter().zip(&replay.logs).any(|(original, replayed)| {
+                format!("0x{:x}", original.address) != replayed.address ||
+                    format!("0x{}", hex::encode(&original.data.data)) != replayed.data ||
+                    original
+                        .topics()
+                        .iter()
+                        .map(|topic| format!("0x{:x}", topic))
+                        .ne(replayed.topics.iter().cloned())
+            });
+
+        Self {
+            status_changed: status != matches!(replay.status, CallStatus::Success),
+            gas_used_delta: replay_gas_used - gas_used as i64,
+            logs_count_delta: replay.logs.len() as i64 - logs.len() as i64,
+            logs_changed,
+        }
+    }
+
+    /// Returns true if the replay differs from the original transaction.
+    pub const fn has_changed(&self) -> bool {
+        self.status_changed || self.gas_used_delta != 0 || self.logs_changed
+    }
+}
+
+/// Gas estimation conversion: a block executing the transaction alone, with the
+/// overrides of the request. The gas limit is left to the estimator.
+impl TryFrom<GasEstimationRequest> for SimBlock {
+    type Error = anyhow::Error;
+
+    fn try_from(request: GasEstimationRequest) -> Result<Self> {
+        let call = TransactionCall {
+            to: request.to,
+            from: request.from,
+            data: request.data,
+            value: request.value,
+            gas: None,
+            access_list: None,
+            nonce: None,
+            max_fee_per_gas: None,
+        };
+
+        Ok(Self {
+            calls: vec![call.try_into()?],
+            block_overrides: request
+                .block_overrides
+                .map(convert_block_overrides)
+                .transpose()?,
+            state_overrides: request
+                .state_overrides
+                .map(convert_state_overrides)
+                .transpose()?,
+        })
     }
 }
 
@@ -292,44 +677,149 @@ impl TryFrom<BatchSimulationRequest> for Vec<SimulatePayload<AlloyTransactionReq
     }
 }
 
-/// Bundle simulation conversion
-impl TryFrom<BundleSimulationRequest> for Vec<SimulatePayload<AlloyTransactionRequest>> {
+/// Bundle simulation conversion.
+///
+/// The calls of every bundle transaction are flattened, in order, into a single
+/// simulated block so that each transaction executes on top of the state left by
+/// the previous ones. The transaction `account` and `gasLimit` apply to the calls
+/// that do not set their own `from` and `gas`. The transactions are validated
+/// (nonce, balance, fees), see [`select_bundle_transactions`] to leave some out.
+impl TryFrom<BundleSimulationRequest> for SimulatePayload<AlloyTransactionRequest> {
     type Error = anyhow::Error;
 
     fn try_from(bundle_request: BundleSimulationRequest) -> Result<Self> {
-        let mut payloads = Self::default();
-        let base_block_overrides = bundle_request.block_overrides;
-        let cumulative_state_overrides = bundle_request.state_overrides.unwrap_or_default();
-
+        let mut calls = Vec::new();
         for bundle_tx in bundle_request.bundle {
-            // Convert bundle transaction to simulation request
-            let simulation_request = SimulationRequest {
-                params: SimulationParams {
-                    calls: bundle_tx.calls,
-                    account: bundle_tx.account,
-                    block_number: bundle_request.block_number.clone(),
-                    block_tag: bundle_request.block_tag,
-                    validation: !bundle_tx.allow_failure,
-                    trace_asset_changes: bundle_request.trace_asset_changes,
-                    trace_transfers: bundle_request.trace_transfers,
-                },
-                options: Some(SimulationOptions {
-                    state_overrides: Some(cumulative_state_overrides.clone()),
-                    block_overrides: base_block_overrides.clone(),
-                }),
-            };
+            let from = bundle_tx
+                .account
+                .as_deref()
+                .map(Address::from_str)
+                .transpose()
+                .map_err(|e| anyhow!("Invalid bundle account: {}", e))?;
+            let gas = bundle_tx
+                .gas_limit
+                .as_deref()
+                .map(|gas| match gas.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => u64::from_str(gas),
+                })
+                .transpose()
+                .map_err(|e| anyhow!("Invalid bundle gas limit: {}", e))?;
+
+            for call in bundle_tx.calls {
+                let mut tx_request = AlloyTransactionRequest::try_from(call)?;
+                tx_request.from = tx_request.from.or(from);
+                tx_request.gas = tx_request.gas.or(gas);
+                calls.push(tx_request);
+            }
+        }
 
-            let payload: SimulatePayload<AlloyTransactionRequest> =
-                simulation_request.try_into()?;
-            payloads.push(payload);
+        let sim_block = SimBlock {
+            calls,
+            block_overrides: bundle_request
+                .block_overrides
+                .map(convert_block_overrides)
+                .transpose()?,
+            state_overrides: bundle_request
+                .state_overrides
+                .map(convert_state_overrides)
+                .transpose()?,
+        };
 
-            // Note: In a real implementation, we'd extract state changes from each
-            // simulation result and apply them to cumulative_state_overrides
-            // This requires running simulations sequentially and parsing results
-        }
+        Ok(Self {
+            block_state_calls: vec![sim_block],
+            trace_transfers: bundle_request.trace_transfers,
+            validation: true,
+            return_full_transactions: false,
+        })
+    }
+}
 
-        Ok(payloads)
+/// Restricts the payload of a bundle to the calls of its `included` transactions,
+/// given by index, so that the other transactions are not executed.
+pub fn select_bundle_transactions(
+    payload: &SimulatePayload<AlloyTransactionRequest>,
+    bundle: &[BundleTransaction],
+    included: &[usize],
+) -> SimulatePayload<AlloyTransactionRequest> {
+    let mut starts = Vec::with_capacity(bundle.len());
+    let mut start = 0;
+    for bundle_tx in bundle {
+        starts.push(start);
+        start += bundle_tx.calls.len();
     }
+
+    let mut payload = payload.clone();
+    if let Some(sim_block) = payload.block_state_calls.first_mut() {
+        sim_block.calls = included
+            .iter()
+            .flat_map(|&index| {
+                let calls = &sim_block.calls[starts[index]..][..bundle[index].calls.len()];
+                calls.to_vec()
+            })
+            .collect();
+    }
+    payload
+}
+
+/// Splits the flattened call results of a bundle back into its transactions.
+///
+/// A transaction reverts if any of its calls reverted. The first reverted
+/// transaction that is not allowed to fail halts the bundle: its results are
+/// kept, and the following transactions are reported as skipped. Returns the
+/// transaction results and the index of the halting transaction, if any.
+pub fn group_bundle_results(
+    bundle: &[BundleTransaction],
+    call_results: Vec<CallResult>,
+) -> (Vec<BundleTransactionResult>, Option<u32>) {
+    let mut call_results = call_results.into_iter();
+    let mut halted_at = None;
+
+    let transactions = bundle
+        .iter()
+        .enumerate()
+        .map(|(index, bundle_tx)| {
+            let calls: Vec<CallResult> =
+                call_results.by_ref().take(bundle_tx.calls.len()).collect();
+            if halted_at.is_some() {
+                return BundleTransactionResult {
+                    transaction_index: index as u32,
+                    status: BundleTransactionStatus::Skipped,
+                    allow_failure: bundle_tx.allow_failure,
+                    gas_used: "0x0".to_string(),
+                    calls: Vec::new(),
+                };
+            }
+
+            let reverted = calls
+                .iter()
+                .any(|call| matches!(call.status, CallStatus::Reverted));
+            if reverted && !bundle_tx.allow_failure {
+                halted_at = Some(index as u32);
+            }
+
+            let gas_used: u64 = calls
+                .iter()
+                .map(|call| {
+                    u64::from_str_radix(call.gas_used.trim_start_matches("0x"), 16).unwrap_or(0)
+                })
+                .sum();
+
+            BundleTransactionResult {
+                transaction_index: index as u32,
+                status: if reverted {
+                    BundleTransactionStatus::Reverted
+                } else {
+                    BundleTransactionStatus::Success
+                },
+                allow_failure: bundle_tx.allow_failure,
+                gas_used: format!("0x{:x}", gas_used),
+                calls,
+            }
+        })
+        .collect();
+
+    (transactions, halted_at)
 }
 
 #[cfg(test)]
@@ -349,6 +839,8 @@ mod tests {
             value: Some("0x1000".to_string()),
             gas: Some("0x5208".to_string()),
             access_list: None,
+            nonce: None,
+            max_fee_per_gas: None,
         };
 
         let alloy_request: AlloyTransactionRequest = api_call.try_into().unwrap();
@@ -371,6 +863,8 @@ mod tests {
                     value: None,
                     gas: None,
                     access_list: None,
+                    nonce: None,
+                    max_fee_per_gas: None,
                 }],
                 account: Some("0x123d35Cc6634C0532925a3b844Bc9e7595f06e8c".to_string()),
                 block_number: Some("0x123abc".to_string()),
@@ -407,6 +901,8 @@ mod tests {
             storage: Some(HashMap::new()),
             state_diff: None,
             move_precompile_to_address: None,
+            storage_layout: None,
+            storage_variables: None,
         }];
 
         let alloy_overrides = convert_state_overrides(api_overrides).unwrap();
@@ -486,4 +982,330 @@ mod tests {
         let override_entry = state_overrides.get(&address).unwrap();
         assert!(override_entry.balance.is_some());
     }
+
+    fn bundle_tx(calls: usize, allow_failure: bool) -> BundleTransaction {
+        BundleTransaction {
+            calls: vec![
+                TransactionCall {
+                    to: Some("0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c".to_string()),
+                    from: None,
+                    data: None,
+                    value: None,
+                    gas: None,
+                    access_list: None,
+                    nonce: None,
+                    max_fee_per_gas: None,
+                };
+                calls
+            ],
+            account: Some("0x123d35Cc6634C0532925a3b844Bc9e7595f06e8c".to_string()),
+            gas_limit: Some("0x30000".to_string()),
+            allow_failure,
+        }
+    }
+
+    fn call_result(index: u32, success: bool) -> CallResult {
+        CallResult {
+            call_index: index,
+            status: if success { CallStatus::Success } else { CallStatus::Reverted },
+            return_data: "0x".to_string(),
+            gas_used: "0x5208".to_string(),
+            gas_breakdown: None,
+            logs: vec![],
+            decoded: None,
+            native_transfers: vec![],
+            error: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_bundle_conversion_flattens_calls() {
+        let request = BundleSimulationRequest {
+            bundle: vec![bundle_tx(1, false), bundle_tx(2, false)],
+            block_number: None,
+            block_tag: None,
+            state_overrides: None,
+            block_overrides: None,
+            trace_asset_changes: false,
+            trace_transfers: true,
+            enable_profiling: false,
+        };
+
+        let payload: SimulatePayload<AlloyTransactionRequest> = request.try_into().unwrap();
+
+        assert_eq!(payload.block_state_calls.len(), 1);
+        assert!(payload.validation);
+        assert!(payload.trace_transfers);
+
+        let calls = &payload.block_state_calls[0].calls;
+        assert_eq!(calls.len(), 3);
+        assert!(calls.iter().all(|call| call.gas == Some(0x30000)));
+        assert!(calls.iter().all(|call| call.from.is_some()));
+    }
+
+    #[test]
+    fn test_select_bundle_transactions() {
+        let bundle = vec![bundle_tx(1, false), bundle_tx(2, true), bundle_tx(1, false)];
+        let request = BundleSimulationRequest {
+            bundle: bundle.clone(),
+            block_number: None,
+            block_tag: None,
+            state_overrides: None,
+            block_overrides: None,
+            trace_asset_changes: false,
+            trace_transfers: false,
+            enable_profiling: false,
+        };
+        let mut payload: SimulatePayload<AlloyTransactionRequest> = request.try_into().unwrap();
+        assert!(payload.validation);
+        for (index, call) in payload.block_state_calls[0].calls.iter_mut().enumerate() {
+            call.nonce = Some(index as u64);
+        }
+
+        let selected = select_bundle_transactions(&payload, &bundle, &[0, 2]);
+        let nonces: Vec<_> = selected.block_state_calls[0]
+            .calls
+            .iter()
+            .map(|call| call.nonce)
+            .collect();
+        assert_eq!(nonces, vec![Some(0), Some(3)]);
+
+        let selected = select_bundle_transactions(&payload, &bundle, &[1]);
+        assert_eq!(selected.block_state_calls[0].calls.len(), 2);
+        assert!(select_bundle_transactions(&payload, &bundle, &[]).block_state_calls[0]
+            .calls
+            .is_empty());
+    }
+
+    #[test]
+    fn test_group_bundle_results_halts_on_failure() {
+        let bundle = vec![bundle_tx(1, true), bundle_tx(2, false), bundle_tx(1, false)];
+        let results = vec![
+            call_result(0, false),
+            call_result(1, true),
+            call_result(2, false),
+            call_result(3, true),
+        ];
+
+        let (transactions, halted_at) = group_bundle_results(&bundle, results);
+
+        assert_eq!(halted_at, Some(1));
+        let statuses: Vec<_> = transactions.iter().map(|tx| tx.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BundleTransactionStatus::Reverted,
+                BundleTransactionStatus::Reverted,
+                BundleTransactionStatus::Skipped
+            ]
+        );
+        assert_eq!(transactions[1].calls.len(), 2);
+        assert_eq!(transactions[1].gas_used, "0xa410");
+        assert!(transactions[2].calls.is_empty());
+    }
+
+    #[test]
+    fn test_call_result_error_types() {
+        let error_type = |code: i32| {
+            let result = SimCallResult {
+                return_data: Bytes::new(),
+                logs: vec![],
+                gas_used: 21_000,
+                status: false,
+                error: Some(alloy_rpc_types_eth::simulate::SimulateError {
+                    code,
+                    message: "execution reverted".to_string(),
+                }),
+            };
+            CallResult::from((0, result)).error.unwrap().error_type
+        };
+
+        assert_eq!(error_type(3), "execution-reverted");
+        assert_eq!(error_type(-32000), "execution-reverted");
+        assert_eq!(error_type(-32015), "vm-execution-error");
+        assert_eq!(error_type(-3200), "unknown-error");
+    }
+
+    #[test]
+    fn test_multi_block_conversion() {
+        let json = r#"{
+            "blocks": [
+                {"calls": [{"to": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c"}]},
+                {"calls": [], "blockOverrides": {"time": 1700000000}},
+                {
+                    "calls": [{"to": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c"}],
+                    "stateOverrides": [{
+                        "address": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+                        "balance": "0x1"
+                    }]
+                }
+            ],
+            "validation": false
+        }"#;
+
+        let request: MultiBlockSimulationRequest = serde_json::from_str(json).unwrap();
+        let payload: SimulatePayload<AlloyTransactionRequest> = request.try_into().unwrap();
+
+        assert_eq!(payload.block_state_calls.len(), 3);
+        assert!(!payload.validation);
+        assert!(payload.block_state_calls[1].calls.is_empty());
+        assert_eq!(
+            payload.block_state_calls[1]
+                .block_overrides
+                .as_ref()
+                .and_then(|overrides| overrides.time),
+            Some(1_700_000_000)
+        );
+        assert!(payload.block_state_calls[2].state_overrides.is_some());
+    }
+
+    #[test]
+    fn test_replay_bundle_applies_modifications() {
+        let request: ReplayRequest = serde_json::from_str(
+            r#"{
+                "transactionHash": "0xbc4a51bbcbe7550446c151d0d53ee14d5318188e2af1726e28a481b075fc7b4c",
+                "from": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+                "gas": "0x30d40",
+                "stateOverrides": [{
+                    "address": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+                    "balance": "0x1"
+                }]
+            }"#,
+        )
+        .unwrap();
+        let transaction = AlloyTransactionRequest {
+            nonce: Some(7),
+            gas: Some(21_000),
+            value: Some(U256::from(1)),
+            input: TransactionInput::new(Bytes::from_static(&[0xa9, 0x05, 0x9c, 0xbb])),
+            ..Default::default()
+        };
+
+        let (bundle, state_overrides) = request.try_into_bundle(transaction).unwrap();
+
+        let replayed = &bundle.transactions[0];
+        assert!(replayed.from.is_some());
+        assert_eq!(replayed.nonce, None);
+        assert_eq!(replayed.gas, Some(200_000));
+        assert_eq!(replayed.value, Some(U256::from(1)));
+        assert_eq!(replayed.input.input().map(|input| input.len()), Some(4));
+        assert!(bundle.block_override.is_none());
+        assert_eq!(state_overrides.map(|overrides| overrides.len()), Some(1));
+    }
+
+    #[test]
+    fn test_call_frame_logs_in_execution_order() {
+        let log = |tag: u8, position: u64| CallLogFrame {
+            address: Some(Address::repeat_byte(tag)),
+            topics: Some(vec![]),
+            data: None,
+            position: Some(position),
+        };
+        let frame = AlloyCallFrame {
+            calls: vec![
+                AlloyCallFrame { logs: vec![log(2, 0)], ..Default::default() },
+                AlloyCallFrame { logs: vec![log(4, 0)], ..Default::default() },
+            ],
+            logs: vec![log(1, 0), log(3, 1), log(5, 2)],
+            gas_used: U256::from(0x5208),
+            ..Default::default()
+        };
+
+        let result = CallResult::from(frame);
+
+        assert!(matches!(result.status, CallStatus::Success));
+        assert_eq!(result.gas_used, "0x5208");
+        let emitters: Vec<_> = result
+            .logs
+            .iter()
+            .map(|log| log.address[2..4].to_string())
+            .collect();
+        assert_eq!(emitters, vec!["01", "02", "03", "04", "05"]);
+        assert_eq!(result.logs[4].log_index.as_deref(), Some("0x4"));
+    }
+
+    #[test]
+    fn test_block_replay_bundle_orders_transactions() {
+        let request: BlockReplayRequest = serde_json::from_str(
+            r#"{
+                "blockNumber": "0x10",
+                "insertions": [
+                    {"index": 3, "call": {"to": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c"}},
+                    {"index": 1, "call": {"to": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c"}},
+                    {"index": 1, "call": {}}
+                ],
+                "removals": [1]
+            }"#,
+        )
+        .unwrap();
+        let transactions = vec![AlloyTransactionRequest::default(); 3];
+
+        let (bundle, entries) = request.try_into_bundle(transactions).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                BlockReplayEntry::Original(0),
+                BlockReplayEntry::Injected(1),
+                BlockReplayEntry::Injected(1),
+                BlockReplayEntry::Original(2),
+                BlockReplayEntry::Injected(3),
+            ]
+        );
+        assert_eq!(bundle.transactions.len(), 5);
+        assert!(bundle.transactions[1].to.is_some());
+        assert!(bundle.transactions[2].to.is_none());
+    }
+
+    #[test]
+    fn test_block_replay_bundle_rejects_out_of_range_indices() {
+        let request: BlockReplayRequest =
+            serde_json::from_str(r#"{"blockNumber": "16", "removals": [2]}"#).unwrap();
+        assert!(request
+            .try_into_bundle(vec![AlloyTransactionRequest::default(); 2])
+            .is_err());
+
+        let request: BlockReplayRequest = serde_json::from_str(
+            r#"{"blockNumber": "16", "insertions": [{"index": 3, "call": {}}]}"#,
+        )
+        .unwrap();
+        assert!(request
+            .try_into_bundle(vec![AlloyTransactionRequest::default(); 2])
+            .is_err());
+    }
+
+    #[test]
+    fn test_replay_comparison() {
+        let log = Log::new_unchecked(Address::repeat_byte(1), vec![B256::ZERO], Bytes::new());
+        let mut replay = call_result(0, true);
+        replay.gas_used = "0x5000".to_string();
+
+        let comparison = ReplayComparison::from_outcome(false, 0x5208, &[&log, &log], &replay);
+
+        assert_eq!(
+            comparison,
+            ReplayComparison {
+                status_changed: true,
+                gas_used_delta: -0x208,
+                logs_count_delta: -2,
+                logs_changed: true
+            }
+        );
+        assert!(comparison.has_changed());
+
+        replay.gas_used = "0x5208".to_string();
+        replay.logs = CallResult::from(AlloyCallFrame {
+            logs: vec![CallLogFrame {
+                address: Some(log.address),
+                topics: Some(log.topics().to_vec()),
+                data: None,
+                position: None,
+            }],
+            ..Default::default()
+        })
+        .logs;
+
+        assert!(!ReplayComparison::from_outcome(true, 0x5208, &[&log], &replay).has_changed());
+    }
 }
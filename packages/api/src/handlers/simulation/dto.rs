@@ -5,10 +5,14 @@ use utoipa::ToSchema;
 use validator::Validate;
 
 use crate::{
-    handlers::validation::{
-        validate_address, validate_block_number_or_tag, validate_hex_string, validate_uint256,
+    handlers::{
+        trace::TraceConfig,
+        validation::{
+            validate_abi_addresses, validate_address, validate_block_number_or_tag, validate_hash,
+            validate_hex_or_decimal, validate_hex_string, validate_uint256,
+        },
     },
-    types::{shared::StateOverride, BlockOverrides, TransactionCall},
+    types::{shared::StateOverride, BlockOverrides, ContractAbis, TransactionCall},
     utils::{default_latest, default_true},
 };
 
@@ -34,6 +38,44 @@ impl SimulationRequest {
     pub const fn call_count(&self) -> usize {
         self.params.calls.len()
     }
+
+    /// Names of the tracers the calls are run with, see
+    /// [`SimulationOptions::tracer_names`].
+    pub fn tracer_names(&self) -> Vec<&'static str> {
+        self.options
+            .as_ref()
+            .map(SimulationOptions::tracer_names)
+            .unwrap_or_default()
+    }
+
+    /// Moves the simulation to another parent block, the latest if neither
+    /// `block_number` nor `block_tag` is set.
+    pub fn at_block(mut self, block_number: Option<String>, block_tag: Option<BlockTag>) -> Self {
+        self.params.block_number = block_number;
+        self.params.block_tag = block_tag;
+        self
+    }
+}
+
+/// Request to re-execute a stored simulation at another block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({
+    "blockTag": "latest"
+}))]
+pub struct RerunRequest {
+    /// Block number to re-execute the simulation against (hex encoded).
+    /// Mutually exclusive with `block_tag`. Defaults to the latest block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_block_number_or_tag"))]
+    #[schema(example = "0x123abc", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: Option<String>,
+
+    /// Block tag to re-execute the simulation against.
+    /// Mutually exclusive with `block_number`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "latest")]
+    pub block_tag: Option<BlockTag>,
 }
 
 /// Batch simulation request for multiple independent transactions.
@@ -50,6 +92,102 @@ pub struct BatchSimulationRequest {
     pub common_options: Option<SimulationOptions>,
 }
 
+/// Simulation request spanning several consecutive blocks.
+///
+/// Each block is simulated on top of the previous one, with its own calls and
+/// overrides. Useful to test time-dependent logic (vesting, TWAPs, auctions).
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiBlockSimulationRequest {
+    /// Blocks to simulate, in order.
+    #[validate(length(min = 1, max = 16, message = "Simulation must contain 1-16 blocks"))]
+    #[validate(nested)]
+    pub blocks: Vec<SimulationBlock>,
+
+    /// Optional account address for tracking asset changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_address"))]
+    #[schema(
+        example = "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub account: Option<String>,
+
+    /// Block number to simulate against (hex encoded).
+    /// This will be the PARENT block of the first simulated block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_block_number_or_tag"))]
+    #[schema(example = "0x123abc", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: Option<String>,
+
+    /// Block tag to use as the parent of the first simulated block.
+    /// Mutually exclusive with `block_number`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "latest")]
+    pub block_tag: Option<BlockTag>,
+
+    /// Enable or disable EVM validation during simulation.
+    #[serde(default = "default_true")]
+    #[schema(example = true)]
+    pub validation: bool,
+
+    /// Enable tracking of ERC-20 token balance changes for `account` across all blocks.
+    #[serde(default)]
+    pub trace_asset_changes: bool,
+
+    /// Enable tracking of native value transfers.
+    #[serde(default)]
+    pub trace_transfers: bool,
+
+    /// JSON ABIs keyed by contract address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
+
+    /// Break the gas used by each call of every block down by category.
+    /// Only the first block can override the state when enabled.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub gas_breakdown: bool,
+}
+
+impl MultiBlockSimulationRequest {
+    /// Total number of transaction calls across the blocks.
+    pub fn call_count(&self) -> usize {
+        self.blocks.iter().map(|block| block.calls.len()).sum()
+    }
+
+    /// Names of the tracers the calls are run with: the struct logger for the gas
+    /// breakdown.
+    pub fn tracer_names(&self) -> Vec<&'static str> {
+        if self.gas_breakdown {
+            vec!["structLogger"]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// A single block of a multi-block simulation.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationBlock {
+    /// Transaction calls executed in this block.
+    #[validate(nested)]
+    pub calls: Vec<TransactionCall>,
+
+    /// State overrides applied before this block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub state_overrides: Option<Vec<StateOverride>>,
+
+    /// Block environment overrides for this block (number, time, base fee, ...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub block_overrides: Option<BlockOverrides>,
+}
+
 /// Access list request. This will return the different account and slots that are accessed by the
 /// transaction.
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
@@ -113,6 +251,13 @@ pub struct BundleSimulationRequest {
     pub enable_profiling: bool,
 }
 
+impl BundleSimulationRequest {
+    /// Total number of transaction calls across the bundle.
+    pub fn call_count(&self) -> usize {
+        self.bundle.iter().map(|tx| tx.calls.len()).sum()
+    }
+}
+
 /// Individual transaction within a bundle.
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -161,6 +306,29 @@ pub struct GasEstimationRequest {
     #[validate(custom(function = "validate_uint256"))]
     pub value: Option<String>,
 
+    /// Block number to estimate against (hex encoded).
+    /// Mutually exclusive with `block_tag`. Defaults to the latest block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_block_number_or_tag"))]
+    #[schema(example = "0x123abc", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: Option<String>,
+
+    /// Block tag to estimate against.
+    /// Mutually exclusive with `block_number`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "latest")]
+    pub block_tag: Option<BlockTag>,
+
+    /// State overrides applied before the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub state_overrides: Option<Vec<StateOverride>>,
+
+    /// Block parameter overrides.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub block_overrides: Option<BlockOverrides>,
+
     /// Optimization level for gas estimation.
     #[serde(default)]
     pub optimization_level: OptimizationLevel,
@@ -183,6 +351,123 @@ pub enum OptimizationLevel {
     Aggressive,
 }
 
+/// Request to replay a mined transaction at its position in its block.
+///
+/// The transaction is re-executed on the state right before it, with the
+/// optional modifications applied, and compared against its receipt.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({
+    "transactionHash": "0xbc4a51bbcbe7550446c151d0d53ee14d5318188e2af1726e28a481b075fc7b4c",
+    "gas": "0x30d40",
+    "stateOverrides": [{
+        "address": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+        "balance": "0xde0b6b3a7640000"
+    }]
+}))]
+pub struct ReplayRequest {
+    /// Hash of the transaction to replay.
+    #[validate(custom(function = validate_hash, message = "Invalid transaction hash"))]
+    #[schema(
+        example = "0xbc4a51bbcbe7550446c151d0d53ee14d5318188e2af1726e28a481b075fc7b4c",
+        pattern = "0x[0-9a-fA-F]{64}"
+    )]
+    pub transaction_hash: String,
+
+    /// Sender replacing the original one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_address"))]
+    pub from: Option<String>,
+
+    /// Calldata replacing the original one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_hex_string"))]
+    pub data: Option<String>,
+
+    /// Value replacing the original one (hex encoded wei).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_uint256"))]
+    pub value: Option<String>,
+
+    /// Gas limit replacing the original one (hex encoded).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_uint256"))]
+    pub gas: Option<String>,
+
+    /// State overrides applied before the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub state_overrides: Option<Vec<StateOverride>>,
+
+    /// Overrides of the block the transaction is replayed in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub block_overrides: Option<BlockOverrides>,
+
+    /// JSON ABIs keyed by contract address.
+    /// Used to decode calldata, return data, events and custom errors of these contracts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
+}
+
+/// Request to re-execute the transactions of a historical block in order.
+///
+/// Calls can be injected between the transactions and transactions can be
+/// dropped, to see how the other transactions of the block would have behaved.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({
+    "blockNumber": "0x123abc",
+    "insertions": [{
+        "index": 2,
+        "call": {
+            "from": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+            "to": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            "data": "0xa9059cbb"
+        }
+    }],
+    "removals": [5]
+}))]
+pub struct BlockReplayRequest {
+    /// Number of the block to re-execute (hex encoded or decimal).
+    #[validate(custom(function = "validate_hex_or_decimal"))]
+    #[schema(example = "0x123abc")]
+    pub block_number: String,
+
+    /// Calls to inject between the transactions of the block.
+    #[serde(default)]
+    #[validate(nested)]
+    pub insertions: Vec<TransactionInsertion>,
+
+    /// Indices of the transactions of the block to drop.
+    #[serde(default)]
+    #[schema(example = json!([5]))]
+    pub removals: Vec<u64>,
+
+    /// JSON ABIs keyed by contract address.
+    /// Used to decode calldata, return data, events and custom errors of these contracts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
+}
+
+/// Call injected into a re-executed block.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInsertion {
+    /// Index of the original transaction the call is executed before.
+    /// Use the number of transactions of the block to append the call.
+    #[schema(example = 2)]
+    pub index: u64,
+
+    /// Call to inject.
+    #[validate(nested)]
+    pub call: TransactionCall,
+}
+
 /// Core simulation parameters including transaction calls and context.
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -222,19 +507,17 @@ pub struct SimulationParams {
     #[schema(example = true)]
     pub validation: bool,
 
-    /// Enable tracking of ERC-20/ERC-721 token balance changes.
-    /// Requires `account` parameter to be set.
-    ///
-    /// NOTE: This is currently not supported
-    ///
-    /// TODO: Viem logic <https://github.com/wevm/viem/blob/100156844c85989bb8c26ca587da7a62a282136b/src/actions/public/simulateCalls.ts#L166>
+    /// Enable tracking of ERC-20 token balance changes for `account`.
+    /// Tokens are discovered from `Transfer` logs and their balances are read
+    /// before and after the calls. Requires `account` parameter to be set.
     #[serde(default = "default_true")]
     #[schema(example = false)]
     pub trace_asset_changes: bool,
 
-    /// Enable tracking of ETH transfers as ERC-20-like logs.
-    /// ETH transfers will appear with address 0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee.
-    /// Requires `account` parameter to be set.
+    /// Enable tracking of native value transfers.
+    /// Transfers are reported per call in `nativeTransfers` (separately from the logs),
+    /// and the resulting native balance changes, including gas fees, in
+    /// `nativeBalanceChanges`. Requires `account` parameter to be set.
     #[serde(default = "default_true")]
     #[schema(example = false)]
     pub trace_transfers: bool,
@@ -255,6 +538,122 @@ pub struct SimulationOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[validate(nested)]
     pub block_overrides: Option<BlockOverrides>,
+
+    /// JSON ABIs keyed by contract address.
+    /// Used to decode calldata, return data, events and custom errors of these contracts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
+
+    /// Break the gas used by each call down by category.
+    /// The calls are traced with the struct logger, which makes the simulation slower.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub gas_breakdown: bool,
+
+    /// Trace the calls and attach the traces to their results.
+    /// Only reverted calls are traced unless `traceCalls` is `all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub tracers: Option<SimulationTraceConfig>,
+
+    /// ERC-20 balance and allowance overrides.
+    /// The storage slots of the token are found automatically and written to the state
+    /// overrides.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub token_overrides: Option<Vec<TokenOverride>>,
+
+    /// Report the state changes caused by the calls.
+    /// The calls are traced with the prestate tracer in diff mode, which makes the
+    /// simulation slower.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub state_diff: bool,
+}
+
+impl SimulationOptions {
+    /// Names of the tracers the calls are run with: the requested tracers, the
+    /// struct logger for the gas breakdown and the prestate tracer for the state
+    /// diff.
+    pub fn tracer_names(&self) -> Vec<&'static str> {
+        let mut names = self
+            .tracers
+            .as_ref()
+            .map(|config| config.tracer_config.tracers.names())
+            .unwrap_or_default();
+        if self.gas_breakdown {
+            names.push("structLogger");
+        }
+        if self.state_diff {
+            names.push("prestateTracer");
+        }
+        names
+    }
+}
+
+/// ERC-20 balance and/or allowance override of a holder.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenOverride {
+    /// Token contract address.
+    #[validate(custom(function = "validate_address"))]
+    #[schema(example = "0xA0b86a33E6441e6e80D0c4C6C7527d72e1d00000")]
+    pub token: String,
+
+    /// Account whose balance and allowance are overridden.
+    #[validate(custom(function = "validate_address"))]
+    #[schema(example = "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c")]
+    pub holder: String,
+
+    /// Token balance of the holder (decimal or hex encoded, in token units).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_uint256"))]
+    #[schema(example = "1000000000000000000")]
+    pub balance: Option<String>,
+
+    /// Spender whose allowance from the holder is overridden.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_address"))]
+    #[schema(example = "0x1111111111111111111111111111111111111111")]
+    pub spender: Option<String>,
+
+    /// Allowance of the spender (decimal or hex encoded, in token units).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_uint256"))]
+    #[schema(example = "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff")]
+    pub allowance: Option<String>,
+}
+
+/// Tracers run on the simulated calls.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({
+    "callTracer": {"onlyTopCall": false, "withLogs": true},
+    "prestateTracer": {"diffMode": true},
+    "traceCalls": "reverted"
+}))]
+pub struct SimulationTraceConfig {
+    /// Tracers to run, the call tracer by default.
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub tracer_config: TraceConfig,
+
+    /// Calls to attach traces to.
+    #[serde(default)]
+    pub trace_calls: TracedCalls,
+}
+
+/// Calls whose traces are attached to the simulation result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TracedCalls {
+    /// Only the reverted calls.
+    #[default]
+    Reverted,
+    /// All the calls.
+    All,
 }
 
 /// Block tag options for specifying block context.
@@ -311,6 +710,8 @@ mod tests {
             state: Some(vec![]),
             state_diff: Some(HashMap::new()),
             move_precompile_to_address: None,
+            storage_layout: None,
+            storage_variables: None,
         };
 
         assert!(override_state.validate_state_exclusivity().is_err());
@@ -327,4 +728,63 @@ mod tests {
         assert_eq!(params.trace_asset_changes, true);
         assert_eq!(params.trace_transfers, true);
     }
+
+    #[test]
+    fn test_serde_simulation_trace_config() {
+        let s = r#"{"prestateTracer": {"diffMode": true}}"#;
+        let config: SimulationTraceConfig = serde_json::from_str(s).unwrap();
+        assert_eq!(config.trace_calls, TracedCalls::Reverted);
+        assert!(config.tracer_config.tracers.prestate_tracer.is_some());
+        assert!(config.tracer_config.tracers.call_tracer.is_some());
+
+        let s = r#"{"traceCalls": "all"}"#;
+        let config: SimulationTraceConfig = serde_json::from_str(s).unwrap();
+        assert_eq!(config.trace_calls, TracedCalls::All);
+        assert_eq!(config.tracer_config, TraceConfig::default());
+    }
+
+    #[test]
+    fn test_simulation_tracer_names() {
+        let s = r#"{"params": {"calls": [{}, {}]}}"#;
+        let request: SimulationRequest = serde_json::from_str(s).unwrap();
+        assert_eq!(request.call_count(), 2);
+        assert!(request.tracer_names().is_empty());
+
+        // The gas breakdown and the state diff run tracers of their own
+        let s = r#"{
+            "params": {"calls": [{}]},
+            "options": {"tracers": {"4byteTracer": true}, "gasBreakdown": true, "stateDiff": true}
+        }"#;
+        let request: SimulationRequest = serde_json::from_str(s).unwrap();
+        assert_eq!(
+            request.tracer_names(),
+            vec!["4byteTracer", "callTracer", "structLogger", "prestateTracer"]
+        );
+    }
+
+    #[test]
+    fn test_stored_request_round_trip() {
+        let s = r#"{
+            "params": {
+                "calls": [{"to": "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c", "data": "0x"}],
+                "blockNumber": "0x10",
+                "validation": false
+            },
+            "options": {"stateDiff": true}
+        }"#;
+        let request: SimulationRequest = serde_json::from_str(s).unwrap();
+        let stored: SimulationRequest =
+            serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap();
+
+        assert_eq!(stored.params.calls[0].to, request.params.calls[0].to);
+        assert_eq!(stored.params.validation, false);
+        assert!(stored
+            .options
+            .as_ref()
+            .is_some_and(|options| options.state_diff));
+
+        let rerun = stored.at_block(None, Some(BlockTag::Latest));
+        assert_eq!(rerun.params.block_number, None);
+        assert!(matches!(rerun.params.block_tag, Some(BlockTag::Latest)));
+    }
 }
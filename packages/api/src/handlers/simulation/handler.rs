@@ -1,11 +1,16 @@
 use crate::{
+    config::CacheConfig,
     define_routes,
-    error::{ApiError, ApiResult},
+    error::{ApiError, ApiResult, ServiceError},
     handlers::{
-        common::{ApiResponse, Handler},
+        common::{cached, ApiResponse, Handler},
         simulation::{dto::*, response::*},
     },
-    services::hyperevm::service::HyperEvmService,
+    services::{
+        api_keys::ApiKey, hyperevm::service::HyperEvmService, request_key, telemetry::request_id,
+        usage, Cache, CacheStatus,
+    },
+    utils::validation::parse_block_number,
 };
 use actix_web::{web, HttpResponse};
 use std::time::Instant;
@@ -18,7 +23,14 @@ use uuid::Uuid;
     paths(
         simulate_transaction,
         simulate_batch_transaction,
-        create_access_list
+        simulate_multi_block,
+        simulate_bundle,
+        estimate_gas,
+        replay_transaction,
+        replay_block,
+        create_access_list,
+        get_simulation,
+        rerun_simulation
     ),
     components(
         schemas(
@@ -27,8 +39,26 @@ use uuid::Uuid;
             AccessListRequest,
             ApiResponse<SimulationResult>,
             ApiResponse<Vec<SimulationResult>>,
+            MultiBlockSimulationRequest,
+            MultiBlockSimulationResult,
+            ApiResponse<MultiBlockSimulationResult>,
+            BundleSimulationRequest,
+            BundleSimulationResult,
+            ApiResponse<BundleSimulationResult>,
+            GasEstimationRequest,
+            GasEstimationResult,
+            ApiResponse<GasEstimationResult>,
+            ReplayRequest,
+            ReplayResult,
+            ApiResponse<ReplayResult>,
+            BlockReplayRequest,
+            BlockReplayResult,
+            ApiResponse<BlockReplayResult>,
             AccessListResponse,
             ApiResponse<AccessListResponse>,
+            StoredSimulation,
+            ApiResponse<StoredSimulation>,
+            RerunRequest,
         ),
     ),
     tags(
@@ -39,11 +69,21 @@ pub struct SimulationApiDoc;
 
 pub struct SimulationHandler {
     service: web::Data<HyperEvmService>,
+    cache: Option<Cache>,
+    cache_config: CacheConfig,
 }
 
 impl SimulationHandler {
-    pub const fn new(service: web::Data<HyperEvmService>) -> Self {
-        Self { service }
+    pub fn new(service: web::Data<HyperEvmService>) -> Self {
+        Self { service, cache: None, cache_config: CacheConfig::default() }
+    }
+
+    /// Caches the simulations pinned to a finalized block, with the TTLs of the
+    /// configuration.
+    pub fn with_cache(mut self, cache: Cache, config: CacheConfig) -> Self {
+        self.cache = Some(cache);
+        self.cache_config = config;
+        self
     }
 
     pub fn into_app_data(self) -> web::Data<Self> {
@@ -65,19 +105,24 @@ impl From<SimulationHandler> for web::Data<SimulationHandler> {
     description = "Simulate a single transaction with comprehensive analysis",
     request_body = SimulationRequest,
     responses(
-        (status = 200, description = "Simulation completed (success or failure)", body = ApiResponse<SimulationResult>),
+        (status = 200, description = "Simulation completed (success or failure)", body = ApiResponse<SimulationResult>,
+            headers(("X-Cache" = String, description = "HIT, MISS or BYPASS: whether the result was served from the cache"))),
         (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     )
 )]
 async fn simulate_transaction(
     handler: web::Data<SimulationHandler>,
     request: web::Json<SimulationRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = Uuid::new_v4().to_string();
+    let request_id = request_id();
     let simulation_request = request.into_inner();
 
+    authorize_simulations(api_key.as_deref(), std::slice::from_ref(&simulation_request))?;
+
     debug!(
         target: "altitrace::api::simulation",
         request_id = %request_id,
@@ -86,12 +131,36 @@ async fn simulate_transaction(
         "Processing simulation request"
     );
 
-    match handler
-        .service
-        .simulate_transaction(simulation_request)
-        .await
-    {
-        Ok(result) => {
+    // Only simulations pinned to a block number are looked up
+    let pinned_block = simulation_request
+        .params
+        .block_number
+        .as_deref()
+        .and_then(|block_number| parse_block_number(block_number).ok());
+    let cache_key = pinned_block.and_then(|_| request_key("simulate", &simulation_request).ok());
+    let result =
+        cached(handler.cache.as_ref(), cache_key, handler.cache_config.simulate_ttl, || async {
+            let result = handler
+                .service
+                .simulate_transaction(simulation_request)
+                .await?;
+            let finalized = match pinned_block {
+                Some(block_number) => handler.service.is_finalized(block_number).await,
+                None => false,
+            };
+            Ok::<_, ServiceError>((result, finalized))
+        })
+        .await;
+
+    match result {
+        Ok((result, cache_status)) => {
+            // Cached simulations are metered like executed ones
+            if cache_status == CacheStatus::Hit {
+                let gas_used =
+                    u64::from_str_radix(result.gas_used.trim_start_matches("0x"), 16).unwrap_or(0);
+                usage::record_simulation(result.calls.len(), gas_used);
+            }
+
             let execution_time = start_time.elapsed().as_millis() as u64;
             debug!(
                 target: "altitrace::api::simulation",
@@ -99,10 +168,12 @@ async fn simulate_transaction(
                 simulation_id = %result.simulation_id,
                 %execution_time,
                 status = ?result.status,
+                ?cache_status,
                 "Simulation completed successfully"
             );
 
-            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time)
+                .with_cache_status(cache_status))
         }
         Err(e) => {
             let execution_time = start_time.elapsed().as_millis() as u64;
@@ -128,17 +199,21 @@ async fn simulate_transaction(
     responses(
         (status = 200, description = "Batch simulation completed (includes individual success/failure)", body = ApiResponse<Vec<SimulationResult>>),
         (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     )
 )]
 async fn simulate_batch_transaction(
     handler: web::Data<SimulationHandler>,
     request: web::Json<Vec<SimulationRequest>>,
+    api_key: Option<web::ReqData<ApiKey>>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = Uuid::new_v4().to_string();
+    let request_id = request_id();
     let simulation_request = request.into_inner();
 
+    authorize_simulations(api_key.as_deref(), &simulation_request)?;
+
     debug!(
         target: "altitrace::api::simulation",
         request_id = %request_id,
@@ -174,6 +249,313 @@ async fn simulate_batch_transaction(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/simulate/multi-block",
+    tag = "simulation",
+    summary = "Simulate calls across consecutive blocks",
+    description = "Simulate several consecutive blocks, each with its own calls, state overrides and block overrides",
+    request_body = MultiBlockSimulationRequest,
+    responses(
+        (status = 200, description = "Multi-block simulation completed (success or failure)", body = ApiResponse<MultiBlockSimulationResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn simulate_multi_block(
+    handler: web::Data<SimulationHandler>,
+    request: web::Json<MultiBlockSimulationRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let simulation_request = request.into_inner();
+
+    if let Some(api_key) = &api_key {
+        api_key.authorize_tracers(simulation_request.tracer_names())?;
+        api_key.authorize_batch(simulation_request.call_count())?;
+    }
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        blocks_count = simulation_request.blocks.len(),
+        ?simulation_request,
+        "Processing multi-block simulation request"
+    );
+
+    match handler
+        .service
+        .simulate_multi_block(simulation_request)
+        .await
+    {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                simulation_id = %result.simulation_id,
+                %execution_time,
+                status = ?result.status,
+                "Multi-block simulation completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Multi-block simulation failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate/bundle",
+    tag = "simulation",
+    summary = "Simulate a bundle of interdependent transactions",
+    description = "Simulate transactions sequentially with shared state, halting at the first failing transaction that is not allowed to fail",
+    request_body = BundleSimulationRequest,
+    responses(
+        (status = 200, description = "Bundle simulation completed (includes per-transaction success/failure)", body = ApiResponse<BundleSimulationResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Batch size not allowed for the API key", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn simulate_bundle(
+    handler: web::Data<SimulationHandler>,
+    request: web::Json<BundleSimulationRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let bundle_request = request.into_inner();
+
+    if let Some(api_key) = &api_key {
+        api_key.authorize_batch(bundle_request.call_count())?;
+    }
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        bundle_size = bundle_request.bundle.len(),
+        ?bundle_request,
+        "Processing bundle simulation request"
+    );
+
+    match handler.service.simulate_bundle(bundle_request).await {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                bundle_id = %result.bundle_id,
+                %execution_time,
+                status = ?result.status,
+                "Bundle simulation completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Bundle simulation failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate/estimate-gas",
+    tag = "simulation",
+    summary = "Estimate the gas limit of a transaction",
+    description = "Binary search the minimal successful gas limit, with a recommended limit and optional optimization suggestions",
+    request_body = GasEstimationRequest,
+    responses(
+        (status = 200, description = "Gas estimation completed", body = ApiResponse<GasEstimationResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn estimate_gas(
+    handler: web::Data<SimulationHandler>,
+    request: web::Json<GasEstimationRequest>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let estimation_request = request.into_inner();
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        ?estimation_request,
+        "Processing gas estimation request"
+    );
+
+    match handler.service.estimate_gas(estimation_request).await {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                %execution_time,
+                gas_estimate = %result.gas_estimate,
+                "Gas estimation completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Gas estimation failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate/replay",
+    tag = "simulation",
+    summary = "Replay a mined transaction",
+    description = "Re-execute a mined transaction at its position in its block, with optional modifications, and compare the outcome against its receipt",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Replay completed (success or failure)", body = ApiResponse<ReplayResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn replay_transaction(
+    handler: web::Data<SimulationHandler>,
+    request: web::Json<ReplayRequest>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let replay_request = request.into_inner();
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        ?replay_request,
+        "Processing replay request"
+    );
+
+    match handler.service.replay_transaction(replay_request).await {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                %execution_time,
+                status = ?result.replay.status,
+                "Replay completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Replay failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate/replay-block",
+    tag = "simulation",
+    summary = "Re-execute a historical block",
+    description = "Re-execute the transactions of a block in order, with injected calls or removed transactions, and compare each transaction against its receipt",
+    request_body = BlockReplayRequest,
+    responses(
+        (status = 200, description = "Block replay completed", body = ApiResponse<BlockReplayResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Number of insertions not allowed for the API key", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn replay_block(
+    handler: web::Data<SimulationHandler>,
+    request: web::Json<BlockReplayRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let replay_request = request.into_inner();
+
+    if let Some(api_key) = &api_key {
+        api_key.authorize_batch(replay_request.insertions.len())?;
+    }
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        ?replay_request,
+        "Processing block replay request"
+    );
+
+    match handler.service.replay_block(replay_request).await {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                %execution_time,
+                changed_transactions = result.changed_transactions,
+                "Block replay completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Block replay failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/simulate/access-list",
@@ -192,7 +574,7 @@ async fn create_access_list(
     request: web::Json<AccessListRequest>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = Uuid::new_v4().to_string();
+    let request_id = request_id();
     let access_list_request = request.into_inner();
 
     debug!(
@@ -233,22 +615,242 @@ async fn create_access_list(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/simulate/{id}",
+    tag = "simulation",
+    summary = "Get a stored simulation",
+    description = "Get a previously executed simulation, with its request, by its simulation ID",
+    params(
+        ("id" = String, Path, description = "Simulation ID")
+    ),
+    responses(
+        (status = 200, description = "Stored simulation found", body = ApiResponse<StoredSimulation>),
+        (status = 400, description = "Invalid simulation ID", body = ApiResponse<String>),
+        (status = 404, description = "Simulation not found or expired", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn get_simulation(
+    handler: web::Data<SimulationHandler>,
+    id: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let simulation_id = id.into_inner();
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        simulation_id = %simulation_id,
+        "Processing stored simulation request"
+    );
+
+    let stored = find_simulation(&handler, &simulation_id).await?;
+    let execution_time = start_time.elapsed().as_millis() as u64;
+
+    Ok(ApiResponse::success_with_timing(stored, request_id, execution_time).into())
+}
+
+#[utoipa::path(
+    post,
+    path = "/simulate/{id}/rerun",
+    tag = "simulation",
+    summary = "Re-execute a stored simulation",
+    description = "Re-execute the request of a stored simulation at another block (the latest by default); the rerun is stored as a new simulation",
+    params(
+        ("id" = String, Path, description = "ID of the simulation to re-execute")
+    ),
+    request_body = RerunRequest,
+    responses(
+        (status = 200, description = "Simulation completed (success or failure)", body = ApiResponse<SimulationResult>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
+        (status = 404, description = "Simulation not found or expired", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    )
+)]
+async fn rerun_simulation(
+    handler: web::Data<SimulationHandler>,
+    id: web::Path<String>,
+    request: web::Json<RerunRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
+) -> ApiResult<HttpResponse> {
+    let start_time = Instant::now();
+    let request_id = request_id();
+    let simulation_id = id.into_inner();
+    let rerun_request = request.into_inner();
+
+    debug!(
+        target: "altitrace::api::simulation",
+        request_id = %request_id,
+        simulation_id = %simulation_id,
+        ?rerun_request,
+        "Processing simulation rerun request"
+    );
+
+    let stored = find_simulation(&handler, &simulation_id).await?;
+    authorize_simulations(api_key.as_deref(), std::slice::from_ref(&stored.request))?;
+
+    match handler
+        .service
+        .rerun_simulation(stored, rerun_request)
+        .await
+    {
+        Ok(result) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                original_id = %simulation_id,
+                simulation_id = %result.simulation_id,
+                %execution_time,
+                status = ?result.status,
+                "Simulation rerun completed successfully"
+            );
+
+            Ok(ApiResponse::success_with_timing(result, request_id, execution_time).into())
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            debug!(
+                target: "altitrace::api::simulation",
+                request_id = %request_id,
+                execution_time_ms = execution_time,
+                error = ?e,
+                "Simulation rerun failed"
+            );
+
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+/// Checks that the API key of the request, if any, can run `simulations`: the
+/// tracers of their options, and their calls as a single batch.
+fn authorize_simulations(
+    api_key: Option<&ApiKey>,
+    simulations: &[SimulationRequest],
+) -> ApiResult<()> {
+    let Some(api_key) = api_key else {
+        return Ok(());
+    };
+    for simulation in simulations {
+        api_key.authorize_tracers(simulation.tracer_names())?;
+    }
+    api_key.authorize_batch(simulations.iter().map(SimulationRequest::call_count).sum())
+}
+
+/// Looks up a stored simulation, failing if the ID is invalid or the simulation
+/// does not exist (anymore).
+async fn find_simulation(
+    handler: &SimulationHandler,
+    simulation_id: &str,
+) -> ApiResult<StoredSimulation> {
+    Uuid::parse_str(simulation_id)
+        .map_err(|_| ApiError::bad_request(format!("Invalid simulation ID: {}", simulation_id)))?;
+
+    handler
+        .service
+        .stored_simulation(simulation_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Simulation {}", simulation_id)))
+}
+
 define_routes!(
     SimulationHandler,
     "/simulate",
     "" => {
         method: post,
         handler: simulate_transaction,
-        params: { request: web::Json<SimulationRequest> }
+        params: { request: web::Json<SimulationRequest>, api_key: Option<web::ReqData<ApiKey>> }
     },
     "/batch" => {
         method: post,
         handler: simulate_batch_transaction,
-        params: { request: web::Json<Vec<SimulationRequest>> }
+        params: { request: web::Json<Vec<SimulationRequest>>, api_key: Option<web::ReqData<ApiKey>> }
+    },
+    "/multi-block" => {
+        method: post,
+        handler: simulate_multi_block,
+        params: { request: web::Json<MultiBlockSimulationRequest>, api_key: Option<web::ReqData<ApiKey>> }
+    },
+    "/bundle" => {
+        method: post,
+        handler: simulate_bundle,
+        params: { request: web::Json<BundleSimulationRequest>, api_key: Option<web::ReqData<ApiKey>> }
+    },
+    "/estimate-gas" => {
+        method: post,
+        handler: estimate_gas,
+        params: { request: web::Json<GasEstimationRequest> }
+    },
+    "/replay" => {
+        method: post,
+        handler: replay_transaction,
+        params: { request: web::Json<ReplayRequest> }
+    },
+    "/replay-block" => {
+        method: post,
+        handler: replay_block,
+        params: { request: web::Json<BlockReplayRequest>, api_key: Option<web::ReqData<ApiKey>> }
     },
     "/access-list" => {
         method: post,
         handler: create_access_list,
         params: { request: web::Json<AccessListRequest> }
+    },
+    "/{id}" => {
+        method: get,
+        handler: get_simulation,
+        params: { id: web::Path<String> }
+    },
+    "/{id}/rerun" => {
+        method: post,
+        handler: rerun_simulation,
+        params: { id: web::Path<String>, request: web::Json<RerunRequest>, api_key: Option<web::ReqData<ApiKey>> }
     }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_keys::{KeyScopes, KeySource};
+
+    fn simulation(options: serde_json::Value) -> SimulationRequest {
+        serde_json::from_value(serde_json::json!({
+            "params": {"calls": [{}, {}, {}]},
+            "options": options
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authorize_simulations() {
+        let api_key = ApiKey {
+            id: "config:partner".to_string(),
+            name: "partner".to_string(),
+            scopes: KeyScopes {
+                tracers: Some(vec!["callTracer".to_string()]),
+                max_batch_size: Some(4),
+                ..Default::default()
+            },
+            source: KeySource::Config,
+            created_at: None,
+        };
+
+        let allowed = simulation(serde_json::json!({"tracers": {"traceCalls": "all"}}));
+        assert!(authorize_simulations(Some(&api_key), std::slice::from_ref(&allowed)).is_ok());
+        assert!(authorize_simulations(None, &[allowed.clone(), allowed.clone()]).is_ok());
+
+        // The struct logger cannot be reached through the simulation options
+        let struct_logger = simulation(serde_json::json!({"tracers": {"structLogger": {}}}));
+        let gas_breakdown = simulation(serde_json::json!({"gasBreakdown": true}));
+        for simulation in [struct_logger, gas_breakdown] {
+            assert!(authorize_simulations(Some(&api_key), &[simulation]).is_err());
+        }
+
+        // Nor can the batch size by splitting the calls across simulations
+        assert!(authorize_simulations(Some(&api_key), &[allowed.clone(), allowed]).is_err());
+    }
+}
@@ -2,9 +2,15 @@ use alloy_eip2930::{
     AccessList as AlloyAccessList, AccessListItem as AlloyAccessListItem,
     AccessListResult as AlloyAccessListResult,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::{
+    handlers::{simulation::dto::SimulationRequest, trace::TracerResponse},
+    types::{GasBreakdown, TransactionReceiptInfo},
+};
+
 /// Complete simulation result containing all execution details.
 ///
 /// This is the main response structure for transaction simulation,
@@ -37,6 +43,183 @@ pub struct SimulationResult {
     /// Token balance changes (if tracing enabled).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub asset_changes: Option<Vec<AssetChange>>,
+
+    /// Native balance changes of every account touched by native transfers,
+    /// including the gas fees paid by the senders (if transfer tracing enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_balance_changes: Option<Vec<NativeBalanceChange>>,
+
+    /// Pre-flight validation report of the calls (if validation enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_report: Option<ValidationReport>,
+
+    /// State changes caused by the calls (if state diff enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<StateDiff>,
+}
+
+/// Simulation stored with its request after its execution, retrievable by the
+/// ID of its result until it expires.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSimulation {
+    /// The request that was simulated.
+    pub request: SimulationRequest,
+
+    /// The result of the simulation.
+    pub result: SimulationResult,
+
+    /// UTC timestamp when the simulation was executed.
+    pub created_at: DateTime<Utc>,
+
+    /// ID of the simulation this one re-executed, if it is a rerun.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "3f8a2c1e-7b4d-4e9a-8c6f-1d2e3f4a5b6c")]
+    pub rerun_of: Option<String>,
+}
+
+/// State changes caused by the calls of a simulation, from the state before the
+/// first call to the state after the last one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDiff {
+    /// Accounts whose balance, nonce or code changed, with their storage changes.
+    pub accounts: Vec<AccountDiff>,
+
+    /// Storage changes of the contracts whose balance, nonce and code did not change.
+    pub storage: Vec<ContractStorageDiff>,
+}
+
+/// Changes of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    /// Account address.
+    #[schema(
+        example = "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub address: String,
+
+    /// Balance change in wei (hex encoded).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<ValueDiff<String>>,
+
+    /// Nonce change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<ValueDiff<u64>>,
+
+    /// Code change (hex encoded, `0x` for no code).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ValueDiff<String>>,
+
+    /// Storage changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageSlotDiff>,
+}
+
+/// Storage changes of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractStorageDiff {
+    /// Contract address.
+    #[schema(
+        example = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub address: String,
+
+    /// Storage changes.
+    pub storage: Vec<StorageSlotDiff>,
+}
+
+/// Value before and after the calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueDiff<T> {
+    /// Value before the calls.
+    pub before: T,
+
+    /// Value after the calls.
+    pub after: T,
+}
+
+/// Change of a storage slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageSlotDiff {
+    /// Storage slot (32 bytes, hex encoded).
+    #[schema(example = "0x0000000000000000000000000000000000000000000000000000000000000001")]
+    pub slot: String,
+
+    /// Value before the calls (32 bytes, hex encoded).
+    #[schema(example = "0x0000000000000000000000000000000000000000000000000000000000000000")]
+    pub before: String,
+
+    /// Value after the calls (32 bytes, hex encoded).
+    #[schema(example = "0x0000000000000000000000000000000000000000000000000000000000000064")]
+    pub after: String,
+}
+
+/// Pre-flight validation report of the calls of a simulation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    /// Whether no finding is an error.
+    #[schema(example = false)]
+    pub valid: bool,
+
+    /// Issues found in the calls, in call order.
+    pub findings: Vec<ValidationFinding>,
+}
+
+/// Issue found in a call before its execution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationFinding {
+    /// Machine-readable finding code.
+    #[schema(example = "insufficient-balance")]
+    pub code: ValidationCode,
+
+    /// Finding severity.
+    #[schema(example = "error")]
+    pub severity: ValidationSeverity,
+
+    /// Index of the call in the simulation.
+    #[schema(example = 0)]
+    pub call_index: u32,
+
+    /// Human-readable description.
+    #[schema(example = "Sender balance 0x0 does not cover the value and gas cost 0x5af3107a4000")]
+    pub message: String,
+}
+
+/// Kind of pre-flight validation finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidationCode {
+    /// The sender cannot pay for the value and the maximum gas cost.
+    InsufficientBalance,
+    /// The nonce differs from the next nonce of the sender.
+    NonceMismatch,
+    /// The gas limit exceeds the block gas limit.
+    GasLimitExceeded,
+    /// The maximum fee per gas is below the block base fee.
+    FeeBelowBaseFee,
+    /// Calldata is sent to an address without code.
+    NoCode,
+    /// A contract creation has no init code.
+    MissingInitCode,
+}
+
+/// Severity of a pre-flight validation finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    /// The call would be rejected by a validating node.
+    Error,
+    /// The call executes but likely not as intended.
+    Warning,
 }
 
 /// Execution status for the overall simulation.
@@ -52,6 +235,305 @@ pub enum SimulationStatus {
     Failed,
 }
 
+/// Result of a multi-block simulation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiBlockSimulationResult {
+    /// Unique identifier for this simulation.
+    pub simulation_id: String,
+
+    /// Overall simulation execution status.
+    #[schema(example = "success")]
+    pub status: SimulationStatus,
+
+    /// Results for each simulated block, in order.
+    pub blocks: Vec<SimulatedBlockResult>,
+
+    /// Total gas consumed by all calls of all blocks.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Token balance changes of `account` across all blocks (if tracing enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_changes: Option<Vec<AssetChange>>,
+}
+
+/// Result of a single block of a multi-block simulation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBlockResult {
+    /// Simulated block number.
+    #[schema(example = "0x123abd", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: String,
+
+    /// Simulated block timestamp.
+    #[schema(example = "0x6553f100", pattern = "^0x[a-fA-F0-9]+$")]
+    pub timestamp: String,
+
+    /// Base fee per gas of the simulated block (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "0x3b9aca00", pattern = "^0x[a-fA-F0-9]+$")]
+    pub base_fee_per_gas: Option<String>,
+
+    /// Total gas used in the simulated block.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Results for each call of this block.
+    pub calls: Vec<CallResult>,
+
+    /// Native balance changes caused by the calls of this block (if transfer tracing
+    /// enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_balance_changes: Option<Vec<NativeBalanceChange>>,
+}
+
+/// Result of a bundle simulation.
+///
+/// All transactions of the bundle are executed sequentially in the same block,
+/// so each transaction sees the state changes of the previous ones.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSimulationResult {
+    /// Unique identifier for this bundle simulation.
+    pub bundle_id: String,
+
+    /// The block number the bundle was simulated in.
+    #[schema(example = "0x123abd", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: String,
+
+    /// Overall bundle status: `success` if every transaction succeeded, `reverted` if
+    /// only transactions allowed to fail reverted, `failed` if the bundle was halted.
+    #[schema(example = "success")]
+    pub status: SimulationStatus,
+
+    /// Index of the transaction that halted the bundle (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 1)]
+    pub halted_at: Option<u32>,
+
+    /// Results for each transaction of the bundle, in execution order.
+    pub transactions: Vec<BundleTransactionResult>,
+
+    /// Total gas consumed by the executed transactions.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Total gas used in the simulated block.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_gas_used: String,
+
+    /// Token balance changes of the first transaction account (if tracing enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_changes: Option<Vec<AssetChange>>,
+
+    /// Native balance changes caused by the executed transactions (if transfer tracing
+    /// enabled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_balance_changes: Option<Vec<NativeBalanceChange>>,
+}
+
+/// Result of a single transaction within a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransactionResult {
+    /// Index of this transaction within the bundle.
+    pub transaction_index: u32,
+
+    /// Execution status of this transaction.
+    #[schema(example = "success")]
+    pub status: BundleTransactionStatus,
+
+    /// Whether this transaction was allowed to fail.
+    pub allow_failure: bool,
+
+    /// Gas consumed by the calls of this transaction.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Results for each call of this transaction (empty if skipped).
+    pub calls: Vec<CallResult>,
+}
+
+/// Execution status of a bundle transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[schema(example = "success")]
+pub enum BundleTransactionStatus {
+    /// All calls of the transaction executed successfully.
+    Success,
+    /// One or more calls of the transaction reverted.
+    Reverted,
+    /// Not executed because an earlier transaction halted the bundle.
+    Skipped,
+}
+
+/// Result of a gas estimation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GasEstimationResult {
+    /// The block number the transaction was executed in.
+    #[schema(example = "0x123abd", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: String,
+
+    /// Minimal gas limit for which the transaction succeeds.
+    #[schema(example = "0xc5d2", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_estimate: String,
+
+    /// Gas actually consumed by the transaction.
+    #[schema(example = "0xb5f2", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Recommended gas limit, the estimate plus a safety margin (capped to the block
+    /// gas limit).
+    #[schema(example = "0xed2f", pattern = "^0x[a-fA-F0-9]+$")]
+    pub recommended_gas_limit: String,
+
+    /// Number of executions performed by the search.
+    #[schema(example = 12)]
+    pub iterations: u32,
+
+    /// Optimization suggestions (if an optimization level or alternatives were requested).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<GasSuggestion>,
+}
+
+/// Suggestion to lower the gas cost of a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GasSuggestion {
+    /// Suggestion kind.
+    #[schema(example = "access-list")]
+    pub kind: GasSuggestionKind,
+
+    /// Human-readable description.
+    #[schema(example = "Attach an access list to pre-warm 2 accounts and 3 storage slots")]
+    pub description: String,
+
+    /// Estimated gas savings (hex encoded).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "0x3e8", pattern = "^0x[a-fA-F0-9]+$")]
+    pub estimated_savings: Option<String>,
+
+    /// Access list to attach to the transaction (if alternatives were requested).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<AccessList>,
+}
+
+/// Kind of gas optimization suggestion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum GasSuggestionKind {
+    /// Attach an EIP-2930 access list.
+    AccessList,
+    /// Reduce the calldata size.
+    Calldata,
+}
+
+/// Result of the replay of a mined transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    /// Hash of the replayed transaction.
+    #[schema(example = "0xbc4a51bbcbe7550446c151d0d53ee14d5318188e2af1726e28a481b075fc7b4c")]
+    pub transaction_hash: String,
+
+    /// Block the transaction was mined in.
+    #[schema(example = "0x123abd", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: String,
+
+    /// Position of the transaction in its block.
+    #[schema(example = 3)]
+    pub transaction_index: u64,
+
+    /// Receipt of the mined transaction.
+    pub original: TransactionReceiptInfo,
+
+    /// Outcome of the replayed transaction, with the modifications applied.
+    pub replay: CallResult,
+
+    /// Differences between the replayed and the original outcome.
+    pub comparison: ReplayComparison,
+}
+
+/// Differences between the replayed and the original outcome of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayComparison {
+    /// Whether the replay succeeded while the original failed, or the other way around.
+    pub status_changed: bool,
+
+    /// Gas used by the replay minus the gas used by the original transaction.
+    #[schema(example = -2100)]
+    pub gas_used_delta: i64,
+
+    /// Number of logs emitted by the replay minus those of the original transaction.
+    #[schema(example = 0)]
+    pub logs_count_delta: i64,
+
+    /// Whether the replay emitted different logs than the original transaction.
+    pub logs_changed: bool,
+}
+
+/// Result of the re-execution of a historical block.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReplayResult {
+    /// The re-executed block.
+    #[schema(example = "0x123abc", pattern = "^0x[a-fA-F0-9]+$")]
+    pub block_number: String,
+
+    /// Total gas consumed by the re-executed transactions and injected calls.
+    #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
+    pub gas_used: String,
+
+    /// Number of original transactions whose outcome changed.
+    #[schema(example = 1)]
+    pub changed_transactions: u32,
+
+    /// Results for each original transaction of the block, in order.
+    pub transactions: Vec<BlockReplayTransaction>,
+
+    /// Results for each injected call, in execution order.
+    pub injected: Vec<InjectedCallResult>,
+}
+
+/// Result of an original transaction of a re-executed block.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReplayTransaction {
+    /// Position of the transaction in the canonical block.
+    #[schema(example = 3)]
+    pub transaction_index: u64,
+
+    /// Hash of the transaction.
+    #[schema(example = "0xbc4a51bbcbe7550446c151d0d53ee14d5318188e2af1726e28a481b075fc7b4c")]
+    pub transaction_hash: String,
+
+    /// Whether the transaction was dropped from the block.
+    pub removed: bool,
+
+    /// Outcome of the re-executed transaction (absent if removed).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<CallResult>,
+
+    /// Differences with the canonical receipt (absent if removed).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<ReplayComparison>,
+}
+
+/// Result of a call injected into a re-executed block.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InjectedCallResult {
+    /// Index of the original transaction the call was executed before.
+    #[schema(example = 2)]
+    pub index: u64,
+
+    /// Outcome of the call.
+    pub result: CallResult,
+}
+
 /// Result of a single transaction call within the simulation.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -74,12 +556,28 @@ pub struct CallResult {
     #[schema(example = "0x5208", pattern = "^0x[a-fA-F0-9]+$")]
     pub gas_used: String,
 
+    /// Gas consumed by category (hex encoded), if requested with `gasBreakdown`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_breakdown: Option<GasBreakdown<String>>,
+
     /// Event logs emitted by this call.
     pub logs: Vec<EnhancedLog>,
 
+    /// Decoded function call and return value (if an ABI was supplied for the target).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedCall>,
+
+    /// Native value transfers made by this call (if transfer tracing enabled).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub native_transfers: Vec<NativeTransfer>,
+
     /// Error details (present if call failed or reverted).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<CallError>,
+
+    /// Execution traces of this call, if requested with `tracers`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TracerResponse>,
 }
 
 /// Execution status for individual calls.
@@ -93,6 +591,58 @@ pub enum CallStatus {
     Reverted,
 }
 
+/// Native value transfer observed while executing a call.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeTransfer {
+    /// Account the value was sent from.
+    #[schema(
+        example = "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub from: String,
+
+    /// Account the value was sent to.
+    #[schema(
+        example = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub to: String,
+
+    /// Transferred value in wei (hex encoded).
+    #[schema(example = "0xde0b6b3a7640000", pattern = "^0x[a-fA-F0-9]+$")]
+    pub value: String,
+
+    /// Depth of the call frame that moved the value (0 for the transaction value).
+    ///
+    /// Frames that do not move value are not reported by the node, so the depth of
+    /// deeply nested transfers is inferred from the transfer chain and may be lower
+    /// than the actual frame depth.
+    #[schema(example = 0)]
+    pub depth: u32,
+}
+
+/// Net native balance change of an account across a simulation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeBalanceChange {
+    /// Account address.
+    #[schema(
+        example = "0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c",
+        pattern = "^0x[a-fA-F0-9]{40}$"
+    )]
+    pub address: String,
+
+    /// Net change in wei including gas fees (hex encoded).
+    /// Positive means gained, negative (prefixed with `-`) means lost.
+    #[schema(example = "-0xde0b6b3a7640000", pattern = "^-?0x[a-fA-F0-9]*$")]
+    pub diff: String,
+
+    /// Gas fees paid by this account in wei (hex encoded).
+    #[schema(example = "0x0", pattern = "^0x[a-fA-F0-9]*$")]
+    pub gas_fee: String,
+}
+
 /// Event log with optional decoded information for better readability.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -188,6 +738,72 @@ pub struct DecodedEventParam {
     pub indexed: bool,
 }
 
+/// Function call decoded with a user-supplied ABI.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedCall {
+    /// Function name.
+    #[schema(example = "transfer")]
+    pub name: String,
+
+    /// Function signature with types.
+    #[schema(example = "transfer(address,uint256)")]
+    pub signature: String,
+
+    /// Function selector (hex encoded).
+    #[schema(example = "0xa9059cbb", pattern = "^0x[a-fA-F0-9]{8}$")]
+    pub selector: String,
+
+    /// Decoded input arguments.
+    pub inputs: Vec<DecodedParam>,
+
+    /// Decoded return values (if the call returned data matching the ABI).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<DecodedParam>>,
+}
+
+/// Error decoded from revert data.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedError {
+    /// Error name.
+    #[schema(example = "InsufficientBalance")]
+    pub name: String,
+
+    /// Error signature with types.
+    #[schema(example = "InsufficientBalance(uint256,uint256)")]
+    pub signature: String,
+
+    /// Error selector (hex encoded).
+    #[schema(example = "0xcf479181", pattern = "^0x[a-fA-F0-9]{8}$")]
+    pub selector: String,
+
+    /// Decoded error parameters.
+    pub params: Vec<DecodedParam>,
+
+    /// Meaning of the error, for built-in errors such as `Panic(uint256)` codes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Arithmetic overflow or underflow")]
+    pub description: Option<String>,
+}
+
+/// Individual decoded function or error parameter.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedParam {
+    /// Parameter name from the ABI (may be empty).
+    #[schema(example = "amount")]
+    pub name: String,
+
+    /// Parameter type (e.g., "address", "uint256", "(uint256,bool)").
+    #[schema(example = "uint256")]
+    pub param_type: String,
+
+    /// Formatted parameter value.
+    #[schema(example = "1000000")]
+    pub value: String,
+}
+
 /// Token balance change information.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -234,8 +850,8 @@ pub struct BalanceChange {
     pub post: String,
 
     /// Net change (post - pre, hex encoded).
-    /// Positive means gained, negative means lost.
-    #[schema(example = "0xf4240", pattern = "^0x[a-fA-F0-9]*$")]
+    /// Positive means gained, negative (prefixed with `-`) means lost.
+    #[schema(example = "-0xf4240", pattern = "^-?0x[a-fA-F0-9]*$")]
     pub diff: String,
 }
 
@@ -258,6 +874,16 @@ pub struct CallError {
     /// Contract address where the error occurred.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contract_address: Option<String>,
+
+    /// Raw revert data (hex encoded).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "0x08c379a0", pattern = "^0x[a-fA-F0-9]*$")]
+    pub data: Option<String>,
+
+    /// Decoded revert data: `Error(string)`, `Panic(uint256)`, or a custom error
+    /// matching a supplied ABI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedError>,
 }
 
 /// Storage operation gas usage breakdown.
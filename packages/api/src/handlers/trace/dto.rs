@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use crate::{
-    handlers::validation::validate_hash,
-    types::{BlockOverrides, Bundle, StateContext, StateOverride, TransactionCall},
+    handlers::validation::{validate_abi_addresses, validate_hash},
+    types::{BlockOverrides, Bundle, ContractAbis, StateContext, StateOverride, TransactionCall},
     utils::default_latest,
 };
 use serde::{Deserialize, Serialize};
@@ -50,6 +50,12 @@ pub struct TraceTransactionRequest {
     #[validate(nested)]
     #[serde(default)]
     pub tracer_config: TraceConfig,
+    /// JSON ABIs keyed by contract address.
+    /// Used to decode the input, output and logs of the traced calls to these contracts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
 }
 
 /// Request to trace a call simulation.
@@ -79,6 +85,12 @@ pub struct TraceCallRequest {
     #[validate(nested)]
     #[serde(default)]
     pub block_overrides: Option<BlockOverrides>,
+    /// JSON ABIs keyed by contract address.
+    /// Used to decode the input, output and logs of the traced calls to these contracts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_abi_addresses"))]
+    #[schema(value_type = Option<HashMap<String, Vec<Object>>>)]
+    pub abis: Option<ContractAbis>,
 }
 
 /// Request to trace many calls.
@@ -129,6 +141,26 @@ impl TraceConfig {
             .as_ref()
             .is_some_and(|config| config.clean_struct_logs)
     }
+
+    /// Returns the configuration to trace with: the prestate tracer is added when
+    /// storage accesses are extracted from the struct logs (which requires the
+    /// stack), as it gives the values of the slots before the transaction.
+    ///
+    /// Use [`TracerResponse::drop_storage_prestate`](super::TracerResponse::drop_storage_prestate)
+    /// to remove it from the response when it was not requested.
+    pub fn with_storage_prestate(&self) -> Self {
+        let mut config = self.clone();
+        let captures_stack = config
+            .tracers
+            .struct_logger
+            .as_ref()
+            .is_some_and(|config| !config.disable_stack);
+        if captures_stack && config.tracers.prestate_tracer.is_none() {
+            config.tracers.prestate_tracer =
+                Some(PrestateTracerConfig { disable_code: true, ..Default::default() });
+        }
+        config
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Validate)]
@@ -182,6 +214,26 @@ impl Tracers {
         self.count_active_tracers() >= 2
     }
 
+    /// Names of the requested tracers, `struct_logger` included.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        if self.four_byte_tracer {
+            names.push("4byteTracer");
+        }
+        if self.call_tracer.is_some() {
+            names.push("callTracer");
+        }
+        if self.prestate_tracer.is_some() {
+            names.push("prestateTracer");
+        }
+        if self.struct_logger.is_some() {
+            names.push("structLogger");
+        }
+
+        names
+    }
+
     /// Counts the number of active tracers (excluding `struct_logger`).
     pub const fn count_active_tracers(&self) -> usize {
         let mut count = 0;
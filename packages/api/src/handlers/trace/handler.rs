@@ -1,14 +1,19 @@
 use std::time::Instant;
 
 use crate::{
+    config::CacheConfig,
     define_routes,
-    error::{ApiError, ApiResult},
+    error::{ApiError, ApiResult, ServiceError},
     handlers::{
-        common::{ApiResponse, Handler},
+        common::{cached, ApiResponse, Handler},
         trace::{dto::*, TracerResponse},
     },
-    services::hyperevm::service::HyperEvmService,
-    utils::generate_request_id,
+    services::{
+        api_keys::ApiKey, decoder::AbiRegistry, hyperevm::service::HyperEvmService, request_key,
+        telemetry::request_id, Cache,
+    },
+    types::{ContractAbis, TraceResponse},
+    utils::validation::parse_block_number,
 };
 use actix_web::{web, HttpResponse};
 use tracing::debug;
@@ -39,11 +44,20 @@ pub struct TraceApiDoc;
 
 pub struct TraceHandler {
     service: web::Data<HyperEvmService>,
+    cache: Option<Cache>,
+    cache_config: CacheConfig,
 }
 
 impl TraceHandler {
-    pub const fn new(service: web::Data<HyperEvmService>) -> Self {
-        Self { service }
+    pub fn new(service: web::Data<HyperEvmService>) -> Self {
+        Self { service, cache: None, cache_config: CacheConfig::default() }
+    }
+
+    /// Caches the immutable traces, with the TTLs of the configuration.
+    pub fn with_cache(mut self, cache: Cache, config: CacheConfig) -> Self {
+        self.cache = Some(cache);
+        self.cache_config = config;
+        self
     }
 
     pub fn into_app_data(self) -> web::Data<Self> {
@@ -65,19 +79,26 @@ impl From<TraceHandler> for web::Data<TraceHandler> {
     description = "Traces a single transaction execution.",
     request_body = TraceTransactionRequest,
     responses(
-        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<TracerResponse>),
+        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<TracerResponse>,
+            headers(("X-Cache" = String, description = "HIT, MISS or BYPASS: whether the trace was served from the cache"))),
         (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     )
 )]
 async fn trace_transaction(
     handler: web::Data<TraceHandler>,
     request: web::Json<TraceTransactionRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = generate_request_id();
+    let request_id = request_id();
     let request = request.into_inner();
 
+    if let Some(api_key) = &api_key {
+        api_key.authorize_tracers(request.tracer_config.tracers.names())?;
+    }
+
     debug!(
         target: "altitrace::trace",
         %request_id,
@@ -85,24 +106,34 @@ async fn trace_transaction(
         "Starting transaction trace",
     );
 
-    match handler.service.trace_transaction(&request).await {
-        Ok(response) => {
-            let mut tracer_response = TracerResponse::from(response.trace_result);
-
-            if let Some(receipt) = response.receipt {
-                tracer_response = tracer_response.with_receipt(receipt);
-            }
-
-            if request.tracer_config.should_clean_struct_logger() {
-                tracer_response.clean_struct_logger();
-            }
+    // Mined transactions are always looked up, but only cached once finalized
+    let cache_key = request_key("trace:tx", &request).ok();
+    let result = cached(
+        handler.cache.as_ref(),
+        cache_key,
+        handler.cache_config.trace_transaction_ttl,
+        || async {
+            let response = handler.service.trace_transaction(&request).await?;
+            let finalized = match response.block_number {
+                Some(block_number) => handler.service.is_finalized(block_number).await,
+                None => false,
+            };
+            let tracer_response =
+                into_tracer_response(response, &request.tracer_config, request.abis.as_ref());
+            Ok::<_, ServiceError>((tracer_response, finalized))
+        },
+    )
+    .await;
 
+    match result {
+        Ok((tracer_response, cache_status)) => {
             let elapsed = start_time.elapsed();
 
             debug!(
                 target: "altitrace::trace",
                 %request_id,
                 ?elapsed,
+                ?cache_status,
                 "Transaction trace completed"
             );
 
@@ -111,7 +142,7 @@ async fn trace_transaction(
                 request_id,
                 elapsed.as_millis() as u64,
             )
-            .into())
+            .with_cache_status(cache_status))
         }
         Err(e) => {
             let elapsed = start_time.elapsed();
@@ -136,19 +167,26 @@ async fn trace_transaction(
     description = "Get a transaction trace from a call request.",
     request_body = TraceCallRequest,
     responses(
-        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<TracerResponse>),
+        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<TracerResponse>,
+            headers(("X-Cache" = String, description = "HIT, MISS or BYPASS: whether the trace was served from the cache"))),
         (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     )
 )]
 async fn trace_call(
     handler: web::Data<TraceHandler>,
     request: web::Json<TraceCallRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = generate_request_id();
+    let request_id = request_id();
     let request = request.into_inner();
 
+    if let Some(api_key) = &api_key {
+        api_key.authorize_tracers(request.tracer_config.tracers.names())?;
+    }
+
     debug!(
         target: "altitrace::trace",
         %request_id,
@@ -156,20 +194,31 @@ async fn trace_call(
         "Starting call trace"
     );
 
-    match handler.service.trace_call(&request).await {
-        Ok(response) => {
-            let mut tracer_response = TracerResponse::from(response.trace_result);
-
-            if request.tracer_config.should_clean_struct_logger() {
-                tracer_response.clean_struct_logger();
-            }
+    // Only calls pinned to a block number are looked up
+    let pinned_block = parse_block_number(&request.block).ok();
+    let cache_key = pinned_block.and_then(|_| request_key("trace:call", &request).ok());
+    let result =
+        cached(handler.cache.as_ref(), cache_key, handler.cache_config.trace_call_ttl, || async {
+            let response = handler.service.trace_call(&request).await?;
+            let finalized = match pinned_block {
+                Some(block_number) => handler.service.is_finalized(block_number).await,
+                None => false,
+            };
+            let tracer_response =
+                into_tracer_response(response, &request.tracer_config, request.abis.as_ref());
+            Ok::<_, ServiceError>((tracer_response, finalized))
+        })
+        .await;
 
+    match result {
+        Ok((tracer_response, cache_status)) => {
             let elapsed = start_time.elapsed();
 
             debug!(
                 target: "altitrace::trace",
                 %request_id,
                 ?elapsed,
+                ?cache_status,
                 "Call trace completed"
             );
 
@@ -178,7 +227,7 @@ async fn trace_call(
                 request_id,
                 elapsed.as_millis() as u64,
             )
-            .into())
+            .with_cache_status(cache_status))
         }
         Err(e) => {
             let elapsed = start_time.elapsed();
@@ -203,19 +252,33 @@ async fn trace_call(
     description = "Execute debug_trace_call_many to trace multiple calls sequentially with cumulative state changes.",
     request_body = TraceCallManyRequest,
     responses(
-        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<Vec<TracerResponse>>),
+        (status = 200, description = "Trace completed (success or failure)", body = ApiResponse<Vec<TracerResponse>>,
+            headers(("X-Cache" = String, description = "HIT, MISS or BYPASS: whether the trace was served from the cache"))),
         (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 403, description = "Tracer or batch size not allowed for the API key", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     )
 )]
 async fn trace_call_many(
     handler: web::Data<TraceHandler>,
     request: web::Json<TraceCallManyRequest>,
+    api_key: Option<web::ReqData<ApiKey>>,
 ) -> ApiResult<HttpResponse> {
     let start_time = Instant::now();
-    let request_id = generate_request_id();
+    let request_id = request_id();
     let request = request.into_inner();
 
+    if let Some(api_key) = &api_key {
+        api_key.authorize_tracers(request.tracer_config.tracers.names())?;
+        api_key.authorize_batch(
+            request
+                .bundles
+                .iter()
+                .map(|bundle| bundle.transactions.len())
+                .sum(),
+        )?;
+    }
+
     debug!(
         target: "altitrace::trace",
         %request_id,
@@ -224,23 +287,30 @@ async fn trace_call_many(
         "Starting call many trace"
     );
 
-    match handler.service.trace_call_many(&request).await {
-        Ok(responses) => {
-            // Convert Vec<TraceResponse> to Vec<TracerResponse>
+    // Only bundles pinned to a block number are looked up
+    let pinned_block = parse_block_number(&request.state_context.block).ok();
+    let cache_key = pinned_block.and_then(|_| request_key("trace:call-many", &request).ok());
+    let result = cached(
+        handler.cache.as_ref(),
+        cache_key,
+        handler.cache_config.trace_call_many_ttl,
+        || async {
+            let responses = handler.service.trace_call_many(&request).await?;
+            let finalized = match pinned_block {
+                Some(block_number) => handler.service.is_finalized(block_number).await,
+                None => false,
+            };
             let tracer_responses: Vec<TracerResponse> = responses
                 .into_iter()
-                .map(|response| {
-                    let mut tracer_response = TracerResponse::from(response.trace_result);
-                    if let Some(receipt) = response.receipt {
-                        tracer_response = tracer_response.with_receipt(receipt);
-                    }
-                    if request.tracer_config.should_clean_struct_logger() {
-                        tracer_response.clean_struct_logger();
-                    }
-                    tracer_response
-                })
+                .map(|response| into_tracer_response(response, &request.tracer_config, None))
                 .collect();
+            Ok::<_, ServiceError>((tracer_responses, finalized))
+        },
+    )
+    .await;
 
+    match result {
+        Ok((tracer_responses, cache_status)) => {
             let elapsed = start_time.elapsed();
 
             debug!(
@@ -248,6 +318,7 @@ async fn trace_call_many(
                 %request_id,
                 responses_count = tracer_responses.len(),
                 ?elapsed,
+                ?cache_status,
                 "Call many trace completed"
             );
 
@@ -256,7 +327,7 @@ async fn trace_call_many(
                 request_id,
                 elapsed.as_millis() as u64,
             )
-            .into())
+            .with_cache_status(cache_status))
         }
         Err(e) => {
             let elapsed = start_time.elapsed();
@@ -273,22 +344,56 @@ async fn trace_call_many(
     }
 }
 
+/// Converts a trace of the service to the API response: computes the gas
+/// breakdown and storage accesses, and decodes the calls with the given ABIs.
+fn into_tracer_response(
+    response: TraceResponse,
+    tracer_config: &TraceConfig,
+    abis: Option<&ContractAbis>,
+) -> TracerResponse {
+    let mut tracer_response = TracerResponse::from(response.trace_result);
+
+    if let Some(receipt) = response.receipt {
+        tracer_response = tracer_response.with_receipt(receipt);
+    }
+
+    if let Some(intrinsic_gas) = response.intrinsic_gas {
+        tracer_response.compute_gas_breakdown(intrinsic_gas);
+    }
+
+    if let Some(target) = response.target {
+        tracer_response.compute_storage_accesses(target, response.access_list.as_ref());
+    }
+    tracer_response.drop_storage_prestate(tracer_config);
+
+    if tracer_config.should_clean_struct_logger() {
+        tracer_response.clean_struct_logger();
+    }
+
+    let registry = AbiRegistry::new(abis);
+    if !registry.is_empty() {
+        tracer_response.apply_abis(&registry);
+    }
+
+    tracer_response
+}
+
 define_routes!(
     TraceHandler,
     "/trace",
     "/tx" => {
         method: post,
         handler: trace_transaction,
-        params: { request: web::Json<TraceTransactionRequest> }
+        params: { request: web::Json<TraceTransactionRequest>, api_key: Option<web::ReqData<ApiKey>> }
     },
     "/call" => {
         method: post,
         handler: trace_call,
-        params: { request: web::Json<TraceCallRequest> }
+        params: { request: web::Json<TraceCallRequest>, api_key: Option<web::ReqData<ApiKey>> }
     },
     "/call-many" => {
         method: post,
         handler: trace_call_many,
-        params: { request: web::Json<TraceCallManyRequest> }
+        params: { request: web::Json<TraceCallManyRequest>, api_key: Option<web::ReqData<ApiKey>> }
     },
 );
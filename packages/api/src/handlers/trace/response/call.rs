@@ -1,4 +1,11 @@
-use crate::types::LogEntry;
+use crate::{
+    handlers::simulation::response::{DecodedCall, DecodedError},
+    services::decoder::{decode_event, decode_revert, revert_reason, AbiRegistry},
+    types::LogEntry,
+};
+use std::str::FromStr;
+
+use alloy_primitives::{hex, Address, B256};
 
 use alloy_rpc_types_trace::geth::{
     mux::MuxFrame, CallFrame as AlloyCallFrame, GethDebugBuiltInTracerType,
@@ -71,10 +78,18 @@ pub struct CallFrame {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub revert_reason: Option<String>,
 
+    /// Decoded revert data (the raw revert data is in `output`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_error: Option<DecodedError>,
+
+    /// Decoded function call and return value (if an ABI was supplied for the target).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedCall>,
+
     /// Sub-calls made by this call.
     #[schema(no_recursion)]
     #[serde(default)]
-    pub calls: Vec<CallFrame>,
+    pub calls: Vec<Self>,
 
     /// Logs emitted by this call.
     #[serde(default)]
@@ -138,9 +153,28 @@ impl CallFrame {
                     .as_ref()
                     .map(|data| format!("0x{}", alloy_primitives::hex::encode(data)))
                     .unwrap_or_else(|| "0x".to_string()),
+                decoded: decode_event(
+                    log.topics.as_deref().unwrap_or_default(),
+                    log.data
+                        .as_ref()
+                        .map(|data| data.as_ref())
+                        .unwrap_or_default(),
+                ),
             })
             .collect();
 
+        // Decode the revert data, falling back to the reason reported by the node
+        let reverted = frame.error.is_some();
+        let decoded_error = frame
+            .output
+            .as_ref()
+            .filter(|_| reverted)
+            .and_then(|output| decode_revert(output));
+        let revert_reason = decoded_error
+            .as_ref()
+            .map(revert_reason)
+            .or_else(|| frame.revert_reason.clone());
+
         Self {
             call_type: frame.typ.clone(),
             from: format!("{:?}", frame.from),
@@ -155,9 +189,11 @@ impl CallFrame {
                 .map(|output| format!("0x{}", alloy_primitives::hex::encode(output)))
                 .unwrap_or_else(|| "0x".to_string()),
             depth,
-            reverted: frame.error.is_some(),
+            reverted,
             error: frame.error.clone(),
-            revert_reason: frame.revert_reason.clone(),
+            revert_reason,
+            decoded_error,
+            decoded: None,
             calls,
             logs,
         }
@@ -171,6 +207,53 @@ impl CallFrame {
     }
 }
 
+impl CallFrame {
+    /// Decodes the input, output and logs of this frame and its sub-calls with
+    /// the supplied ABIs.
+    pub fn apply_abis(&mut self, registry: &AbiRegistry<'_>) {
+        if let Some(to) = self.to.as_deref().and_then(|to| Address::from_str(to).ok()) {
+            let input = hex::decode(&self.input).unwrap_or_default();
+            let output = hex::decode(&self.output).unwrap_or_default();
+            if self.reverted {
+                self.decoded = registry.decode_call(to, &input, None);
+                if self.decoded_error.is_none() {
+                    if let Some(decoded) = registry.decode_error(Some(to), &output) {
+                        self.revert_reason = Some(revert_reason(&decoded));
+                        self.decoded_error = Some(decoded);
+                    }
+                }
+            } else {
+                self.decoded = registry.decode_call(to, &input, Some(&output));
+            }
+        }
+
+        for log in &mut self.logs {
+            log.apply_abis(registry);
+        }
+
+        for call in &mut self.calls {
+            call.apply_abis(registry);
+        }
+    }
+}
+
+impl LogEntry {
+    /// Decodes this log with the supplied ABI of the emitting contract, if any.
+    pub fn apply_abis(&mut self, registry: &AbiRegistry<'_>) {
+        let Ok(address) = Address::from_str(&self.address) else { return };
+        let topics: Vec<B256> = self
+            .topics
+            .iter()
+            .filter_map(|topic| B256::from_str(topic).ok())
+            .collect();
+        let data = hex::decode(&self.data).unwrap_or_default();
+
+        if let Some(decoded) = registry.decode_event(address, &topics, &data) {
+            self.decoded = Some(decoded);
+        }
+    }
+}
+
 impl From<&AlloyCallFrame> for CallFrame {
     fn from(frame: &AlloyCallFrame) -> Self {
         Self::from_alloy_call_frame(frame, 0)
@@ -198,7 +281,7 @@ impl TryFrom<MuxFrame> for CallTraceResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_primitives::{Address, Bytes, U256};
+    use alloy_primitives::{Bytes, U256};
 
     #[test]
     fn test_alloy_call_frame_to_call_trace_response() {
@@ -325,4 +408,31 @@ mod tests {
         assert_eq!(response.root_call.calls[0].depth, 1);
         assert_eq!(response.root_call.calls[0].calls[0].depth, 2);
     }
+
+    #[test]
+    fn test_reverted_call_frame_decodes_panic() {
+        let mut output = vec![0x4e, 0x48, 0x7b, 0x71];
+        output.extend(U256::from(0x12).to_be_bytes::<32>());
+
+        let frame = AlloyCallFrame {
+            from: Address::ZERO,
+            to: Some(Address::repeat_byte(1)),
+            gas: U256::from(50000),
+            gas_used: U256::from(30000),
+            input: Default::default(),
+            output: Some(output.into()),
+            value: None,
+            typ: "CALL".to_string(),
+            error: Some("execution reverted".to_string()),
+            revert_reason: None,
+            calls: vec![],
+            logs: vec![],
+        };
+
+        let call = CallFrame::from(frame);
+
+        assert_eq!(call.decoded_error.as_ref().map(|e| e.name.as_str()), Some("Panic"));
+        assert_eq!(call.revert_reason.as_deref(), Some("Panic(0x12): Division or modulo by zero"));
+        assert!(call.output.starts_with("0x4e487b71"));
+    }
 }
@@ -11,12 +11,24 @@ pub use struct_log::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use alloy_primitives::Address;
+use alloy_rpc_types_eth::AccessList;
 use alloy_rpc_types_trace::geth::{
     mux::MuxFrame, CallFrame as AlloyCallFrame, DefaultFrame, FourByteFrame, GethTrace,
     PreStateFrame,
 };
 
-use crate::{handlers::trace::TracingResult, types::TransactionReceiptInfo};
+use crate::{
+    handlers::trace::{TraceConfig, TracingResult},
+    services::{
+        decoder::AbiRegistry,
+        hyperevm::{
+            gas_breakdown::{compute_gas_breakdown, IntrinsicGas},
+            storage_access::storage_accesses,
+        },
+    },
+    types::{GasBreakdown, TransactionReceiptInfo},
+};
 
 /// Container for all tracer results.
 #[derive(Debug, Default, Serialize, Deserialize, Clone, ToSchema)]
@@ -42,6 +54,14 @@ pub struct TracerResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "4byteTracer")]
     pub four_byte_tracer: Option<FourByteResponse>,
+
+    /// Gas used by category (hex encoded), computed from the struct logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_breakdown: Option<GasBreakdown<String>>,
+
+    /// Storage slots read and written by each contract, extracted from the struct logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_accesses: Option<Vec<ContractStorageAccesses>>,
 }
 
 impl TracerResponse {
@@ -55,6 +75,55 @@ impl TracerResponse {
             struct_logger.clean();
         }
     }
+
+    /// Computes the gas breakdown from the struct logs.
+    ///
+    /// Must be called before [`Self::clean_struct_logger`], which drops the logs.
+    pub fn compute_gas_breakdown(&mut self, intrinsic: IntrinsicGas) {
+        let Some(struct_logger) = &self.struct_logger else { return };
+        let Some(logs) = &struct_logger.inner else { return };
+
+        let breakdown = compute_gas_breakdown(logs, intrinsic, struct_logger.total_gas);
+        self.gas_breakdown = Some(breakdown.to_hex_breakdown());
+    }
+
+    /// Extracts the storage accesses from the struct logs, `target` being the
+    /// contract executed by the transaction and `access_list` its access list.
+    /// The values of the slots before the transaction are read from the
+    /// prestate tracer, if any.
+    ///
+    /// Must be called before [`Self::clean_struct_logger`], which drops the logs.
+    pub fn compute_storage_accesses(&mut self, target: Address, access_list: Option<&AccessList>) {
+        let Some(logs) = self
+            .struct_logger
+            .as_ref()
+            .and_then(|logger| logger.inner.as_ref())
+        else {
+            return;
+        };
+
+        let prestate = self
+            .prestate_tracer
+            .as_ref()
+            .map(PrestateTraceResponse::storage_values)
+            .unwrap_or_default();
+        self.storage_accesses = storage_accesses(logs, target, access_list, &prestate);
+    }
+
+    /// Drops the prestate tracer added by [`TraceConfig::with_storage_prestate`]
+    /// when `config` did not request it.
+    pub fn drop_storage_prestate(&mut self, config: &TraceConfig) {
+        if config.tracers.prestate_tracer.is_none() {
+            self.prestate_tracer = None;
+        }
+    }
+
+    /// Decodes the call tracer frames with the supplied ABIs.
+    pub fn apply_abis(&mut self, registry: &AbiRegistry<'_>) {
+        if let Some(call_tracer) = &mut self.call_tracer {
+            call_tracer.root_call.apply_abis(registry);
+        }
+    }
 }
 
 impl From<TracingResult> for TracerResponse {
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
+use alloy_primitives::{Address, B256};
 use alloy_rpc_types_trace::geth::{
     mux::MuxFrame, AccountState as AlloyAccountState, DiffMode, GethDebugBuiltInTracerType,
     PreStateFrame, PreStateMode,
@@ -65,6 +66,27 @@ pub struct AccountState {
     pub storage: HashMap<String, String>,
 }
 
+impl PrestateTraceResponse {
+    /// Returns the values of the storage slots before the transaction, keyed by
+    /// account and slot.
+    pub fn storage_values(&self) -> HashMap<(Address, B256), B256> {
+        let accounts = match self {
+            Self::Default(mode) => &mode.accounts,
+            Self::Diff(diff) => &diff.pre,
+        };
+
+        accounts
+            .iter()
+            .filter_map(|(address, state)| Some((Address::from_str(address).ok()?, state)))
+            .flat_map(|(address, state)| {
+                state.storage.iter().filter_map(move |(slot, value)| {
+                    Some(((address, B256::from_str(slot).ok()?), B256::from_str(value).ok()?))
+                })
+            })
+            .collect()
+    }
+}
+
 impl From<PreStateFrame> for PrestateTraceResponse {
     fn from(frame: PreStateFrame) -> Self {
         match frame {
@@ -4,6 +4,14 @@ use alloy_rpc_types_trace::geth::{DefaultFrame, StructLog as AlloyStructLog};
 use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use utoipa::ToSchema;
 
+use crate::{
+    services::metrics::metrics,
+    types::{
+        RegularStorageSlotAccess, SimpleStorageValue, StorageSlotAccess, StorageValue,
+        TransientSlotAccess,
+    },
+};
+
 /// API response for the struct log tracer.
 ///
 /// This is the default tracer and the most verbose one.
@@ -61,8 +69,8 @@ impl From<DefaultFrame> for StructLogResponse {
         //
         // We track state transitions to count distinct refund events and accumulate totals
         for log in &frame.struct_logs {
-            if log.refund_counter.is_some() && last_gas_refunded_state == 0 {
-                total_gas_refunded += log.refund_counter.unwrap();
+            if let (Some(refund), 0) = (log.refund_counter, last_gas_refunded_state) {
+                total_gas_refunded += refund;
                 total_refund_counter += 1;
                 last_gas_refunded_state = 1;
                 continue;
@@ -90,6 +98,7 @@ impl From<DefaultFrame> for StructLogResponse {
             .collect();
 
         let total_opcode = struct_logs.len() as u64;
+        metrics().record_struct_logs(struct_logs.len());
 
         Self {
             inner: Some(struct_logs),
@@ -103,6 +112,23 @@ impl From<DefaultFrame> for StructLogResponse {
     }
 }
 
+/// Storage accessed by a contract, extracted from the struct logs.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractStorageAccesses {
+    /// Contract address.
+    #[schema(example = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")]
+    pub address: String,
+    /// Storage accesses (`SLOAD` and `SSTORE`), in execution order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<StorageSlotAccess<StorageValue>>)]
+    pub storage: Vec<RegularStorageSlotAccess>,
+    /// Transient storage accesses (`TLOAD` and `TSTORE`, EIP-1153), in execution order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<StorageSlotAccess<SimpleStorageValue>>)]
+    pub transient_storage: Vec<TransientSlotAccess>,
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct StructLog {
     /// program counter
@@ -236,10 +236,27 @@ impl TracingStrategy {
             .collect::<Vec<_>>();
         let alloy_state_context: AlloyStateContext = state_context.into();
 
+        self.execute_bundles(alloy_bundles, alloy_state_context, None, trace_fn)
+            .await
+    }
+
+    /// Execute trace of Alloy bundles with optional state overrides
+    pub async fn execute_bundles<F, Fut, E>(
+        &self,
+        bundles: Vec<AlloyBundle>,
+        state_context: AlloyStateContext,
+        state_overrides: Option<StateOverride>,
+        trace_fn: F,
+    ) -> Result<TracingResultMany, RpcError>
+    where
+        F: Fn(Vec<AlloyBundle>, AlloyStateContext, GethDebugTracingCallOptions) -> Fut + Clone,
+        Fut: Future<Output = Result<Vec<GethTrace>, E>>,
+        E: Into<RpcError>,
+    {
         match self {
             Self::StructLoggerOnly(options) | Self::TracersOnly(options) => {
-                let call_options = self.create_call_options(options, None, None);
-                let trace = trace_fn(alloy_bundles, alloy_state_context, call_options)
+                let call_options = self.create_call_options(options, state_overrides, None);
+                let trace = trace_fn(bundles, state_context, call_options)
                     .await
                     .map_err(|e| e.into())?;
 
@@ -247,15 +264,15 @@ impl TracingStrategy {
             }
             Self::Hybrid { tracers_options, struct_logger_options } => {
                 // Create call options for both traces
-                let tracers_call_options = self.create_call_options(tracers_options, None, None);
+                let tracers_call_options =
+                    self.create_call_options(tracers_options, state_overrides.clone(), None);
                 let struct_logger_call_options =
-                    self.create_call_options(struct_logger_options, None, None);
+                    self.create_call_options(struct_logger_options, state_overrides, None);
 
                 // Execute both calls concurrently
-                let tracers_fut =
-                    trace_fn(alloy_bundles.clone(), alloy_state_context, tracers_call_options);
+                let tracers_fut = trace_fn(bundles.clone(), state_context, tracers_call_options);
                 let struct_logger_fut =
-                    trace_fn(alloy_bundles, alloy_state_context, struct_logger_call_options);
+                    trace_fn(bundles, state_context, struct_logger_call_options);
 
                 let (tracers_trace, struct_logger_trace) =
                     tokio::try_join!(tracers_fut, struct_logger_fut).map_err(|e| e.into())?;
@@ -0,0 +1,129 @@
+use crate::{
+    define_routes,
+    error::{ApiError, ApiResult},
+    handlers::common::{ApiResponse, Handler},
+    services::{
+        api_keys::ApiKey,
+        telemetry::request_id,
+        usage::{Granularity, KeyUsage, Usage, UsageBucket, UsageMeter, UsageQuery},
+    },
+};
+use actix_web::{
+    body::{BodySize, MessageBody},
+    web, HttpMessage, HttpRequest, HttpResponse,
+};
+use std::sync::Arc;
+use tracing::warn;
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_usage,
+    ),
+    components(
+        schemas(
+            Usage,
+            UsageBucket,
+            KeyUsage,
+            Granularity,
+            ApiResponse<KeyUsage>,
+        ),
+    ),
+    tags(
+        (name = "usage", description = "Usage endpoints for monitoring the consumption of an API key")
+    )
+)]
+pub struct UsageApiDoc;
+
+pub struct UsageHandler {
+    meter: Option<Arc<UsageMeter>>,
+}
+
+impl UsageHandler {
+    pub const fn new(meter: Option<Arc<UsageMeter>>) -> Self {
+        Self { meter }
+    }
+
+    pub fn into_app_data(self) -> web::Data<Self> {
+        web::Data::new(self)
+    }
+}
+
+/// Returns the meter, if usage is recorded.
+pub(crate) fn usage_meter(meter: &Option<Arc<UsageMeter>>) -> ApiResult<&UsageMeter> {
+    meter
+        .as_deref()
+        .ok_or_else(|| ApiError::service_unavailable("Usage metering is not available"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tag = "usage",
+    summary = "Get the usage of the API key",
+    description = "Get the requests by endpoint, simulated calls, gas simulated, trace bytes returned and upstream RPC calls of the API key of the request, by hour or day (UTC)",
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Usage of the API key", body = ApiResponse<KeyUsage>),
+        (status = 400, description = "Invalid request parameters", body = ApiResponse<String>),
+        (status = 401, description = "Missing or invalid API key", body = ApiResponse<String>),
+        (status = 503, description = "Usage metering is not available", body = ApiResponse<String>)
+    )
+)]
+async fn get_usage(
+    handler: web::Data<UsageHandler>,
+    api_key: Option<web::ReqData<ApiKey>>,
+    query: web::Query<UsageQuery>,
+) -> ApiResult<HttpResponse> {
+    let api_key =
+        api_key.ok_or_else(|| ApiError::unauthorized("usage is only recorded for API keys"))?;
+    let periods = query.periods()?;
+
+    let usage = usage_meter(&handler.meter)?
+        .usage(&api_key.id, &api_key.name, query.granularity, periods)
+        .await?;
+    Ok(ApiResponse::success(usage, request_id()).into())
+}
+
+/// Records the usage of a request authenticated with an API key, in the
+/// background so that it never delays the response. See [`define_routes`].
+pub(crate) fn record_request(
+    request: &HttpRequest,
+    endpoint: &str,
+    mut usage: Usage,
+    result: &ApiResult<HttpResponse>,
+) {
+    let Some(meter) = request.app_data::<web::Data<UsageMeter>>().cloned() else {
+        return;
+    };
+    let Some(api_key) = request.extensions().get::<ApiKey>().cloned() else {
+        return;
+    };
+
+    usage.requests.insert(endpoint.to_string(), 1);
+    if let (true, Ok(response)) = (endpoint.starts_with("/trace"), result) {
+        if let BodySize::Sized(size) = response.body().size() {
+            usage.trace_bytes = size;
+        }
+    }
+
+    actix_web::rt::spawn(async move {
+        if let Err(e) = meter.record(&api_key, &usage).await {
+            warn!(target: "altitrace::api::usage", name = api_key.name, error = %e, "Failed to record usage");
+        }
+    });
+}
+
+define_routes!(
+    UsageHandler,
+    "/usage",
+    "" => {
+        method: get,
+        handler: get_usage,
+        params: {
+            api_key: Option<web::ReqData<ApiKey>>,
+            query: web::Query<UsageQuery>
+        }
+    },
+);
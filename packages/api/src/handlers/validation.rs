@@ -1,3 +1,4 @@
+use crate::types::ContractAbis;
 use alloy_primitives::Address;
 use regex::Regex;
 use std::{str::FromStr, sync::LazyLock};
@@ -197,6 +198,15 @@ pub fn validate_hex_or_decimal(value: &str) -> Result<(), ValidationError> {
     validate_uint256(value)
 }
 
+/// Validates that every key of a contract ABI map is a valid EVM address.
+pub fn validate_abi_addresses(abis: &ContractAbis) -> Result<(), ValidationError> {
+    if abis.keys().all(|address| validate_address(address).is_ok()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("ABI map keys must be valid contract addresses"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
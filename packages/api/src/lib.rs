@@ -10,15 +10,19 @@ pub mod version;
 
 use alloy_transport_http::reqwest::Url;
 use chrono::{DateTime, Utc};
-pub use services::{service::HyperEvmService, RedisCache, RpcProvider};
-use std::{sync::LazyLock, time::SystemTime};
+use services::{api_keys::ApiKeyStore, usage::UsageMeter};
+pub use services::{service::HyperEvmService, Cache, RedisCache, RpcProvider};
+use std::{
+    sync::{Arc, LazyLock},
+    time::SystemTime,
+};
 pub mod handlers;
 pub mod macros;
 pub use routes::*;
 
 pub use utils::validation::*;
 
-use handlers::{Handler, HealthHandler, OpenApiHandler};
+use handlers::{AdminHandler, Handler, HealthHandler, MetricsHandler, OpenApiHandler};
 mod middlewares;
 mod tracing_log;
 
@@ -28,12 +32,19 @@ use actix_web::{
 };
 pub use config::*;
 
-use ::tracing::info;
-use clap::{command, Parser};
+use ::tracing::{info, warn};
+use clap::Parser;
 use error::{ApiResult, CacheError};
 use eyre::eyre;
 use futures::Future;
-use middlewares::{auth::AuthMiddlewareFactory, cors::CorsMiddlewareFactory};
+use middlewares::{
+    api_key::ApiKeyMiddlewareFactory,
+    auth::AuthMiddlewareFactory,
+    cors::CorsMiddlewareFactory,
+    metrics::MetricsMiddlewareFactory,
+    rate_limit::{RateLimitMiddlewareFactory, RateLimiter},
+    request_tracing::RequestTracingMiddlewareFactory,
+};
 use tracing_log::{init_tracing, LogFileConfig, LogFormat, LogsArgs};
 
 use crate::version::{LONG_VERSION, SHORT_VERSION};
@@ -71,12 +82,16 @@ where
         args.log_args.max_log_files,
     );
 
-    let _file_guard = init_tracing(LogFormat::Text, Some((args.log_args.logs_format, file_config)))
-        .map_err(|e| eyre!("Failed to initialize tracing: {e}"))?;
-
     let app_config = AppConfig::default();
+    let _tracing_guard = init_tracing(
+        LogFormat::Text,
+        Some((args.log_args.logs_format, file_config)),
+        &app_config.telemetry,
+    )
+    .map_err(|e| eyre!("Failed to initialize tracing: {e}"))?;
+
     let bind_address = format!("{}:{}", app_config.server.host, app_config.server.port);
-    let redis_cache = Data::new(RedisCache::init_cache(&app_config.redis).await?);
+    let cache = Data::new(Cache::init(&app_config.cache, &app_config.redis).await);
     info!(target: "altitrace::api", "Starting server on {bind_address}");
     let start_time = *START_TIME;
     info!(target: "altitrace::api", "Application started at {:?}", DateTime::<Utc>::from(start_time));
@@ -87,67 +102,120 @@ where
     let rpc_provider =
         RpcProvider::new(Url::parse(rpc_url.as_str()).map_err(|_| eyre!("Invalid URL"))?).await?;
 
-    let hyperevm_service = Data::new(HyperEvmService::new(rpc_provider));
+    let hyperevm_service = Data::new(
+        HyperEvmService::new(rpc_provider)
+            .with_cache(cache.get_ref().clone())
+            .with_simulation_ttl(app_config.redis.simulation_ttl),
+    );
 
-    let auth_middleware = api_config
+    let api_keys = Arc::new(ApiKeyStore::from_config(&app_config.api, &app_config.redis));
+    let api_key_middleware = ApiKeyMiddlewareFactory::new(api_keys.clone());
+    let usage_meter = UsageMeter::from_config(&app_config.redis).map(Arc::new);
+    let admin_middleware = app_config
         .api
-        .auth_token
-        .clone()
-        .map(AuthMiddlewareFactory::new);
+        .admin_token()
+        .map(|token| AuthMiddlewareFactory::new(token.to_string()));
+
+    let rate_limit_middleware = match api_config.api.rate_limit() {
+        Some(config) => match RateLimiter::new(&app_config.redis.url, config) {
+            Ok(limiter) => RateLimitMiddlewareFactory::new(limiter),
+            Err(e) => {
+                warn!(target: "altitrace::api", error = %e, "Invalid Redis configuration, rate limiting disabled");
+                RateLimitMiddlewareFactory::disabled()
+            }
+        },
+        None => RateLimitMiddlewareFactory::disabled(),
+    };
+
+    // Metrics on a separate server, outside of the authentication of the API
+    let metrics_server = if app_config.metrics.enabled {
+        let metrics_address = format!("{}:{}", app_config.metrics.host, app_config.metrics.port);
+        info!(target: "altitrace::api", "Serving metrics on {metrics_address}");
+        let server = HttpServer::new(|| {
+            App::new()
+                .app_data(MetricsHandler::new().into_app_data())
+                .configure(MetricsHandler::configure)
+        })
+        .workers(1)
+        .bind(metrics_address)
+        .map_err(|e| eyre!("Failed to start metrics server: {e}"))?
+        .run();
+        Some(server)
+    } else {
+        None
+    };
+    let metrics_handle = metrics_server.as_ref().map(|server| server.handle());
+    if let Some(metrics_server) = metrics_server {
+        tokio::spawn(metrics_server);
+    }
 
     let server = HttpServer::new(move || {
-        let auth_middleware_clone = auth_middleware.clone();
-
         // Configure CORS middleware
         let cors_middleware = CorsMiddlewareFactory::create_cors_middleware(
             &api_config.api.cors,
             &api_config.environment,
         );
 
-        App::new()
+        let mut app = App::new()
             .wrap(cors_middleware)
+            .wrap(MetricsMiddlewareFactory)
+            .wrap(RequestTracingMiddlewareFactory)
             .app_data(api_config.clone())
-            .app_data(redis_cache.clone())
-            .service(
-                web::scope("/v1")
-                    .service(
-                        // Health check without middleware
-                        web::scope("/status").configure(|cfg| {
-                            let health_handler = HealthHandler::new(redis_cache.clone());
-                            cfg.app_data(health_handler.into_app_data())
-                                .configure(HealthHandler::configure);
+            .app_data(cache.clone());
+        // Usage of the API keys is recorded by the routes, see `define_routes`
+        if let Some(usage_meter) = usage_meter.clone() {
+            app = app.app_data(Data::from(usage_meter));
+        }
+        app.service(
+            web::scope("/v1")
+                .service(
+                    // Health check without middleware
+                    web::scope("/status").configure(|cfg| {
+                        let health_handler = HealthHandler::new(cache.clone());
+                        cfg.app_data(health_handler.into_app_data())
+                            .configure(HealthHandler::configure);
+                    }),
+                )
+                .service(
+                    // OpenAPI with auth middleware
+                    web::scope("/openapi")
+                        .wrap(api_key_middleware.clone().authenticate_only())
+                        .configure(|cfg| {
+                            let api_key_enabled = api_key_middleware.is_enabled();
+                            let openapi_handler =
+                                OpenApiHandler::new(api_key_enabled).with_url(&api_config);
+                            cfg.app_data(openapi_handler.into_app_data())
+                                .configure(OpenApiHandler::configure);
                         }),
-                    )
-                    .service(
-                        // OpenAPI with auth middleware
-                        web::scope("/openapi")
-                            .wrap(
-                                auth_middleware_clone
-                                    .clone()
-                                    .unwrap_or_else(|| AuthMiddlewareFactory::new(String::new())),
-                            )
-                            .configure(|cfg| {
-                                let api_key_enabled = auth_middleware_clone
-                                    .as_ref()
-                                    .map(|auth| auth.is_enabled())
-                                    .unwrap_or(false);
-                                let openapi_handler =
-                                    OpenApiHandler::new(api_key_enabled).with_url(&api_config);
-                                cfg.app_data(openapi_handler.into_app_data())
-                                    .configure(OpenApiHandler::configure);
-                            }),
-                    )
-                    .service(
-                        web::scope("")
-                            .wrap(
-                                auth_middleware_clone
-                                    .unwrap_or_else(|| AuthMiddlewareFactory::new(String::new())),
+                )
+                .configure(|cfg| {
+                    // Admin endpoints with the admin token, only if it is set
+                    if let Some(admin_middleware) = admin_middleware.clone() {
+                        let admin_handler =
+                            AdminHandler::new(api_keys.clone()).with_usage(usage_meter.clone());
+                        cfg.service(
+                            web::scope("/admin")
+                                .wrap(admin_middleware)
+                                .app_data(admin_handler.into_app_data())
+                                .configure(AdminHandler::configure),
+                        );
+                    }
+                })
+                .service(
+                    web::scope("")
+                        .wrap(rate_limit_middleware.clone())
+                        .wrap(api_key_middleware.clone())
+                        .configure(|cfg| {
+                            init_routes(
+                                cfg,
+                                cache.clone(),
+                                hyperevm_service.clone(),
+                                api_config.cache.clone(),
+                                usage_meter.clone(),
                             )
-                            .configure(|cfg| {
-                                init_routes(cfg, redis_cache.clone(), hyperevm_service.clone())
-                            }),
-                    ),
-            )
+                        }),
+                ),
+        )
     })
     .workers(num_cpus)
     .bind(bind_address)
@@ -162,5 +230,8 @@ where
             info!(target: "altitrace::api", "Received shutdown signal, stopping server");
         }
     }
+    if let Some(metrics_handle) = metrics_handle {
+        metrics_handle.stop(true).await;
+    }
     Ok(())
 }
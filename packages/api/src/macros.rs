@@ -19,6 +19,7 @@ macro_rules! define_routes {
                         $(.route(
                             $route,
                             web::$method().to(|
+                                http_request: actix_web::HttpRequest,
                                 handler: web::Data<$handler>,
                                 $($(
                                     $param: $param_type,
@@ -34,12 +35,12 @@ macro_rules! define_routes {
 
                                 let start = std::time::Instant::now();
 
-                                let result = $handler_fn(
+                                let (result, usage) = $crate::services::usage::metered($handler_fn(
                                     handler,
                                     $($(
                                         $param,
                                     )*)?
-                                ).await;
+                                )).await;
 
                                 let duration = start.elapsed();
 
@@ -51,6 +52,13 @@ macro_rules! define_routes {
                                     "Query completed"
                                 );
 
+                                $crate::handlers::usage::record_request(
+                                    &http_request,
+                                    concat!($scope, $route),
+                                    usage,
+                                    &result,
+                                );
+
                                 result
                             })
                         ))*
@@ -0,0 +1,235 @@
+use crate::{
+    error::ApiError,
+    services::api_keys::{ApiKey, ApiKeyStore},
+};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, HeaderMap},
+    Error, HttpMessage,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::{rc::Rc, sync::Arc};
+use tracing::{trace, warn};
+
+/// Authenticates requests with the API keys of the [`ApiKeyStore`].
+///
+/// The key of an authenticated request is added to its extensions, so that
+/// handlers can check the scopes depending on the request body (tracers, batch
+/// size) with `Option<web::ReqData<ApiKey>>`. Endpoint scopes and daily quotas
+/// are enforced here.
+#[derive(Clone)]
+pub(crate) struct ApiKeyMiddlewareFactory {
+    store: Arc<ApiKeyStore>,
+    enforce_scopes: bool,
+}
+
+impl ApiKeyMiddlewareFactory {
+    pub(crate) const fn new(store: Arc<ApiKeyStore>) -> Self {
+        Self { store, enforce_scopes: true }
+    }
+
+    /// Only authenticates requests, without enforcing the endpoint scopes and
+    /// daily quotas (e.g. for the documentation).
+    pub(crate) const fn authenticate_only(mut self) -> Self {
+        self.enforce_scopes = false;
+        self
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.store.is_enabled()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ApiKeyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            enforce_scopes: self.enforce_scopes,
+        }))
+    }
+}
+
+pub(crate) struct ApiKeyMiddleware<S> {
+    service: Rc<S>,
+    store: Arc<ApiKeyStore>,
+    enforce_scopes: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let store = self.store.clone();
+        let enforce_scopes = self.enforce_scopes;
+
+        Box::pin(async move {
+            if !store.is_enabled() {
+                return service.call(req).await;
+            }
+
+            let api_key = authenticate(&store, &req).await?;
+            if !enforce_scopes {
+                return service.call(req).await;
+            }
+            api_key.authorize_endpoint(req.match_info().unprocessed())?;
+
+            match store.consume_quota(&api_key).await {
+                Ok(None) => {}
+                Ok(Some(retry_after)) => {
+                    warn!(target: "altitrace::middlewares::api_key", name = api_key.name, "Daily quota exhausted");
+                    return Err(ApiError::rate_limit_exceeded(Some(retry_after)).into());
+                }
+                Err(e) => {
+                    // Never fail requests because the quota counters are unavailable
+                    warn!(target: "altitrace::middlewares::api_key", name = api_key.name, error = %e, "Quota check failed, allowing request");
+                }
+            }
+
+            trace!(target: "altitrace::middlewares::api_key", name = api_key.name, "Authenticated request");
+            req.extensions_mut().insert(api_key);
+            service.call(req).await
+        })
+    }
+}
+
+/// Header carrying the API key, as an alternative to `Authorization`.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Returns the API key sent in the `Authorization` (as a bearer token or not)
+/// or `X-API-Key` header.
+pub(crate) fn request_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .or_else(|| headers.get(API_KEY_HEADER))
+        .and_then(|h| h.to_str().ok())
+        .map(|token| token.trim_start_matches("Bearer ").trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Returns the API key of the request.
+async fn authenticate(store: &ApiKeyStore, req: &ServiceRequest) -> Result<ApiKey, ApiError> {
+    let key =
+        request_api_key(req.headers()).ok_or_else(|| ApiError::unauthorized("missing API key"))?;
+
+    match store.authenticate(key).await {
+        Ok(Some(api_key)) => Ok(api_key),
+        Ok(None) => {
+            warn!(target: "altitrace::middlewares::api_key", path = req.path(), "Unauthorized API request");
+            Err(ApiError::unauthorized("invalid API key"))
+        }
+        Err(e) => {
+            warn!(target: "altitrace::middlewares::api_key", error = %e, "API key lookup failed");
+            Err(ApiError::service_unavailable("API keys cannot be verified"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_keys::{hash_key, ApiKeyConfig, KeyScopes};
+    use actix_web::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn test_handler(key: Option<web::ReqData<ApiKey>>) -> String {
+        key.map(|key| key.name.clone()).unwrap_or_default()
+    }
+
+    fn store(keys: Vec<ApiKeyConfig>) -> Arc<ApiKeyStore> {
+        Arc::new(ApiKeyStore::new(keys, None))
+    }
+
+    #[actix_web::test]
+    async fn test_api_key_middleware() {
+        let keys = vec![
+            ApiKeyConfig {
+                name: "admin".to_string(),
+                key_hash: hash_key("admin-key"),
+                scopes: KeyScopes::default(),
+            },
+            ApiKeyConfig {
+                name: "tracer".to_string(),
+                key_hash: hash_key("trace-key"),
+                scopes: KeyScopes { endpoints: vec!["/trace".to_string()], ..Default::default() },
+            },
+        ];
+        let app = test::init_service(
+            App::new()
+                .wrap(ApiKeyMiddlewareFactory::new(store(keys)))
+                .route("/trace/tx", web::get().to(test_handler))
+                .route("/simulate", web::get().to(test_handler)),
+        )
+        .await;
+
+        let call = |uri: &'static str, key: Option<&'static str>| {
+            let mut req = TestRequest::get().uri(uri);
+            if let Some(key) = key {
+                req = req.insert_header((header::AUTHORIZATION, format!("Bearer {}", key)));
+            }
+            req.to_request()
+        };
+
+        // The key is available to the handlers
+        let resp = test::call_service(&app, call("/simulate", Some("admin-key"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(test::read_body(resp).await, "admin");
+
+        let resp = test::call_service(&app, call("/trace/tx", Some("trace-key"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Out of the endpoints of the key
+        let err = test::try_call_service(&app, call("/simulate", Some("trace-key")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::get()
+            .uri("/trace/tx")
+            .insert_header((API_KEY_HEADER, "trace-key"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(resp).await, "tracer");
+
+        // Unknown or missing key
+        for req in [call("/simulate", Some("wrong-key")), call("/simulate", None)] {
+            let err = test::try_call_service(&app, req).await.unwrap_err();
+            assert_eq!(err.as_response_error().status_code(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_no_keys() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ApiKeyMiddlewareFactory::new(store(vec![])))
+                .route("/simulate", web::get().to(test_handler)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, TestRequest::get().uri("/simulate").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
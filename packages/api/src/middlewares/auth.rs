@@ -19,12 +19,6 @@ impl AuthMiddlewareFactory {
     }
 }
 
-impl AuthMiddlewareFactory {
-    pub(crate) fn is_enabled(&self) -> bool {
-        !self.api_token.is_empty()
-    }
-}
-
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddlewareFactory
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
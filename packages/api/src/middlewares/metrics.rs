@@ -0,0 +1,109 @@
+use crate::services::metrics::metrics;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::{rc::Rc, time::Instant};
+
+/// Endpoint label of the requests matching no route, so that unknown paths do
+/// not create new series.
+const UNMATCHED_ENDPOINT: &str = "unmatched";
+
+/// Records the request metrics: count and latency by endpoint, method and
+/// status, and requests in flight.
+///
+/// Endpoints are labeled with their route pattern (e.g.
+/// `/v1/simulate/{simulation_id}`) rather than their path.
+#[derive(Clone, Default)]
+pub(crate) struct MetricsMiddlewareFactory;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = MetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub(crate) struct MetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let _in_flight = metrics().track_request();
+            let start = Instant::now();
+            let endpoint = req
+                .match_pattern()
+                .unwrap_or_else(|| UNMATCHED_ENDPOINT.to_string());
+            let method = req.method().clone();
+
+            let result = service.call(req).await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            metrics().record_request(&endpoint, method.as_str(), status.as_u16(), start.elapsed());
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn test_handler() -> actix_web::Result<&'static str> {
+        Ok("success")
+    }
+
+    #[actix_web::test]
+    async fn test_request_metrics() {
+        let app = test::init_service(
+            App::new()
+                .wrap(MetricsMiddlewareFactory)
+                .route("/metrics-test/{id}", web::get().to(test_handler)),
+        )
+        .await;
+
+        let resp =
+            test::call_service(&app, TestRequest::get().uri("/metrics-test/42").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, TestRequest::get().uri("/unknown").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let encoded = metrics().encode();
+        assert!(encoded.contains(
+            r#"altitrace_http_requests_total{endpoint="/metrics-test/{id}",method="GET",status="200"} 1"#
+        ));
+        assert!(encoded.contains(r#"endpoint="unmatched",method="GET",status="404""#));
+    }
+}
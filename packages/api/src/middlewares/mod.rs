@@ -1,2 +1,6 @@
+pub(crate) mod api_key;
 pub(crate) mod auth;
 pub(crate) mod cors;
+pub(crate) mod metrics;
+pub(crate) mod rate_limit;
+pub(crate) mod request_tracing;
@@ -0,0 +1,434 @@
+//! Rate limiting of the API.
+//!
+//! Clients are identified by their authenticated API key, or by their IP address
+//! for anonymous requests, and limited with a sliding window counter: the requests
+//! of the current fixed window are added to those of the previous window,
+//! weighted by how much of it still overlaps the sliding window. The counters
+//! live in Redis so that every worker and instance of the API shares the limits.
+//! Each request costs the weight of its endpoint, so that expensive traces count
+//! for more than plain simulations.
+
+use crate::{
+    config::RateLimitConfig,
+    error::ApiError,
+    services::{api_keys::ApiKey, metrics::metrics, RedisConnection},
+};
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    Error, HttpMessage, ResponseError,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use redis::AsyncCommands;
+use std::{
+    net::{IpAddr, SocketAddr},
+    rc::Rc,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+/// Maximum cost of requests in the window.
+const LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+
+/// Cost the client can still spend in the window.
+const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// Seconds until the current window ends.
+const RESET_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Outcome of a rate limit check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    /// The request is allowed, leaving `remaining` to spend in the window.
+    Allowed { remaining: u64 },
+    /// The request exceeds the limit and can be retried after `retry_after`
+    /// seconds.
+    Limited { retry_after: u64 },
+}
+
+/// Sliding window state of a client at the time of a request.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    /// Maximum cost of requests in a window.
+    limit: u64,
+    /// Length of a window, in milliseconds.
+    length: u64,
+    /// Time elapsed in the current fixed window, in milliseconds.
+    elapsed: u64,
+    /// Cost spent in the previous fixed window.
+    previous: u64,
+    /// Cost spent in the current fixed window, this request excluded.
+    current: u64,
+}
+
+impl Window {
+    /// Decides whether a request costing `weight` is allowed.
+    fn decide(&self, weight: u64) -> Decision {
+        let Self { limit, length, elapsed, previous, current } = *self;
+        let overlap = length - elapsed;
+        let weighted_previous = mul_div(previous, overlap, length);
+        let used = weighted_previous + current;
+
+        if used + weight <= limit {
+            return Decision::Allowed { remaining: limit - used - weight };
+        }
+
+        // Wait for the previous window to slide out enough, or for the current one
+        // to end if it is already over budget on its own
+        let wait = if current + weight <= limit && previous > 0 {
+            let budget = limit - current - weight;
+            let decayed_at = length - mul_div(budget, length, previous);
+            decayed_at.saturating_sub(elapsed)
+        } else {
+            overlap
+        };
+
+        Decision::Limited { retry_after: wait.div_ceil(1000).max(1) }
+    }
+
+    /// Seconds until the current fixed window ends.
+    const fn reset(&self) -> u64 {
+        (self.length - self.elapsed).div_ceil(1000)
+    }
+}
+
+/// `value * numerator / denominator` without overflowing.
+fn mul_div(value: u64, numerator: u64, denominator: u64) -> u64 {
+    (u128::from(value) * u128::from(numerator) / u128::from(denominator)) as u64
+}
+
+/// Rate limiter keeping its counters in Redis.
+pub(crate) struct RateLimiter {
+    conn: RedisConnection,
+    config: RateLimitConfig,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(redis_url: &str, config: RateLimitConfig) -> redis::RedisResult<Self> {
+        Ok(Self { conn: RedisConnection::open(redis_url)?, config })
+    }
+
+    /// Cost of a request to the route `pattern`.
+    fn weight(&self, pattern: &str) -> u64 {
+        self.config.weights.get(pattern).copied().unwrap_or(1)
+    }
+
+    /// Records a request of `client` costing `weight`, unless it exceeds the
+    /// limit.
+    async fn check(&self, client: &str, weight: u64) -> redis::RedisResult<(Decision, Window)> {
+        let length = self.config.duration * 1000;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let index = now / length;
+        let current_key = format!("rate_limit:{}:{}", client, index);
+        let previous_key = format!("rate_limit:{}:{}", client, index.saturating_sub(1));
+
+        let (current, previous): (u64, Option<u64>) = self
+            .conn
+            .run(async |conn| {
+                redis::pipe()
+                    .atomic()
+                    .incr(&current_key, weight)
+                    .expire(&current_key, (self.config.duration * 2) as i64)
+                    .ignore()
+                    .get(&previous_key)
+                    .query_async(conn)
+                    .await
+            })
+            .await?;
+
+        let window = Window {
+            limit: self.config.requests,
+            length,
+            elapsed: now % length,
+            previous: previous.unwrap_or_default(),
+            current: current.saturating_sub(weight),
+        };
+        let decision = window.decide(weight);
+
+        // Rejected requests do not count against the limit
+        if matches!(decision, Decision::Limited { .. }) {
+            self.conn
+                .run(async |conn| conn.decr::<_, _, ()>(&current_key, weight).await)
+                .await?;
+        }
+
+        Ok((decision, window))
+    }
+}
+
+/// Identifies the client of a request: the API key it was authenticated with
+/// (see [`ApiKeyMiddleware`](super::api_key::ApiKeyMiddleware)), or its IP
+/// address.
+fn client_id(req: &ServiceRequest, trusted_proxies: &[IpAddr]) -> String {
+    if let Some(api_key) = req.extensions().get::<ApiKey>() {
+        return format!("key:{}", api_key.id);
+    }
+    match client_ip(req, trusted_proxies) {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+/// IP address of the client of a request: the peer of the connection, or the
+/// client it forwards the request of (`Forwarded` or `X-Forwarded-For`) if the
+/// peer is a trusted proxy.
+fn client_ip(req: &ServiceRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded = req.connection_info().realip_remote_addr().and_then(|addr| {
+        // The address may include a port
+        addr.parse::<SocketAddr>()
+            .map(|addr| addr.ip())
+            .or_else(|_| addr.parse::<IpAddr>())
+            .ok()
+    });
+    Some(forwarded.unwrap_or(peer))
+}
+
+/// Route pattern of a request relative to the scope the middleware wraps, e.g.
+/// `/simulate/{id}/rerun`, or its remaining path if it matches no route.
+fn route_pattern(req: &ServiceRequest) -> String {
+    let path = req.match_info().as_str();
+    let unprocessed = req.match_info().unprocessed();
+    let scope = &path[..path.len() - unprocessed.len()];
+    req.match_pattern()
+        .and_then(|pattern| pattern.strip_prefix(scope).map(str::to_string))
+        .unwrap_or_else(|| unprocessed.to_string())
+}
+
+#[derive(Clone)]
+pub(crate) struct RateLimitMiddlewareFactory {
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl RateLimitMiddlewareFactory {
+    pub(crate) fn new(limiter: RateLimiter) -> Self {
+        Self { limiter: Some(Arc::new(limiter)) }
+    }
+
+    /// Middleware letting every request through.
+    pub(crate) const fn disabled() -> Self {
+        Self { limiter: None }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware { service: Rc::new(service), limiter: self.limiter.clone() }))
+    }
+}
+
+pub(crate) struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let Some(limiter) = self.limiter.clone() else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) });
+        };
+
+        Box::pin(async move {
+            let client = client_id(&req, &limiter.config.trusted_proxies);
+            let weight = limiter.weight(&route_pattern(&req));
+
+            let check = limiter.check(&client, weight);
+            let (decision, window) = match metrics().redis("rate_limit", check).await {
+                Ok(checked) => checked,
+                Err(e) => {
+                    // Never fail requests because the limiter is unavailable
+                    warn!(target: "altitrace::middlewares::rate_limit", error = %e, "Rate limit check failed, allowing request");
+                    return Ok(service.call(req).await?.map_into_left_body());
+                }
+            };
+
+            let limit_headers = |headers: &mut HeaderMap, remaining: u64| {
+                headers.insert(LIMIT_HEADER, HeaderValue::from(window.limit));
+                headers.insert(REMAINING_HEADER, HeaderValue::from(remaining));
+                headers.insert(RESET_HEADER, HeaderValue::from(window.reset()));
+            };
+
+            match decision {
+                Decision::Allowed { remaining } => {
+                    let mut res = service.call(req).await?;
+                    limit_headers(res.headers_mut(), remaining);
+                    Ok(res.map_into_left_body())
+                }
+                Decision::Limited { retry_after } => {
+                    debug!(target: "altitrace::middlewares::rate_limit", client, weight, retry_after, "Rate limit exceeded");
+                    let mut response =
+                        ApiError::rate_limit_exceeded(Some(retry_after)).error_response();
+                    limit_headers(response.headers_mut(), 0);
+                    Ok(req.into_response(response).map_into_right_body())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api_keys::{KeyScopes, KeySource};
+    use actix_web::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+    use std::collections::HashMap;
+
+    fn window(elapsed: u64, previous: u64, current: u64) -> Window {
+        Window { limit: 10, length: 60_000, elapsed, previous, current }
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        // Half of the previous window still overlaps: 8 / 2 + 3 = 7 spent
+        assert_eq!(window(30_000, 8, 3).decide(1), Decision::Allowed { remaining: 2 });
+        assert_eq!(window(30_000, 8, 3).decide(3), Decision::Allowed { remaining: 0 });
+
+        // 7 + 5 > 10: the previous window must decay to 2, a quarter of it, which
+        // happens 45 seconds into the current window
+        assert_eq!(window(30_000, 8, 3).decide(5), Decision::Limited { retry_after: 15 });
+
+        // The current window alone is over budget
+        assert_eq!(window(15_000, 0, 10).decide(1), Decision::Limited { retry_after: 45 });
+    }
+
+    fn request(peer: &str, headers: &[(&'static str, &'static str)]) -> ServiceRequest {
+        let mut req = TestRequest::get()
+            .uri("/test")
+            .peer_addr(peer.parse().unwrap());
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        req.to_srv_request()
+    }
+
+    #[test]
+    fn test_client_id() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        assert_eq!(client_id(&request("10.0.0.1:4242", &[]), &[]), "ip:10.0.0.1");
+
+        // Forwarded addresses are only trusted from the trusted proxies
+        let forwarded =
+            [("x-forwarded-for", "203.0.113.7"), ("forwarded", "for=\"203.0.113.8:1234\"")];
+        for header in forwarded {
+            assert_eq!(client_id(&request("10.0.0.2:4242", &[header]), &[proxy]), "ip:10.0.0.2");
+        }
+        assert_eq!(
+            client_id(&request("10.0.0.1:4242", &forwarded[..1]), &[proxy]),
+            "ip:203.0.113.7"
+        );
+        assert_eq!(
+            client_id(&request("10.0.0.1:4242", &forwarded[1..]), &[proxy]),
+            "ip:203.0.113.8"
+        );
+
+        // Unverified keys do not identify the client, authenticated ones do
+        let req = request("10.0.0.2:4242", &[("authorization", "Bearer secret")]);
+        assert_eq!(client_id(&req, &[]), "ip:10.0.0.2");
+        req.extensions_mut().insert(ApiKey {
+            id: "config:partner".to_string(),
+            name: "partner".to_string(),
+            scopes: KeyScopes::default(),
+            source: KeySource::Config,
+            created_at: None,
+        });
+        assert_eq!(client_id(&req, &[]), "key:config:partner");
+    }
+
+    async fn test_handler() -> actix_web::Result<&'static str> {
+        Ok("success")
+    }
+
+    #[actix_web::test]
+    async fn test_route_pattern() {
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/v1").service(
+                    web::scope("")
+                        .wrap_fn(|req, srv| {
+                            let pattern = route_pattern(&req);
+                            let res = srv.call(req);
+                            async move {
+                                let mut res = res.await?;
+                                res.headers_mut().insert(
+                                    HeaderName::from_static("x-route"),
+                                    HeaderValue::from_str(&pattern).unwrap(),
+                                );
+                                Ok(res)
+                            }
+                        })
+                        .route("/simulate/{id}/rerun", web::post().to(test_handler)),
+                ),
+            ),
+        )
+        .await;
+
+        let route = async |uri: &str| {
+            let resp = test::call_service(&app, TestRequest::post().uri(uri).to_request()).await;
+            resp.headers()
+                .get("x-route")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(route("/v1/simulate/sim_123/rerun").await, "/simulate/{id}/rerun");
+        assert_eq!(route("/v1/unknown").await, "/unknown");
+    }
+
+    #[actix_web::test]
+    async fn test_unavailable_limiter_allows_requests() {
+        let config = RateLimitConfig {
+            requests: 1,
+            duration: 60,
+            weights: HashMap::new(),
+            trusted_proxies: Vec::new(),
+        };
+        let limiter = RateLimiter::new("redis://127.0.0.1:1", config).unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(RateLimitMiddlewareFactory::new(limiter))
+                .route("/test", web::get().to(test_handler)),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let resp = test::call_service(&app, TestRequest::get().uri("/test").to_request()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(!resp.headers().contains_key(LIMIT_HEADER));
+        }
+    }
+}
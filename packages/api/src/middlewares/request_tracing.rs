@@ -0,0 +1,185 @@
+use crate::services::telemetry::{trace_request_id, with_request_id};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::rc::Rc;
+use tracing::{field::Empty, info_span, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// ID of the request, which is also the ID of its trace.
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Reads the trace context fields from the headers of a request.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Handles each request in a span continuing the trace of its `traceparent`
+/// header, and under the ID of that trace, returned in the `x-request-id`
+/// header and in the responses. See [`crate::services::telemetry`].
+#[derive(Clone, Default)]
+pub(crate) struct RequestTracingMiddlewareFactory;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracingMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestTracingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub(crate) struct RequestTracingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        let parent = TraceContextPropagator::new().extract(&HeaderExtractor(req.headers()));
+        // Spans are named after the route pattern, not the path
+        let route = req.match_pattern();
+        let name = match &route {
+            Some(route) => format!("{} {}", req.method(), route),
+            None => req.method().to_string(),
+        };
+        let span = info_span!(
+            target: "altitrace::api",
+            "request",
+            otel.name = name,
+            otel.kind = "server",
+            otel.status_code = Empty,
+            http.request.method = %req.method(),
+            http.route = route,
+            http.response.status_code = Empty,
+            request_id = Empty,
+        );
+        // The span has not started yet, so it cannot have a parent already
+        let _ = span.set_parent(parent.clone());
+        let request_id = trace_request_id(&span, &parent);
+        span.record("request_id", request_id.as_str());
+
+        // Errors of the inner middlewares are converted to responses within the
+        // request, for them to carry its ID
+        let response = with_request_id(request_id.clone(), async move {
+            service.call(req).await.map_err(|e| {
+                let response = e.error_response();
+                (e, response)
+            })
+        })
+        .instrument(span.clone());
+
+        Box::pin(async move {
+            let set_request_id = |headers: &mut HeaderMap| {
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    headers.insert(REQUEST_ID_HEADER, value);
+                }
+            };
+            let (status, result) = match response.await {
+                Ok(mut res) => {
+                    set_request_id(res.headers_mut());
+                    (res.status(), Ok(res))
+                }
+                Err((e, mut response)) => {
+                    set_request_id(response.headers_mut());
+                    (response.status(), Err(InternalError::from_response(e, response).into()))
+                }
+            };
+
+            span.record("http.response.status_code", status.as_u16());
+            if status.is_server_error() {
+                span.record("otel.status_code", "ERROR");
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::ApiError, services::telemetry::request_id};
+    use actix_web::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn test_handler() -> actix_web::Result<String> {
+        Ok(request_id())
+    }
+
+    async fn failing_handler() -> Result<String, ApiError> {
+        Err(ApiError::service_unavailable("failed"))
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_continues_trace() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestTracingMiddlewareFactory)
+                .route("/test", web::get().to(test_handler))
+                .route("/fail", web::get().to(failing_handler)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/test")
+            .insert_header((
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        // Without a trace, the request gets a new ID, also in its errors
+        let resp = test::call_service(&app, TestRequest::get().uri("/fail").to_request()).await;
+        let request_id = resp
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(request_id.len(), 32);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["request_id"], request_id);
+    }
+}
@@ -1,21 +1,30 @@
 use actix_web::web;
 
 use crate::{
-    handlers::{simulation::SimulationHandler, trace::TraceHandler, Handler},
-    HyperEvmService, RedisCache,
+    config::CacheConfig,
+    handlers::{simulation::SimulationHandler, trace::TraceHandler, Handler, UsageHandler},
+    services::usage::UsageMeter,
+    Cache, HyperEvmService,
 };
+use std::sync::Arc;
 
 /// Initializes the API routes.
 pub fn init_routes(
     cfg: &mut web::ServiceConfig,
-    _redis: web::Data<RedisCache>,
+    cache: web::Data<Cache>,
     hyperevm_service: web::Data<HyperEvmService>,
+    cache_config: CacheConfig,
+    usage_meter: Option<Arc<UsageMeter>>,
 ) {
-    let simulation_handler = SimulationHandler::new(hyperevm_service.clone());
-    let trace_handler = TraceHandler::new(hyperevm_service);
+    let simulation_handler = SimulationHandler::new(hyperevm_service.clone())
+        .with_cache(cache.get_ref().clone(), cache_config.clone());
+    let trace_handler =
+        TraceHandler::new(hyperevm_service).with_cache(cache.get_ref().clone(), cache_config);
 
     cfg.app_data::<web::Data<SimulationHandler>>(simulation_handler.into())
         .configure(SimulationHandler::configure)
         .app_data::<web::Data<TraceHandler>>(trace_handler.into())
-        .configure(TraceHandler::configure);
+        .configure(TraceHandler::configure)
+        .app_data(UsageHandler::new(usage_meter).into_app_data())
+        .configure(UsageHandler::configure);
 }
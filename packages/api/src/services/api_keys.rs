@@ -0,0 +1,543 @@
+//! API keys and their scopes.
+//!
+//! Keys are either declared in the configuration or created at runtime through
+//! the admin endpoints, in which case they are stored in Redis. Only the hash of
+//! a key is ever stored: the key itself is returned once, when it is created or
+//! rotated, and requests are authenticated by hashing the key they carry.
+
+use crate::{
+    config::{ApiConfig, RedisConfig},
+    error::{ApiError, CacheError},
+    services::{metrics::metrics, RedisConnection},
+    ApiResult, CacheResult,
+};
+
+use alloy_primitives::keccak256;
+use chrono::{DateTime, Days, Utc};
+use redis::{aio::MultiplexedConnection, AsyncCommands, RedisResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Redis hash of the stored keys, by key hash.
+const KEYS_KEY: &str = "api_keys";
+
+/// Redis hash of the key hashes, by key name.
+const NAMES_KEY: &str = "api_key_names";
+
+/// Stores a key (`ARGV[3]`) under its hash (`ARGV[2]`) in `KEYS[2]`, unless its
+/// name (`ARGV[1]`) is already taken in `KEYS[1]`. Returns whether it was stored.
+const CREATE_KEY_SCRIPT: &str = r"
+if redis.call('HSETNX', KEYS[1], ARGV[1], ARGV[2]) == 0 then
+    return 0
+end
+redis.call('HSET', KEYS[2], ARGV[2], ARGV[3])
+return 1
+";
+
+/// Replaces the hash `ARGV[2]` of the key named `ARGV[1]` in `KEYS[1]` by
+/// `ARGV[3]`, and the key stored under it in `KEYS[2]` by `ARGV[4]`, unless the
+/// key was rotated or revoked meanwhile. Returns whether it was replaced.
+const ROTATE_KEY_SCRIPT: &str = r"
+if redis.call('HGET', KEYS[1], ARGV[1]) ~= ARGV[2] then
+    return 0
+end
+redis.call('HDEL', KEYS[2], ARGV[2])
+redis.call('HSET', KEYS[2], ARGV[3], ARGV[4])
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[3])
+return 1
+";
+
+/// Removes the key named `ARGV[1]` from `KEYS[1]`, and the key stored under its
+/// hash from `KEYS[2]`. Returns whether it existed.
+const REVOKE_KEY_SCRIPT: &str = r"
+local hash = redis.call('HGET', KEYS[1], ARGV[1])
+if not hash then
+    return 0
+end
+redis.call('HDEL', KEYS[2], hash)
+redis.call('HDEL', KEYS[1], ARGV[1])
+return 1
+";
+
+/// Name of the key of `auth_token`.
+const DEFAULT_KEY_NAME: &str = "default";
+
+/// Prefix of the generated keys.
+const KEY_PREFIX: &str = "alt_";
+
+/// Hash of an API key, as stored.
+pub fn hash_key(key: &str) -> String {
+    format!("{:x}", keccak256(key.as_bytes()))
+}
+
+/// Generates a new random API key.
+fn generate_key() -> String {
+    format!("{}{}{}", KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// What an API key is allowed to do. Every unset field is unrestricted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyScopes {
+    /// Endpoints the key can call, by path prefix (e.g. `/trace`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<String>,
+    /// Tracers the key can use (e.g. `callTracer`, `prestateTracer`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracers: Option<Vec<String>>,
+    /// Maximum number of transactions of a batch request.
+    #[serde(default, alias = "max_batch_size", skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<usize>,
+    /// Maximum number of requests per day (UTC).
+    #[serde(default, alias = "daily_quota", skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<u64>,
+}
+
+/// Where an API key is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// Declared in the configuration, and only changed by editing it.
+    Config,
+    /// Created through the admin endpoints.
+    Redis,
+}
+
+/// API key, without the key itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    /// Unique ID of the key, kept when it is rotated. Quotas are counted by ID,
+    /// so that a key created under the name of a revoked one starts afresh.
+    pub id: String,
+    /// Unique name of the key.
+    pub name: String,
+    #[serde(flatten)]
+    pub scopes: KeyScopes,
+    pub source: KeySource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Checks that the key can call the endpoint at `path`.
+    pub fn authorize_endpoint(&self, path: &str) -> ApiResult<()> {
+        let endpoints = &self.scopes.endpoints;
+        if endpoints.is_empty() ||
+            endpoints
+                .iter()
+                .any(|prefix| path.starts_with(prefix.as_str()))
+        {
+            return Ok(());
+        }
+        Err(ApiError::forbidden(format!("API key '{}' cannot call {}", self.name, path)))
+    }
+
+    /// Checks that the key can use every tracer of `tracers`.
+    pub fn authorize_tracers<'a>(
+        &self,
+        tracers: impl IntoIterator<Item = &'a str>,
+    ) -> ApiResult<()> {
+        let Some(allowed) = &self.scopes.tracers else {
+            return Ok(());
+        };
+        match tracers
+            .into_iter()
+            .find(|tracer| !allowed.iter().any(|allowed| allowed == tracer))
+        {
+            Some(tracer) => Err(ApiError::forbidden(format!(
+                "API key '{}' cannot use the {} tracer",
+                self.name, tracer
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the key can send a batch of `size` transactions.
+    pub fn authorize_batch(&self, size: usize) -> ApiResult<()> {
+        match self.scopes.max_batch_size {
+            Some(max) if size > max => Err(ApiError::forbidden(format!(
+                "API key '{}' is limited to batches of {} transactions, got {}",
+                self.name, max, size
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// API key declared in the configuration, by hash.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    /// Hash of the key, see [`hash_key`].
+    pub key_hash: String,
+    #[serde(flatten)]
+    pub scopes: KeyScopes,
+}
+
+/// Store of the API keys.
+pub struct ApiKeyStore {
+    /// Keys declared in the configuration, by hash.
+    config_keys: HashMap<String, ApiKey>,
+    /// Redis storing the quota counters, and the keys created at runtime.
+    redis: Option<RedisConnection>,
+    /// Whether keys can be created at runtime.
+    runtime_keys: bool,
+}
+
+impl ApiKeyStore {
+    pub fn new(
+        keys: impl IntoIterator<Item = ApiKeyConfig>,
+        redis: Option<RedisConnection>,
+    ) -> Self {
+        let config_keys = keys
+            .into_iter()
+            .map(|key| {
+                let api_key = ApiKey {
+                    id: format!("config:{}", key.name),
+                    name: key.name,
+                    scopes: key.scopes,
+                    source: KeySource::Config,
+                    created_at: None,
+                };
+                (key.key_hash.to_lowercase(), api_key)
+            })
+            .collect();
+        Self { config_keys, redis, runtime_keys: false }
+    }
+
+    /// Creates the store of the configured keys, `auth_token` being an
+    /// unrestricted key named `default`. Keys can be created at runtime if the
+    /// admin endpoints are enabled.
+    pub(crate) fn from_config(api: &ApiConfig, redis: &RedisConfig) -> Self {
+        let default_key = api
+            .auth_token
+            .as_deref()
+            .filter(|token| !token.is_empty())
+            .map(|token| ApiKeyConfig {
+                name: DEFAULT_KEY_NAME.to_string(),
+                key_hash: hash_key(token),
+                scopes: KeyScopes::default(),
+            });
+        let keys = api.keys.iter().cloned().chain(default_key);
+
+        let redis = match RedisConnection::open(redis.url.as_str()) {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                warn!(target: "altitrace::api::keys", error = %e, "Invalid Redis configuration, quotas and runtime keys disabled");
+                None
+            }
+        };
+
+        let store = Self::new(keys, redis);
+        if api.admin_enabled() && store.redis.is_some() {
+            store.with_runtime_keys()
+        } else {
+            store
+        }
+    }
+
+    /// Enables the keys created at runtime, stored in Redis.
+    pub const fn with_runtime_keys(mut self) -> Self {
+        self.runtime_keys = true;
+        self
+    }
+
+    /// Whether requests must be authenticated: some key is declared, or can be
+    /// created.
+    pub fn is_enabled(&self) -> bool {
+        !self.config_keys.is_empty() || self.runtime_keys
+    }
+
+    /// Runs commands on the shared Redis connection.
+    async fn run<T>(
+        &self,
+        run: impl AsyncFnOnce(&mut MultiplexedConnection) -> RedisResult<T>,
+    ) -> CacheResult<T> {
+        let conn = self.redis.as_ref().ok_or_else(|| {
+            CacheError::Connection("API keys are not stored in Redis".to_string())
+        })?;
+        Ok(conn.run(run).await?)
+    }
+
+    fn config_key_named(&self, name: &str) -> Option<&ApiKey> {
+        self.config_keys.values().find(|key| key.name == name)
+    }
+
+    /// Returns the API key matching `key`, if any.
+    pub async fn authenticate(&self, key: &str) -> CacheResult<Option<ApiKey>> {
+        let hash = hash_key(key);
+        if let Some(api_key) = self.config_keys.get(&hash) {
+            return Ok(Some(api_key.clone()));
+        }
+        if !self.runtime_keys {
+            return Ok(None);
+        }
+
+        let lookup = self.run(async |conn| conn.hget(KEYS_KEY, &hash).await);
+        let stored: Option<String> = metrics().redis("api_key_lookup", lookup).await?;
+        stored
+            .map(|stored| serde_json::from_str(&stored).map_err(CacheError::from))
+            .transpose()
+    }
+
+    /// Lists the API keys, sorted by name.
+    pub async fn list(&self) -> CacheResult<Vec<ApiKey>> {
+        let mut keys: Vec<ApiKey> = self.config_keys.values().cloned().collect();
+        if self.runtime_keys {
+            let stored: HashMap<String, String> =
+                self.run(async |conn| conn.hgetall(KEYS_KEY).await).await?;
+            for stored in stored.values() {
+                keys.push(serde_json::from_str(stored)?);
+            }
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Creates a key, returning it along with the key itself. Fails if the name
+    /// is already used.
+    pub async fn create(&self, name: String, scopes: KeyScopes) -> ApiResult<(ApiKey, String)> {
+        if self.config_key_named(&name).is_some() {
+            return Err(ApiError::conflict(format!("API key '{}' already exists", name)));
+        }
+
+        let key = generate_key();
+        let hash = hash_key(&key);
+        let api_key = ApiKey {
+            id: Uuid::new_v4().to_string(),
+            name,
+            scopes,
+            source: KeySource::Redis,
+            created_at: Some(Utc::now()),
+        };
+        let stored = serde_json::to_string(&api_key).map_err(CacheError::from)?;
+
+        // The name is reserved and the key stored at once
+        let script = redis::Script::new(CREATE_KEY_SCRIPT);
+        let mut invocation = script.key(NAMES_KEY);
+        invocation
+            .key(KEYS_KEY)
+            .arg(&api_key.name)
+            .arg(&hash)
+            .arg(stored);
+        let created: bool = self
+            .run(async |conn| invocation.invoke_async(conn).await)
+            .await?;
+        if !created {
+            return Err(ApiError::conflict(format!("API key '{}' already exists", api_key.name)));
+        }
+
+        info!(target: "altitrace::api::keys", name = api_key.name, "API key created");
+        Ok((api_key, key))
+    }
+
+    /// Replaces the key named `name` by a new one with the same scopes,
+    /// returning the new key. Fails if the key is rotated or revoked
+    /// concurrently.
+    pub async fn rotate(&self, name: &str) -> ApiResult<(ApiKey, String)> {
+        let (old_hash, mut api_key) = self.stored_key(name).await?;
+
+        let key = generate_key();
+        let hash = hash_key(&key);
+        api_key.created_at = Some(Utc::now());
+        let stored = serde_json::to_string(&api_key).map_err(CacheError::from)?;
+
+        // The key is only replaced if it is still the one read above
+        let script = redis::Script::new(ROTATE_KEY_SCRIPT);
+        let mut invocation = script.key(NAMES_KEY);
+        invocation
+            .key(KEYS_KEY)
+            .arg(name)
+            .arg(&old_hash)
+            .arg(&hash)
+            .arg(stored);
+        let rotated: bool = self
+            .run(async |conn| invocation.invoke_async(conn).await)
+            .await?;
+        if !rotated {
+            return Err(ApiError::conflict(format!("API key '{}' was changed concurrently", name)));
+        }
+
+        info!(target: "altitrace::api::keys", name, "API key rotated");
+        Ok((api_key, key))
+    }
+
+    /// Revokes the key named `name`.
+    pub async fn revoke(&self, name: &str) -> ApiResult<()> {
+        self.check_runtime_key(name)?;
+        let script = redis::Script::new(REVOKE_KEY_SCRIPT);
+        let mut invocation = script.key(NAMES_KEY);
+        invocation.key(KEYS_KEY).arg(name);
+        let revoked: bool = self
+            .run(async |conn| invocation.invoke_async(conn).await)
+            .await?;
+        if !revoked {
+            return Err(ApiError::not_found(format!("API key '{}'", name)));
+        }
+
+        info!(target: "altitrace::api::keys", name, "API key revoked");
+        Ok(())
+    }
+
+    /// Checks that the key named `name` is not declared in the configuration.
+    fn check_runtime_key(&self, name: &str) -> ApiResult<()> {
+        if self.config_key_named(name).is_some() {
+            return Err(ApiError::conflict(format!(
+                "API key '{}' is declared in the configuration and cannot be changed at runtime",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the hash and the key named `name` stored in Redis.
+    async fn stored_key(&self, name: &str) -> ApiResult<(String, ApiKey)> {
+        self.check_runtime_key(name)?;
+
+        let hash: Option<String> = self
+            .run(async |conn| conn.hget(NAMES_KEY, name).await)
+            .await?;
+        let hash = hash.ok_or_else(|| ApiError::not_found(format!("API key '{}'", name)))?;
+        let stored: Option<String> = self
+            .run(async |conn| conn.hget(KEYS_KEY, &hash).await)
+            .await?;
+        let stored = stored.ok_or_else(|| ApiError::not_found(format!("API key '{}'", name)))?;
+
+        Ok((hash, serde_json::from_str(&stored).map_err(CacheError::from)?))
+    }
+
+    /// Counts a request of `key` against its daily quota. Returns the seconds
+    /// until the quota resets if it is exhausted, in which case the request is
+    /// not counted.
+    pub async fn consume_quota(&self, key: &ApiKey) -> CacheResult<Option<u64>> {
+        let Some(quota) = key.scopes.daily_quota else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        let counter = quota_counter(&key.id, now);
+        metrics()
+            .redis(
+                "quota",
+                self.run(async |conn| {
+                    let (used,): (u64,) = redis::pipe()
+                        .atomic()
+                        .incr(&counter, 1)
+                        .expire(&counter, 2 * 24 * 60 * 60)
+                        .ignore()
+                        .query_async(conn)
+                        .await?;
+
+                    if used <= quota {
+                        return Ok(None);
+                    }
+
+                    conn.decr::<_, _, ()>(&counter, 1).await?;
+                    Ok(Some(seconds_until_midnight(now)))
+                }),
+            )
+            .await
+    }
+}
+
+/// Counter of the requests of the key `key_id` on the day of `now`.
+fn quota_counter(key_id: &str, now: DateTime<Utc>) -> String {
+    format!("quota:{}:{}", key_id, now.format("%Y-%m-%d"))
+}
+
+/// Seconds until the next UTC midnight, when daily quotas reset.
+fn seconds_until_midnight(now: DateTime<Utc>) -> u64 {
+    let midnight = now
+        .date_naive()
+        .checked_add_days(Days::new(1))
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc());
+    midnight.map_or(1, |midnight| (midnight - now).num_seconds().max(1) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn key(scopes: KeyScopes) -> ApiKey {
+        ApiKey {
+            id: "config:partner".to_string(),
+            name: "partner".to_string(),
+            scopes,
+            source: KeySource::Config,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_scopes() {
+        let unrestricted = key(KeyScopes::default());
+        assert!(unrestricted.authorize_endpoint("/simulate/batch").is_ok());
+        assert!(unrestricted.authorize_tracers(["structLogger"]).is_ok());
+        assert!(unrestricted.authorize_batch(1000).is_ok());
+
+        let trace_only = key(KeyScopes {
+            endpoints: vec!["/trace".to_string()],
+            tracers: Some(vec!["callTracer".to_string(), "prestateTracer".to_string()]),
+            max_batch_size: Some(10),
+            daily_quota: None,
+        });
+        assert!(trace_only.authorize_endpoint("/trace/call-many").is_ok());
+        assert!(trace_only.authorize_endpoint("/simulate").is_err());
+        assert!(trace_only.authorize_tracers(["callTracer"]).is_ok());
+        assert!(trace_only
+            .authorize_tracers(["callTracer", "structLogger"])
+            .is_err());
+        assert!(trace_only.authorize_batch(10).is_ok());
+        assert!(trace_only.authorize_batch(11).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_config_keys() {
+        let config = ApiKeyConfig {
+            name: "partner".to_string(),
+            key_hash: hash_key("secret").to_uppercase(),
+            scopes: KeyScopes::default(),
+        };
+        let store = ApiKeyStore::new([config], None);
+        assert!(store.is_enabled());
+        assert!(!ApiKeyStore::new([], None).is_enabled());
+        assert!(ApiKeyStore::new([], None).with_runtime_keys().is_enabled());
+
+        let found = store.authenticate("secret").await.unwrap().unwrap();
+        assert_eq!(found.name, "partner");
+        assert_eq!(found.id, "config:partner");
+        assert_eq!(store.authenticate("other").await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_generated_keys() {
+        let key = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_key());
+        assert_eq!(hash_key(&key).len(), 64);
+    }
+
+    #[test]
+    fn test_seconds_until_midnight() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap();
+        assert_eq!(seconds_until_midnight(now), 3600);
+    }
+
+    #[test]
+    fn test_quota_counter() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap();
+        let revoked = ApiKey { id: Uuid::new_v4().to_string(), ..key(KeyScopes::default()) };
+        let recreated = ApiKey { id: Uuid::new_v4().to_string(), ..key(KeyScopes::default()) };
+
+        // Keys sharing a name do not share their quota
+        assert_eq!(quota_counter(&revoked.id, now), format!("quota:{}:2025-01-31", revoked.id));
+        assert_ne!(quota_counter(&revoked.id, now), quota_counter(&recreated.id, now));
+    }
+}
@@ -0,0 +1,334 @@
+use super::{BackendHealth, CacheBackend};
+use crate::{error::CacheError, CacheResult};
+
+use async_trait::async_trait;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Maximal duration of an operation on the primary backend, connection included.
+const PRIMARY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Delay before the primary backend is tried again after it failed.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Cache falling back to a secondary backend while its primary one fails.
+///
+/// Operations are attempted on the primary backend (Redis), within a timeout,
+/// and on the fallback (the in-process cache) if they fail, so that an
+/// unreachable Redis only costs cache hits instead of failing requests. Once
+/// the primary backend failed, the cache is degraded: operations go straight to
+/// the fallback, and the primary backend is only tried again after a delay.
+///
+/// Entries written to the fallback while degraded are not copied to the primary
+/// backend: the fallback is cleared when the primary backend recovers, so that
+/// it does not serve stale entries at the next outage. Such entries, e.g.
+/// stored simulations, are lost.
+pub struct FallbackCache<P, F> {
+    primary: P,
+    fallback: F,
+    degraded: AtomicBool,
+    /// Time from which the primary backend is tried again, while degraded.
+    retry_at: Mutex<Option<Instant>>,
+    timeout: Duration,
+    retry_delay: Duration,
+}
+
+impl<P: CacheBackend, F: CacheBackend> FallbackCache<P, F> {
+    /// Creates the cache, `degraded` if the primary backend is already known to
+    /// be unreachable.
+    pub fn new(primary: P, fallback: F, degraded: bool) -> Self {
+        Self {
+            primary,
+            fallback,
+            degraded: AtomicBool::new(degraded),
+            retry_at: Mutex::new(degraded.then(|| Instant::now() + RETRY_DELAY)),
+            timeout: PRIMARY_TIMEOUT,
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    /// Sets the timeout of the operations on the primary backend, and the delay
+    /// before it is tried again after it failed.
+    pub fn with_timing(mut self, timeout: Duration, retry_delay: Duration) -> Self {
+        self.timeout = timeout;
+        self.retry_delay = retry_delay;
+        if self.degraded.load(Ordering::Relaxed) {
+            *self
+                .retry_at
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + retry_delay);
+        }
+        self
+    }
+
+    /// Runs an operation on the primary backend, and on the fallback if it fails
+    /// or if the cache is degraded.
+    async fn with_fallback<'a, T, Fut>(
+        &'a self,
+        operation: &str,
+        run: impl Fn(&'a dyn CacheBackend) -> Fut,
+    ) -> CacheResult<T>
+    where
+        Fut: Future<Output = CacheResult<T>>,
+    {
+        if !self.should_try_primary() {
+            return run(&self.fallback).await;
+        }
+
+        let result = tokio::time::timeout(self.timeout, run(&self.primary))
+            .await
+            .unwrap_or_else(|_| {
+                Err(CacheError::Connection(format!(
+                    "{} operation timed out after {:?}",
+                    operation, self.timeout
+                )))
+            });
+        match result {
+            Ok(value) => {
+                self.set_degraded(false).await;
+                Ok(value)
+            }
+            Err(e) => {
+                warn!(
+                    target: "altitrace::api::cache",
+                    operation,
+                    primary = self.primary.name(),
+                    fallback = self.fallback.name(),
+                    retry_delay = ?self.retry_delay,
+                    error = ?e,
+                    "Cache operation failed, using fallback"
+                );
+                self.set_degraded(true).await;
+                run(&self.fallback).await
+            }
+        }
+    }
+
+    /// Whether the primary backend should be tried: it is not degraded, or the
+    /// retry delay elapsed.
+    fn should_try_primary(&self) -> bool {
+        self.retry_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none_or(|retry_at| Instant::now() >= retry_at)
+    }
+
+    /// Records whether the fallback is in use, logging the transitions and
+    /// clearing the fallback when the primary backend recovers.
+    async fn set_degraded(&self, degraded: bool) {
+        *self.retry_at.lock().unwrap_or_else(PoisonError::into_inner) =
+            degraded.then(|| Instant::now() + self.retry_delay);
+        if self.degraded.swap(degraded, Ordering::Relaxed) && !degraded {
+            info!(
+                target: "altitrace::api::cache",
+                primary = self.primary.name(),
+                "Cache backend recovered"
+            );
+            if let Err(e) = self.fallback.clear_prefix("").await {
+                warn!(
+                    target: "altitrace::api::cache",
+                    fallback = self.fallback.name(),
+                    error = ?e,
+                    "Failed to clear the fallback cache"
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: CacheBackend, F: CacheBackend> CacheBackend for FallbackCache<P, F> {
+    fn name(&self) -> &'static str {
+        if self.degraded.load(Ordering::Relaxed) {
+            self.fallback.name()
+        } else {
+            self.primary.name()
+        }
+    }
+
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        self.with_fallback("get", |backend| backend.get(key)).await
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<u64>) -> CacheResult<()> {
+        self.with_fallback("set", |backend| backend.set(key, value.clone(), ttl))
+            .await
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        self.with_fallback("exists", |backend| backend.exists(key))
+            .await
+    }
+
+    async fn remove(&self, key: &str) -> CacheResult<bool> {
+        self.with_fallback("remove", |backend| backend.remove(key))
+            .await
+    }
+
+    async fn clear_prefix(&self, prefix: &str) -> CacheResult<()> {
+        // The fallback may hold entries written while the primary was unreachable
+        self.fallback.clear_prefix(prefix).await?;
+        self.with_fallback("clear", |backend| backend.clear_prefix(prefix))
+            .await
+    }
+
+    async fn health(&self) -> BackendHealth {
+        let health = tokio::time::timeout(self.timeout, self.primary.health())
+            .await
+            .unwrap_or(BackendHealth::Unhealthy);
+        match health {
+            BackendHealth::Healthy => {
+                self.set_degraded(false).await;
+                BackendHealth::Healthy
+            }
+            _ => {
+                self.set_degraded(true).await;
+                match self.fallback.health().await {
+                    BackendHealth::Healthy => BackendHealth::Degraded,
+                    health => health,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::cache::MemoryCache;
+    use std::{
+        num::NonZeroUsize,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Backend failing every operation, like an unreachable Redis.
+    struct Unreachable;
+
+    #[async_trait]
+    impl CacheBackend for Unreachable {
+        fn name(&self) -> &'static str {
+            "unreachable"
+        }
+
+        async fn get(&self, _key: &str) -> CacheResult<Option<String>> {
+            Err(CacheError::Connection("unreachable".to_string()))
+        }
+
+        async fn set(&self, _key: &str, _value: String, _ttl: Option<u64>) -> CacheResult<()> {
+            Err(CacheError::Connection("unreachable".to_string()))
+        }
+
+        async fn exists(&self, _key: &str) -> CacheResult<bool> {
+            Err(CacheError::Connection("unreachable".to_string()))
+        }
+
+        async fn remove(&self, _key: &str) -> CacheResult<bool> {
+            Err(CacheError::Connection("unreachable".to_string()))
+        }
+
+        async fn clear_prefix(&self, _prefix: &str) -> CacheResult<()> {
+            Err(CacheError::Connection("unreachable".to_string()))
+        }
+
+        async fn health(&self) -> BackendHealth {
+            BackendHealth::Unhealthy
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_while_primary_fails() {
+        let memory = MemoryCache::new(NonZeroUsize::new(8).unwrap());
+        let cache = FallbackCache::new(Unreachable, memory, false);
+        assert_eq!(cache.name(), "unreachable");
+
+        cache.set("key", "value".to_string(), None).await.unwrap();
+        assert_eq!(cache.name(), "memory");
+        assert_eq!(cache.get("key").await.unwrap(), Some("value".to_string()));
+        assert_eq!(cache.health().await, BackendHealth::Degraded);
+    }
+
+    /// Backend never answering, like a Redis behind a dropped connection,
+    /// counting the operations attempted.
+    #[derive(Default)]
+    struct Hanging(AtomicUsize);
+
+    impl Hanging {
+        async fn hang<T>(&self) -> CacheResult<T> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            std::future::pending().await
+        }
+    }
+
+    #[async_trait]
+    impl CacheBackend for Hanging {
+        fn name(&self) -> &'static str {
+            "hanging"
+        }
+
+        async fn get(&self, _key: &str) -> CacheResult<Option<String>> {
+            self.hang().await
+        }
+
+        async fn set(&self, _key: &str, _value: String, _ttl: Option<u64>) -> CacheResult<()> {
+            self.hang().await
+        }
+
+        async fn exists(&self, _key: &str) -> CacheResult<bool> {
+            self.hang().await
+        }
+
+        async fn remove(&self, _key: &str) -> CacheResult<bool> {
+            self.hang().await
+        }
+
+        async fn clear_prefix(&self, _prefix: &str) -> CacheResult<()> {
+            self.hang().await
+        }
+
+        async fn health(&self) -> BackendHealth {
+            self.hang::<()>().await.ok();
+            BackendHealth::Healthy
+        }
+    }
+
+    #[tokio::test]
+    async fn test_times_out_and_backs_off() {
+        let memory = MemoryCache::new(NonZeroUsize::new(8).unwrap());
+        let cache = FallbackCache::new(Hanging::default(), memory, false)
+            .with_timing(Duration::from_millis(20), Duration::from_millis(200));
+
+        // The hanging operation times out, and the fallback serves it
+        cache.set("key", "value".to_string(), None).await.unwrap();
+        assert_eq!(cache.name(), "memory");
+        assert_eq!(cache.primary.0.load(Ordering::Relaxed), 1);
+
+        // The primary backend is not tried again before the retry delay
+        assert_eq!(cache.get("key").await.unwrap(), Some("value".to_string()));
+        assert_eq!(cache.primary.0.load(Ordering::Relaxed), 1);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(cache.get("key").await.unwrap(), Some("value".to_string()));
+        assert_eq!(cache.primary.0.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_clears_fallback() {
+        let memory = MemoryCache::new(NonZeroUsize::new(8).unwrap());
+        memory.set("key", "stale".to_string(), None).await.unwrap();
+        let primary = MemoryCache::new(NonZeroUsize::new(8).unwrap());
+        let cache = FallbackCache::new(primary, memory, true)
+            .with_timing(Duration::from_millis(20), Duration::ZERO);
+
+        // The primary backend is used again, and the entries of the fallback dropped
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(!cache.degraded.load(Ordering::Relaxed));
+        assert_eq!(cache.fallback.get("key").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,166 @@
+use super::{BackendHealth, CacheBackend};
+use crate::CacheResult;
+
+use async_trait::async_trait;
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// In-process cache evicting the least recently used entries past its capacity.
+///
+/// Entries are local to the instance of the API and lost on restart, which is
+/// fine for local development and single-node deployments.
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+/// Cached value with its expiry.
+struct Entry {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl MemoryCache {
+    /// Creates an empty cache holding at most `capacity` entries.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self { entries: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// Locks the entries. A panic while holding the lock cannot leave an entry
+    /// half-written, so a poisoned lock is still usable.
+    fn entries(&self) -> MutexGuard<'_, LruCache<String, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the live entry at `key`, dropping it if it has expired.
+    fn live_entry<'a>(
+        entries: &'a mut LruCache<String, Entry>,
+        key: &str,
+    ) -> Option<&'a mut Entry> {
+        if entries.peek(key)?.is_expired(Instant::now()) {
+            entries.pop(key);
+            return None;
+        }
+        entries.get_mut(key)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        let mut entries = self.entries();
+        Ok(Self::live_entry(&mut entries, key).map(|entry| entry.value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<u64>) -> CacheResult<()> {
+        let expires_at = ttl.map(|seconds| Instant::now() + Duration::from_secs(seconds));
+        self.entries()
+            .put(key.to_string(), Entry { value, expires_at });
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        let mut entries = self.entries();
+        Ok(Self::live_entry(&mut entries, key).is_some())
+    }
+
+    async fn remove(&self, key: &str) -> CacheResult<bool> {
+        let mut entries = self.entries();
+        let live = Self::live_entry(&mut entries, key).is_some();
+        entries.pop(key);
+        Ok(live)
+    }
+
+    async fn clear_prefix(&self, prefix: &str) -> CacheResult<()> {
+        let mut entries = self.entries();
+        let keys: Vec<String> = entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in keys {
+            entries.pop(&key);
+        }
+        Ok(())
+    }
+
+    async fn health(&self) -> BackendHealth {
+        BackendHealth::Healthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> MemoryCache {
+        MemoryCache::new(NonZeroUsize::new(capacity).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let cache = cache(2);
+        cache.set("a", "1".to_string(), None).await.unwrap();
+        cache.set("b", "2".to_string(), None).await.unwrap();
+
+        // Reading `a` makes `b` the least recently used entry
+        assert_eq!(cache.get("a").await.unwrap(), Some("1".to_string()));
+        cache.set("c", "3".to_string(), None).await.unwrap();
+
+        assert!(cache.exists("a").await.unwrap());
+        assert!(!cache.exists("b").await.unwrap());
+        assert!(cache.exists("c").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries() {
+        let cache = cache(2);
+        cache
+            .set("expired", "1".to_string(), Some(0))
+            .await
+            .unwrap();
+        cache.set("kept", "2".to_string(), Some(60)).await.unwrap();
+
+        assert_eq!(cache.get("expired").await.unwrap(), None);
+        assert!(!cache.remove("expired").await.unwrap());
+        assert_eq!(cache.get("kept").await.unwrap(), Some("2".to_string()));
+        assert!(cache.remove("kept").await.unwrap());
+        assert!(!cache.exists("kept").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_clear_prefix() {
+        let cache = cache(4);
+        cache
+            .set("response:a", "1".to_string(), None)
+            .await
+            .unwrap();
+        cache
+            .set("response:b", "2".to_string(), None)
+            .await
+            .unwrap();
+        cache
+            .set("simulation:c", "3".to_string(), None)
+            .await
+            .unwrap();
+
+        cache.clear_prefix("response:").await.unwrap();
+        assert!(!cache.exists("response:a").await.unwrap());
+        assert!(!cache.exists("response:b").await.unwrap());
+        assert!(cache.exists("simulation:c").await.unwrap());
+    }
+}
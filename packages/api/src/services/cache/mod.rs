@@ -0,0 +1,298 @@
+//! Cache of the API.
+//!
+//! The cache stores serialized values behind a [`CacheBackend`]: Redis, shared
+//! by all the instances of the API, or an in-process LRU. The backend is
+//! selected in the configuration, and a Redis backend falls back to the
+//! in-process cache while Redis is unreachable, so that the API neither needs
+//! Redis to start nor fails requests when it goes down.
+
+mod fallback;
+mod memory;
+mod redis;
+
+pub use fallback::FallbackCache;
+pub use memory::MemoryCache;
+pub use redis::{RedisCache, RedisConnection};
+
+use crate::{
+    config::{CacheBackendKind, CacheConfig, RedisConfig},
+    error::CacheError,
+    services::metrics::metrics,
+    CacheResult,
+};
+
+use alloy_primitives::keccak256;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{future::Future, sync::Arc};
+use tracing::{debug, error, info, warn};
+
+/// Storage of the cache, holding serialized values.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Name of the backend serving the requests, reported by the health check.
+    fn name(&self) -> &'static str;
+
+    /// Returns the value stored at `key`.
+    async fn get(&self, key: &str) -> CacheResult<Option<String>>;
+
+    /// Stores a value at `key`, expiring after `ttl` seconds if set.
+    async fn set(&self, key: &str, value: String, ttl: Option<u64>) -> CacheResult<()>;
+
+    /// Returns whether a value is stored at `key`.
+    async fn exists(&self, key: &str) -> CacheResult<bool>;
+
+    /// Removes the value stored at `key`, returning whether there was one.
+    async fn remove(&self, key: &str) -> CacheResult<bool>;
+
+    /// Removes every value stored at a key starting with `prefix`.
+    async fn clear_prefix(&self, prefix: &str) -> CacheResult<()>;
+
+    /// Checks that the backend can serve requests.
+    async fn health(&self) -> BackendHealth;
+}
+
+/// Health of a cache backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendHealth {
+    /// The backend serves requests.
+    Healthy,
+    /// The backend serves requests from its fallback.
+    Degraded,
+    /// The backend cannot serve requests.
+    Unhealthy,
+}
+
+impl BackendHealth {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+/// Outcome of a read-through cache lookup, reported in the `X-Cache` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The value was found in the cache.
+    Hit,
+    /// The value was computed and stored.
+    Miss,
+    /// The value was computed but cannot be cached.
+    Bypass,
+}
+
+impl CacheStatus {
+    /// Name of the response header carrying the status.
+    pub const HEADER: &'static str = "x-cache";
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hit => "HIT",
+            Self::Miss => "MISS",
+            Self::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// Prefix of the keys of the cached responses, which are the only values that
+/// can be cleared: the other values (stored simulations, ...) live in the same
+/// storage.
+pub const RESPONSE_KEY_PREFIX: &str = "response:";
+
+/// Cache key of a request, from the hash of its canonical JSON encoding.
+///
+/// `serde_json` sorts the keys of its maps, so requests that only differ by the
+/// order of their fields or map entries share the same key. The version of the
+/// API is part of the key so that a new release never reads responses cached by
+/// a previous one.
+pub fn request_key<T: Serialize>(namespace: &str, request: &T) -> CacheResult<String> {
+    let canonical = serde_json::to_value(request)?.to_string();
+    Ok(format!(
+        "{}{}:{}:{:x}",
+        RESPONSE_KEY_PREFIX,
+        namespace,
+        env!("CARGO_PKG_VERSION"),
+        keccak256(canonical.as_bytes())
+    ))
+}
+
+/// Cache of JSON values, shared by the handlers and services.
+#[derive(Clone)]
+pub struct Cache {
+    backend: Arc<dyn CacheBackend>,
+}
+
+impl Cache {
+    pub fn new(backend: impl CacheBackend + 'static) -> Self {
+        Self { backend: Arc::new(backend) }
+    }
+
+    /// Initializes the configured backend.
+    ///
+    /// A Redis backend falls back to the in-process cache while Redis is
+    /// unreachable, including at startup, and the in-process cache is used
+    /// alone if the Redis client cannot even be created (e.g. invalid URL).
+    pub async fn init(config: &CacheConfig, redis: &RedisConfig) -> Self {
+        let memory = MemoryCache::new(config.memory_capacity);
+
+        match config.backend {
+            CacheBackendKind::Memory => {
+                info!(
+                    target: "altitrace::api::cache",
+                    capacity = config.memory_capacity,
+                    "Using the in-process cache"
+                );
+                Self::new(memory)
+            }
+            CacheBackendKind::Redis => match RedisCache::open(redis) {
+                Ok(redis_cache) => {
+                    let degraded = match redis_cache.check_health().await {
+                        Ok(()) => {
+                            info!(target: "altitrace::api::cache", "Redis connection successfully established");
+                            false
+                        }
+                        Err(e) => {
+                            warn!(target: "altitrace::api::cache", error = %e, "Redis is unreachable, using the in-process cache until it recovers");
+                            true
+                        }
+                    };
+                    Self::new(FallbackCache::new(redis_cache, memory, degraded))
+                }
+                Err(e) => {
+                    error!(target: "altitrace::api::cache", error = %e, "Invalid Redis configuration, using the in-process cache");
+                    Self::new(memory)
+                }
+            },
+        }
+    }
+
+    /// Name of the backend serving the requests.
+    pub fn backend(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    // add a new key value pair in the cache
+    pub async fn push<T: Serialize>(
+        &self,
+        key: &str,
+        value: T,
+        dur: Option<i64>,
+    ) -> CacheResult<()> {
+        let serialized_value = serde_json::to_string(&value)?;
+        let ttl = dur
+            .map(|duration| u64::try_from(duration).map_err(|_| CacheError::invalid_ttl(duration)))
+            .transpose()?;
+        self.backend.set(key, serialized_value, ttl).await
+    }
+
+    // get a value associated to a key
+    pub async fn pull<T: DeserializeOwned>(&self, key: &str) -> CacheResult<Option<T>> {
+        let Some(value) = self.backend.get(key).await? else {
+            return Ok(None);
+        };
+
+        match serde_json::from_str(&value) {
+            Ok(deserialized) => Ok(Some(deserialized)),
+            Err(e) => {
+                error!(target: "altitrace::api::cache", "Deserialization error for key {}: {}", key, e);
+                Err(CacheError::Deserialization(e.to_string()))
+            }
+        }
+    }
+
+    // check if a key is already stored in the cache
+    pub async fn exist(&self, key: &str) -> CacheResult<bool> {
+        self.backend.exists(key).await
+    }
+
+    // remove a key-value pair from the cache
+    pub async fn remove(&self, key: &str) -> CacheResult<bool> {
+        self.backend.remove(key).await
+    }
+
+    /// Removes the cached responses, see [`RESPONSE_KEY_PREFIX`].
+    pub async fn clear(&self) -> CacheResult<()> {
+        self.backend.clear_prefix(RESPONSE_KEY_PREFIX).await
+    }
+
+    /// Checks that the cache can serve requests.
+    pub async fn health(&self) -> BackendHealth {
+        self.backend.health().await
+    }
+
+    /// Returns the value stored at `key`, or runs `query_fn` and stores its value
+    /// for `ttl` seconds.
+    ///
+    /// `query_fn` returns its value along with whether it can be cached, as some
+    /// values are only known to be immutable once computed. Cache failures are
+    /// logged and fall back to the query, so that the cache never fails a request.
+    pub async fn cache<T, E, F, Fut>(
+        &self,
+        key: &str,
+        ttl: i64,
+        query_fn: F,
+    ) -> Result<(T, CacheStatus), E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(T, bool), E>>,
+    {
+        match self.pull(key).await {
+            Ok(Some(value)) => {
+                debug!(target: "altitrace::api::cache", key, "Cache hit");
+                metrics().record_cache_lookup(CacheStatus::Hit);
+                return Ok((value, CacheStatus::Hit));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(target: "altitrace::api::cache", key, error = ?e, "Cache lookup failed");
+            }
+        }
+
+        let (value, cacheable) = query_fn().await?;
+        let status = if !cacheable {
+            CacheStatus::Bypass
+        } else if let Err(e) = self.push(key, &value, Some(ttl)).await {
+            warn!(target: "altitrace::api::cache", key, error = ?e, "Failed to cache value");
+            CacheStatus::Bypass
+        } else {
+            CacheStatus::Miss
+        };
+
+        metrics().record_cache_lookup(status);
+        Ok((value, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, num::NonZeroUsize};
+
+    #[test]
+    fn test_request_key_is_canonical() {
+        let first = HashMap::from([("a", 1), ("b", 2), ("c", 3)]);
+        let second = HashMap::from([("c", 3), ("a", 1), ("b", 2)]);
+        let other = HashMap::from([("a", 1), ("b", 2), ("c", 4)]);
+
+        let key = request_key("trace:tx", &first).unwrap();
+        assert!(key.starts_with("response:trace:tx:"));
+        assert_eq!(key, request_key("trace:tx", &second).unwrap());
+        assert_ne!(key, request_key("trace:tx", &other).unwrap());
+        assert_ne!(key, request_key("trace:call", &first).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_read_through() {
+        let cache = Cache::new(MemoryCache::new(NonZeroUsize::new(8).unwrap()));
+        let query = |value: u64, cacheable: bool| async move { Ok::<_, ()>((value, cacheable)) };
+
+        // Values that cannot be cached are computed every time
+        assert_eq!(cache.cache("key", 60, || query(1, false)).await, Ok((1, CacheStatus::Bypass)));
+        assert_eq!(cache.cache("key", 60, || query(2, true)).await, Ok((2, CacheStatus::Miss)));
+        assert_eq!(cache.cache("key", 60, || query(3, true)).await, Ok((2, CacheStatus::Hit)));
+    }
+}
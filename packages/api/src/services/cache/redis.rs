@@ -0,0 +1,283 @@
+use super::{BackendHealth, CacheBackend};
+use crate::{config::RedisConfig, error::CacheError, services::metrics::metrics, CacheResult};
+
+use async_trait::async_trait;
+use redis::{
+    aio::MultiplexedConnection, AsyncCommands, AsyncConnectionConfig, Client, RedisResult,
+};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tracing::{error, info};
+
+/// Maximal duration of the connection to the Redis server.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Maximal duration of a Redis command.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of keys scanned per `SCAN` call.
+const SCAN_COUNT: usize = 1_000;
+
+/// Escapes the glob characters of `MATCH` patterns.
+fn escape_pattern(prefix: &str) -> String {
+    let mut escaped = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Connection to a Redis server shared by the operations of a service.
+///
+/// The connection is opened on first use and again after it failed, with
+/// connection and response timeouts so that an unreachable server fails the
+/// operations instead of blocking them.
+#[derive(Clone)]
+pub struct RedisConnection {
+    client: Client,
+    conn: Arc<Mutex<Option<MultiplexedConnection>>>,
+}
+
+impl RedisConnection {
+    pub fn new(client: Client) -> Self {
+        Self { client, conn: Arc::default() }
+    }
+
+    /// Creates the client of the Redis server at `url`, without connecting to it.
+    pub fn open(url: &str) -> RedisResult<Self> {
+        Ok(Self::new(Client::open(url)?))
+    }
+
+    /// Returns the shared connection, opening it if needed.
+    pub async fn get(&self) -> RedisResult<MultiplexedConnection> {
+        if let Some(conn) = self.shared().as_ref() {
+            return Ok(conn.clone());
+        }
+
+        let config = AsyncConnectionConfig::new()
+            .set_connection_timeout(CONNECTION_TIMEOUT)
+            .set_response_timeout(RESPONSE_TIMEOUT);
+        let conn = self
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        *self.shared() = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// Runs commands on the shared connection, dropping it if it failed so that
+    /// the next operation connects again.
+    pub async fn run<T>(
+        &self,
+        run: impl AsyncFnOnce(&mut MultiplexedConnection) -> RedisResult<T>,
+    ) -> RedisResult<T> {
+        let mut conn = self.get().await?;
+        run(&mut conn).await.inspect_err(|e| {
+            if e.is_io_error() || e.is_timeout() || e.is_connection_dropped() {
+                self.reset();
+            }
+        })
+    }
+
+    /// Drops the shared connection, the next operation connects again.
+    pub fn reset(&self) {
+        self.shared().take();
+    }
+
+    fn shared(&self) -> std::sync::MutexGuard<'_, Option<MultiplexedConnection>> {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Cache backed by a Redis server, shared by all the instances of the API.
+#[derive(Clone)]
+pub struct RedisCache {
+    conn: RedisConnection,
+}
+
+impl RedisCache {
+    /// Creates the client of the Redis server, without connecting to it.
+    pub fn open(config: &RedisConfig) -> CacheResult<Self> {
+        let url = config.url.clone();
+        info!(target: "altitrace::api::cache", "Initializing Redis connection to {}", url);
+
+        match RedisConnection::open(&url) {
+            Ok(conn) => Ok(Self { conn }),
+            Err(e) => {
+                error!(target: "altitrace::api::cache", "Failed to create Redis client: {:#}", e);
+                Err(CacheError::from(e))
+            }
+        }
+    }
+
+    pub async fn init_cache(config: &RedisConfig) -> CacheResult<Self> {
+        let cache = Self::open(config)?;
+
+        // Verify connection works by performing a health check
+        match cache.check_health().await {
+            Ok(()) => {
+                info!(target: "altitrace::api::cache", "Redis connection successfully established");
+                Ok(cache)
+            }
+            Err(e) => {
+                error!(target: "altitrace::api::cache", "Redis health check error: {:#}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn with_conn<T>(
+        &self,
+        run: impl AsyncFnOnce(&mut MultiplexedConnection) -> RedisResult<T>,
+    ) -> CacheResult<T> {
+        Ok(self.conn.run(run).await?)
+    }
+
+    pub async fn check_health(&self) -> CacheResult<()> {
+        let result = self.run_health_check().await;
+        if result.is_err() {
+            self.conn.reset();
+        }
+        result
+    }
+
+    async fn run_health_check(&self) -> CacheResult<()> {
+        let mut conn = self.conn.get().await.map_err(|e| {
+            CacheError::Connection(format!("Health check connection failed: {}", e))
+        })?;
+
+        const TEST_KEY: &str = "health_check_key";
+        const TEST_VALUE: &str = "health_check_value";
+
+        // Set test value
+        conn.set::<&str, &str, ()>(TEST_KEY, TEST_VALUE)
+            .await
+            .map_err(|e| CacheError::Operation(format!("Health check set failed: {}", e)))?;
+
+        // Get test value
+        let value = conn
+            .get::<&str, Option<String>>(TEST_KEY)
+            .await
+            .map_err(|e| CacheError::Operation(format!("Health check get failed: {}", e)))?;
+
+        let value = value.ok_or_else(|| {
+            CacheError::Operation("Health check test value not found".to_string())
+        })?;
+
+        if value != TEST_VALUE {
+            return Err(CacheError::Operation(format!(
+                "Health check value mismatch: expected '{}', got '{}'",
+                TEST_VALUE, value
+            )));
+        }
+
+        // Cleanup test key
+        conn.del::<&str, ()>(TEST_KEY)
+            .await
+            .map_err(|e| CacheError::Operation(format!("Health check cleanup failed: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        metrics()
+            .redis("cache_get", async {
+                self.with_conn(async |conn| conn.get::<&str, Option<String>>(key).await)
+                    .await
+                    .inspect_err(|_| {
+                        error!(target: "altitrace::api::cache", "Error during fetch of: {}", key);
+                    })
+            })
+            .await
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<u64>) -> CacheResult<()> {
+        metrics()
+            .redis(
+                "cache_set",
+                self.with_conn(async |conn| match ttl {
+                    Some(seconds) => conn.set_ex::<&str, String, ()>(key, value, seconds).await,
+                    None => conn.set::<&str, String, ()>(key, value).await,
+                }),
+            )
+            .await
+    }
+
+    async fn exists(&self, key: &str) -> CacheResult<bool> {
+        metrics()
+            .redis("cache_exists", self.with_conn(async |conn| conn.exists(key).await))
+            .await
+    }
+
+    async fn remove(&self, key: &str) -> CacheResult<bool> {
+        metrics()
+            .redis(
+                "cache_remove",
+                self.with_conn(async |conn| {
+                    let removed: u64 = conn.del(key).await?;
+                    Ok(removed > 0)
+                }),
+            )
+            .await
+    }
+
+    async fn clear_prefix(&self, prefix: &str) -> CacheResult<()> {
+        metrics()
+            .redis(
+                "cache_clear",
+                self.with_conn(async |conn| {
+                    // Scanned in batches, without blocking the server like `KEYS`
+                    let pattern = format!("{}*", escape_pattern(prefix));
+                    let mut cursor = 0u64;
+                    loop {
+                        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                            .arg(cursor)
+                            .arg("MATCH")
+                            .arg(&pattern)
+                            .arg("COUNT")
+                            .arg(SCAN_COUNT)
+                            .query_async(conn)
+                            .await?;
+                        if !keys.is_empty() {
+                            conn.unlink::<_, ()>(keys).await?;
+                        }
+                        if next == 0 {
+                            return Ok(());
+                        }
+                        cursor = next;
+                    }
+                }),
+            )
+            .await
+    }
+
+    async fn health(&self) -> BackendHealth {
+        match self.check_health().await {
+            Ok(()) => BackendHealth::Healthy,
+            Err(_) => BackendHealth::Unhealthy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_pattern() {
+        assert_eq!(escape_pattern("response:"), "response:");
+        assert_eq!(escape_pattern("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\");
+    }
+}
@@ -0,0 +1,581 @@
+//! ABI decoding of calldata, return data, events and custom errors.
+//!
+//! Values are decoded against [`AbiType`]s parsed from JSON ABI parameters and
+//! rendered as strings: addresses and bytes as hex, integers in decimal, arrays
+//! as `[a, b]` and tuples as `(a, b)`.
+
+use crate::{
+    handlers::simulation::response::{
+        DecodedCall, DecodedError, DecodedEvent, DecodedEventParam, DecodedParam,
+    },
+    types::ContractAbis,
+};
+use alloy_json_abi::{EventParam, JsonAbi, Param};
+use alloy_primitives::{hex, Address, B256, I256, U256};
+use std::{collections::HashMap, iter, str::FromStr};
+
+/// Maximum nesting of arrays and tuples in a type.
+const MAX_DEPTH: usize = 32;
+
+/// Solidity ABI type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    Address,
+    Bool,
+    Uint(usize),
+    Int(usize),
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Function,
+    Array(Box<Self>),
+    FixedArray(Box<Self>, usize),
+    Tuple(Vec<Self>),
+}
+
+impl AbiType {
+    /// Parses a type string as found in JSON ABIs (e.g. `uint256`, `tuple[]`).
+    ///
+    /// `components` are used to resolve `tuple` types. Types nested deeper than
+    /// [`MAX_DEPTH`] are rejected.
+    pub fn parse(ty: &str, components: &[Param]) -> Option<Self> {
+        Self::parse_nested(ty, components, 0)
+    }
+
+    fn parse_nested(ty: &str, components: &[Param], depth: usize) -> Option<Self> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        if let Some(stripped) = ty.strip_suffix(']') {
+            let open = stripped.rfind('[')?;
+            let inner = Box::new(Self::parse_nested(&stripped[..open], components, depth + 1)?);
+            return match &stripped[open + 1..] {
+                "" => Some(Self::Array(inner)),
+                size => Some(Self::FixedArray(inner, size.parse().ok()?)),
+            };
+        }
+
+        let parse_size = |size: &str, default: usize| match size {
+            "" => Some(default),
+            size => size.parse().ok(),
+        };
+
+        match ty {
+            "address" => Some(Self::Address),
+            "bool" => Some(Self::Bool),
+            "string" => Some(Self::String),
+            "bytes" => Some(Self::Bytes),
+            "function" => Some(Self::Function),
+            "tuple" => components
+                .iter()
+                .map(|component| {
+                    Self::parse_nested(&component.ty, &component.components, depth + 1)
+                })
+                .collect::<Option<_>>()
+                .map(Self::Tuple),
+            _ => {
+                if let Some(size) = ty.strip_prefix("uint") {
+                    parse_size(size, 256).map(Self::Uint)
+                } else if let Some(size) = ty.strip_prefix("int") {
+                    parse_size(size, 256).map(Self::Int)
+                } else if let Some(size) = ty.strip_prefix("bytes") {
+                    size.parse()
+                        .ok()
+                        .filter(|size| (1..=32).contains(size))
+                        .map(Self::FixedBytes)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Returns whether the type is dynamically sized.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Self::Bytes | Self::String | Self::Array(_) => true,
+            Self::FixedArray(inner, _) => inner.is_dynamic(),
+            Self::Tuple(types) => types.iter().any(Self::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Size of the type in the head of an encoded tuple, `None` if it
+    /// overflows.
+    fn head_size(&self) -> Option<usize> {
+        if self.is_dynamic() {
+            return Some(32);
+        }
+        match self {
+            Self::FixedArray(inner, size) => inner.head_size()?.checked_mul(*size),
+            Self::Tuple(types) => types
+                .iter()
+                .try_fold(0usize, |sum, ty| sum.checked_add(ty.head_size()?)),
+            _ => Some(32),
+        }
+    }
+}
+
+/// Bounds the work of decoding untrusted data.
+///
+/// A canonical encoding reads each of its words once, so the words read are
+/// capped at the size of the data: offsets pointing at the same data cannot
+/// make the decoding blow up.
+#[derive(Debug)]
+struct Decoder {
+    words: usize,
+}
+
+impl Decoder {
+    const fn new(data: &[u8]) -> Self {
+        Self { words: data.len() / 32 + 1 }
+    }
+
+    fn read_word(&mut self, data: &[u8], offset: usize) -> Option<B256> {
+        self.words = self.words.checked_sub(1)?;
+        read_word(data, offset)
+    }
+
+    fn read_usize(&mut self, data: &[u8], offset: usize) -> Option<usize> {
+        usize::try_from(U256::from_be_bytes(self.read_word(data, offset)?.0)).ok()
+    }
+
+    fn read_bytes<'a>(&mut self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let length = self.read_usize(data, 0)?;
+        let bytes = data.get(32..32usize.checked_add(length)?)?;
+        self.words = self.words.checked_sub(length.div_ceil(32))?;
+        Some(bytes)
+    }
+
+    /// Decodes a tuple of `types` starting at the beginning of `data`.
+    fn decode_params<'t>(
+        &mut self,
+        types: impl IntoIterator<Item = &'t AbiType>,
+        data: &[u8],
+        depth: usize,
+    ) -> Option<Vec<String>> {
+        let mut head = 0usize;
+        let mut values = Vec::new();
+
+        for ty in types {
+            let value = if ty.is_dynamic() {
+                let offset = self.read_usize(data, head)?;
+                self.decode_value(ty, data.get(offset..)?, depth)?
+            } else {
+                self.decode_value(ty, data.get(head..)?, depth)?
+            };
+            head = head.checked_add(ty.head_size()?)?;
+            values.push(value);
+        }
+
+        Some(values)
+    }
+
+    /// Decodes a single value starting at the beginning of `data`.
+    fn decode_value(&mut self, ty: &AbiType, data: &[u8], depth: usize) -> Option<String> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match ty {
+            AbiType::Address => {
+                Some(format!("0x{:x}", Address::from_word(self.read_word(data, 0)?)))
+            }
+            AbiType::Bool => Some((!self.read_word(data, 0)?.is_zero()).to_string()),
+            AbiType::Uint(_) => Some(U256::from_be_bytes(self.read_word(data, 0)?.0).to_string()),
+            AbiType::Int(_) => {
+                Some(I256::from_raw(U256::from_be_bytes(self.read_word(data, 0)?.0)).to_string())
+            }
+            AbiType::FixedBytes(size) => {
+                Some(hex::encode_prefixed(&self.read_word(data, 0)?[..*size]))
+            }
+            AbiType::Function => Some(hex::encode_prefixed(&self.read_word(data, 0)?[..24])),
+            AbiType::Bytes => self.read_bytes(data).map(hex::encode_prefixed),
+            AbiType::String => self
+                .read_bytes(data)
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+            AbiType::Array(inner) => {
+                let length = self.read_usize(data, 0)?;
+                // Every element takes at least one word
+                if length > data.len() / 32 {
+                    return None;
+                }
+                let items = self.decode_params(
+                    iter::repeat_n(&**inner, length),
+                    data.get(32..)?,
+                    depth + 1,
+                )?;
+                Some(format!("[{}]", items.join(", ")))
+            }
+            AbiType::FixedArray(inner, size) => {
+                if *size > data.len() / 32 {
+                    return None;
+                }
+                let items = self.decode_params(iter::repeat_n(&**inner, *size), data, depth + 1)?;
+                Some(format!("[{}]", items.join(", ")))
+            }
+            AbiType::Tuple(types) => {
+                let items = self.decode_params(types, data, depth + 1)?;
+                Some(format!("({})", items.join(", ")))
+            }
+        }
+    }
+}
+
+/// Decodes an ABI-encoded tuple of `types`.
+pub fn decode_params(types: &[AbiType], data: &[u8]) -> Option<Vec<String>> {
+    Decoder::new(data).decode_params(types, data, 0)
+}
+
+/// Decodes an indexed event parameter from its topic.
+///
+/// Dynamic types are hashed when indexed, so their topic is returned as is.
+pub fn decode_topic(ty: &AbiType, topic: &B256) -> Option<String> {
+    if ty.is_dynamic() || matches!(ty, AbiType::Tuple(_) | AbiType::FixedArray(..)) {
+        return Some(format!("0x{:x}", topic));
+    }
+    Decoder::new(topic.as_slice()).decode_value(ty, topic.as_slice(), 0)
+}
+
+fn read_word(data: &[u8], offset: usize) -> Option<B256> {
+    data.get(offset..offset.checked_add(32)?)
+        .map(B256::from_slice)
+}
+
+/// Set of user-supplied contract ABIs, keyed by contract address.
+#[derive(Debug, Default)]
+pub struct AbiRegistry<'a> {
+    abis: HashMap<Address, &'a JsonAbi>,
+}
+
+impl<'a> AbiRegistry<'a> {
+    /// Builds a registry from the `address -> ABI` map of a request.
+    ///
+    /// Entries with an invalid address are ignored, as they are rejected by
+    /// request validation.
+    pub fn new(abis: Option<&'a ContractAbis>) -> Self {
+        let abis = abis
+            .into_iter()
+            .flatten()
+            .filter_map(|(address, abi)| Some((Address::from_str(address).ok()?, abi)))
+            .collect();
+        Self { abis }
+    }
+
+    /// Returns true if no ABI was supplied.
+    pub fn is_empty(&self) -> bool {
+        self.abis.is_empty()
+    }
+
+    /// Decodes the calldata of a call to `to`, and its output if provided.
+    pub fn decode_call(
+        &self,
+        to: Address,
+        input: &[u8],
+        output: Option<&[u8]>,
+    ) -> Option<DecodedCall> {
+        let abi = self.abis.get(&to)?;
+        let (selector, arguments) = input.split_at_checked(4)?;
+        let function = abi
+            .functions()
+            .find(|function| function.selector() == selector)?;
+
+        Some(DecodedCall {
+            name: function.name.clone(),
+            signature: function.signature(),
+            selector: hex::encode_prefixed(selector),
+            inputs: decode_named_params(&function.inputs, arguments)?,
+            outputs: output.and_then(|output| decode_named_params(&function.outputs, output)),
+        })
+    }
+
+    /// Decodes a log emitted by `address` with one of its ABI events.
+    pub fn decode_event(
+        &self,
+        address: Address,
+        topics: &[B256],
+        data: &[u8],
+    ) -> Option<DecodedEvent> {
+        let abi = self.abis.get(&address)?;
+        let (topic0, indexed_topics) = topics.split_first()?;
+        let event = abi.events().find(|event| {
+            !event.anonymous &&
+                event.selector() == *topic0 &&
+                event.inputs.iter().filter(|param| param.indexed).count() == indexed_topics.len()
+        })?;
+
+        let types = event
+            .inputs
+            .iter()
+            .map(|param| AbiType::parse(&param.ty, &param.components))
+            .collect::<Option<Vec<_>>>()?;
+        let data_types: Vec<AbiType> = event
+            .inputs
+            .iter()
+            .zip(&types)
+            .filter(|(param, _)| !param.indexed)
+            .map(|(_, ty)| ty.clone())
+            .collect();
+
+        let mut data_values = decode_params(&data_types, data)?.into_iter();
+        let mut indexed_topics = indexed_topics.iter();
+        let params = event
+            .inputs
+            .iter()
+            .zip(&types)
+            .map(|(param, ty)| {
+                let value = if param.indexed {
+                    decode_topic(ty, indexed_topics.next()?)?
+                } else {
+                    data_values.next()?
+                };
+                Some(event_param(param, value))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let summary = format!(
+            "{}({})",
+            event.name,
+            params
+                .iter()
+                .map(|param| format!("{}={}", param.name, param.value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Some(DecodedEvent {
+            name: event.name.clone(),
+            signature: event.signature(),
+            standard: None,
+            description: "Event decoded with the supplied ABI".to_string(),
+            params,
+            summary,
+        })
+    }
+
+    /// Decodes revert data as a custom error.
+    ///
+    /// The ABI of the reverting contract is tried first, then every other
+    /// supplied ABI, since errors often bubble up from nested calls.
+    pub fn decode_error(&self, address: Option<Address>, data: &[u8]) -> Option<DecodedError> {
+        let (selector, arguments) = data.split_at_checked(4)?;
+        let preferred = address.and_then(|address| self.abis.get(&address));
+
+        preferred
+            .into_iter()
+            .chain(self.abis.values())
+            .find_map(|abi| {
+                let error = abi.errors().find(|error| error.selector() == selector)?;
+                Some(DecodedError {
+                    name: error.name.clone(),
+                    signature: error.signature(),
+                    selector: hex::encode_prefixed(selector),
+                    params: decode_named_params(&error.inputs, arguments)?,
+                    description: None,
+                })
+            })
+    }
+}
+
+fn decode_named_params(params: &[Param], data: &[u8]) -> Option<Vec<DecodedParam>> {
+    let types = params
+        .iter()
+        .map(|param| AbiType::parse(&param.ty, &param.components))
+        .collect::<Option<Vec<_>>>()?;
+    let values = decode_params(&types, data)?;
+
+    Some(
+        params
+            .iter()
+            .zip(values)
+            .map(|(param, value)| DecodedParam {
+                name: param.name.clone(),
+                param_type: param.selector_type().into_owned(),
+                value,
+            })
+            .collect(),
+    )
+}
+
+fn event_param(param: &EventParam, value: String) -> DecodedEventParam {
+    DecodedEventParam {
+        name: param.name.clone(),
+        param_type: param.selector_type().into_owned(),
+        value,
+        indexed: param.indexed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> Vec<u8> {
+        U256::from(value).to_be_bytes_vec()
+    }
+
+    fn abi() -> JsonAbi {
+        serde_json::from_str(
+            r#"[
+                {
+                    "type": "function",
+                    "name": "deposit",
+                    "stateMutability": "nonpayable",
+                    "inputs": [
+                        {"name": "amounts", "type": "uint256[]"},
+                        {"name": "memo", "type": "string"}
+                    ],
+                    "outputs": [{"name": "shares", "type": "uint256"}]
+                },
+                {
+                    "type": "event",
+                    "name": "Deposited",
+                    "anonymous": false,
+                    "inputs": [
+                        {"name": "user", "type": "address", "indexed": true},
+                        {
+                            "name": "position",
+                            "type": "tuple",
+                            "indexed": false,
+                            "components": [
+                                {"name": "id", "type": "uint64"},
+                                {"name": "active", "type": "bool"}
+                            ]
+                        }
+                    ]
+                },
+                {
+                    "type": "error",
+                    "name": "InsufficientShares",
+                    "inputs": [
+                        {"name": "available", "type": "uint256"},
+                        {"name": "required", "type": "uint256"}
+                    ]
+                }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(AbiType::parse("uint", &[]), Some(AbiType::Uint(256)));
+        assert_eq!(AbiType::parse("bytes4", &[]), Some(AbiType::FixedBytes(4)));
+        assert_eq!(
+            AbiType::parse("address[2][]", &[]),
+            Some(AbiType::Array(Box::new(AbiType::FixedArray(Box::new(AbiType::Address), 2))))
+        );
+        assert_eq!(AbiType::parse("bytes33", &[]), None);
+        assert_eq!(AbiType::parse("unknown", &[]), None);
+    }
+
+    #[test]
+    fn test_decode_call_with_dynamic_params() {
+        let abi = abi();
+        let abis = HashMap::from([(format!("0x{:x}", Address::repeat_byte(0xaa)), abi.clone())]);
+        let registry = AbiRegistry::new(Some(&abis));
+
+        let function = abi.functions().next().unwrap();
+        let input = [
+            function.selector().to_vec(),
+            word(64),
+            word(160),
+            word(2),
+            word(7),
+            word(8),
+            word(5),
+            b"hello".iter().copied().chain([0u8; 27]).collect(),
+        ]
+        .concat();
+
+        let decoded = registry
+            .decode_call(Address::repeat_byte(0xaa), &input, Some(&word(42)))
+            .unwrap();
+
+        assert_eq!(decoded.name, "deposit");
+        assert_eq!(decoded.signature, "deposit(uint256[],string)");
+        assert_eq!(decoded.inputs[0].value, "[7, 8]");
+        assert_eq!(decoded.inputs[1].value, "hello");
+        assert_eq!(decoded.outputs.unwrap()[0].value, "42");
+
+        assert!(registry
+            .decode_call(Address::repeat_byte(0xbb), &input, None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_decode_event_with_tuple() {
+        let abi = abi();
+        let address = Address::repeat_byte(0xaa);
+        let abis = HashMap::from([(format!("0x{:x}", address), abi.clone())]);
+        let registry = AbiRegistry::new(Some(&abis));
+
+        let event = abi.events().next().unwrap();
+        let topics = [event.selector(), Address::repeat_byte(0x11).into_word()];
+        let data = [word(3), word(1)].concat();
+
+        let decoded = registry.decode_event(address, &topics, &data).unwrap();
+        assert_eq!(decoded.signature, "Deposited(address,(uint64,bool))");
+        assert_eq!(decoded.params[1].value, "(3, true)");
+        assert!(decoded.params[0].indexed);
+        assert_eq!(
+            decoded.summary,
+            format!("Deposited(user=0x{:x}, position=(3, true))", Address::repeat_byte(0x11))
+        );
+    }
+
+    #[test]
+    fn test_decode_error_from_any_abi() {
+        let abi = abi();
+        let abis = HashMap::from([(format!("0x{:x}", Address::repeat_byte(0xaa)), abi.clone())]);
+        let registry = AbiRegistry::new(Some(&abis));
+
+        let error = abi.errors().next().unwrap();
+        let data = [error.selector().to_vec(), word(1), word(2)].concat();
+
+        let decoded = registry
+            .decode_error(Some(Address::repeat_byte(0xbb)), &data)
+            .unwrap();
+        assert_eq!(decoded.name, "InsufficientShares");
+        assert_eq!(decoded.params[1].value, "2");
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_array() {
+        let types = [AbiType::Array(Box::new(AbiType::Uint(256)))];
+        let data = [word(32), word(u64::MAX)].concat();
+        assert!(decode_params(&types, &data).is_none());
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_fixed_array() {
+        let types = [AbiType::parse("uint256[99999999999]", &[]).unwrap()];
+        assert!(decode_params(&types, &word(1)).is_none());
+    }
+
+    #[test]
+    fn test_head_size_overflow() {
+        let ty = AbiType::parse(&format!("uint256[{}][2]", usize::MAX), &[]).unwrap();
+        assert_eq!(ty.head_size(), None);
+        assert!(decode_params(&[ty], &word(1)).is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_deep_nesting() {
+        assert!(AbiType::parse(&format!("uint256{}", "[]".repeat(MAX_DEPTH)), &[]).is_some());
+        assert!(AbiType::parse(&format!("uint256{}", "[]".repeat(MAX_DEPTH + 1)), &[]).is_none());
+    }
+
+    #[test]
+    fn test_decode_rejects_aliased_offsets() {
+        // Every element of each level points at the same array of the next level,
+        // which would take 4^16 reads to decode
+        let depth = 16;
+        let ty = AbiType::parse(&format!("uint256{}", "[]".repeat(depth)), &[]).unwrap();
+        let mut data = word(32);
+        for _ in 0..depth {
+            data.extend(word(4));
+            data.extend((0..4).flat_map(|_| word(4 * 32)));
+        }
+        assert!(decode_params(&[ty], &data).is_none());
+    }
+}
@@ -0,0 +1,408 @@
+//! Built-in registry of well-known events.
+//!
+//! Events are matched by their `topic0` and by the number of indexed
+//! parameters, which disambiguates events sharing a signature across standards
+//! (e.g. ERC-20 and ERC-721 `Transfer`).
+
+use super::abi::{decode_params, decode_topic, AbiType};
+use crate::handlers::simulation::response::{DecodedEvent, DecodedEventParam};
+use alloy_primitives::{keccak256, B256};
+use std::sync::LazyLock;
+
+/// Description of a well-known event.
+struct EventSpec {
+    /// Event name.
+    name: &'static str,
+    /// Canonical signature, hashed to obtain `topic0`.
+    signature: &'static str,
+    /// Token or protocol standard.
+    standard: &'static str,
+    /// Human-readable description.
+    description: &'static str,
+    /// Parameters as `(name, type, indexed)`, in declaration order.
+    params: &'static [(&'static str, &'static str, bool)],
+    /// Summary template, `{param}` placeholders are replaced by parameter values.
+    summary: &'static str,
+}
+
+impl EventSpec {
+    fn indexed_count(&self) -> usize {
+        self.params
+            .iter()
+            .filter(|(_, _, indexed)| *indexed)
+            .count()
+    }
+}
+
+const EVENTS: &[EventSpec] = &[
+    EventSpec {
+        name: "Transfer",
+        signature: "Transfer(address,address,uint256)",
+        standard: "ERC20",
+        description: "Token transfer event",
+        params: &[("from", "address", true), ("to", "address", true), ("value", "uint256", false)],
+        summary: "Transfer {value} from {from} to {to}",
+    },
+    EventSpec {
+        name: "Approval",
+        signature: "Approval(address,address,uint256)",
+        standard: "ERC20",
+        description: "Token spending approval event",
+        params: &[
+            ("owner", "address", true),
+            ("spender", "address", true),
+            ("value", "uint256", false),
+        ],
+        summary: "{owner} approved {spender} to spend {value}",
+    },
+    EventSpec {
+        name: "Transfer",
+        signature: "Transfer(address,address,uint256)",
+        standard: "ERC721",
+        description: "NFT transfer event",
+        params: &[("from", "address", true), ("to", "address", true), ("tokenId", "uint256", true)],
+        summary: "Transfer token #{tokenId} from {from} to {to}",
+    },
+    EventSpec {
+        name: "Approval",
+        signature: "Approval(address,address,uint256)",
+        standard: "ERC721",
+        description: "NFT approval event",
+        params: &[
+            ("owner", "address", true),
+            ("approved", "address", true),
+            ("tokenId", "uint256", true),
+        ],
+        summary: "{owner} approved {approved} for token #{tokenId}",
+    },
+    EventSpec {
+        name: "ApprovalForAll",
+        signature: "ApprovalForAll(address,address,bool)",
+        standard: "ERC721/ERC1155",
+        description: "Operator approval for all tokens event",
+        params: &[
+            ("owner", "address", true),
+            ("operator", "address", true),
+            ("approved", "bool", false),
+        ],
+        summary: "{owner} set approval for all tokens of operator {operator} to {approved}",
+    },
+    EventSpec {
+        name: "TransferSingle",
+        signature: "TransferSingle(address,address,address,uint256,uint256)",
+        standard: "ERC1155",
+        description: "Multi-token single transfer event",
+        params: &[
+            ("operator", "address", true),
+            ("from", "address", true),
+            ("to", "address", true),
+            ("id", "uint256", false),
+            ("value", "uint256", false),
+        ],
+        summary: "Transfer {value} of token #{id} from {from} to {to}",
+    },
+    EventSpec {
+        name: "TransferBatch",
+        signature: "TransferBatch(address,address,address,uint256[],uint256[])",
+        standard: "ERC1155",
+        description: "Multi-token batch transfer event",
+        params: &[
+            ("operator", "address", true),
+            ("from", "address", true),
+            ("to", "address", true),
+            ("ids", "uint256[]", false),
+            ("values", "uint256[]", false),
+        ],
+        summary: "Transfer tokens {ids} with amounts {values} from {from} to {to}",
+    },
+    EventSpec {
+        name: "Deposit",
+        signature: "Deposit(address,uint256)",
+        standard: "WETH",
+        description: "Native token wrapping event",
+        params: &[("dst", "address", true), ("wad", "uint256", false)],
+        summary: "Wrap {wad} for {dst}",
+    },
+    EventSpec {
+        name: "Withdrawal",
+        signature: "Withdrawal(address,uint256)",
+        standard: "WETH",
+        description: "Native token unwrapping event",
+        params: &[("src", "address", true), ("wad", "uint256", false)],
+        summary: "Unwrap {wad} for {src}",
+    },
+    EventSpec {
+        name: "Swap",
+        signature: "Swap(address,uint256,uint256,uint256,uint256,address)",
+        standard: "Uniswap V2",
+        description: "Pair swap event",
+        params: &[
+            ("sender", "address", true),
+            ("amount0In", "uint256", false),
+            ("amount1In", "uint256", false),
+            ("amount0Out", "uint256", false),
+            ("amount1Out", "uint256", false),
+            ("to", "address", true),
+        ],
+        summary: "Swap {amount0In}/{amount1In} in for {amount0Out}/{amount1Out} out from {sender} \
+                  to {to}",
+    },
+    EventSpec {
+        name: "Mint",
+        signature: "Mint(address,uint256,uint256)",
+        standard: "Uniswap V2",
+        description: "Pair liquidity added event",
+        params: &[
+            ("sender", "address", true),
+            ("amount0", "uint256", false),
+            ("amount1", "uint256", false),
+        ],
+        summary: "Add liquidity {amount0}/{amount1} from {sender}",
+    },
+    EventSpec {
+        name: "Burn",
+        signature: "Burn(address,uint256,uint256,address)",
+        standard: "Uniswap V2",
+        description: "Pair liquidity removed event",
+        params: &[
+            ("sender", "address", true),
+            ("amount0", "uint256", false),
+            ("amount1", "uint256", false),
+            ("to", "address", true),
+        ],
+        summary: "Remove liquidity {amount0}/{amount1} from {sender} to {to}",
+    },
+    EventSpec {
+        name: "Swap",
+        signature: "Swap(address,address,int256,int256,uint160,uint128,int24)",
+        standard: "Uniswap V3",
+        description: "Pool swap event",
+        params: &[
+            ("sender", "address", true),
+            ("recipient", "address", true),
+            ("amount0", "int256", false),
+            ("amount1", "int256", false),
+            ("sqrtPriceX96", "uint160", false),
+            ("liquidity", "uint128", false),
+            ("tick", "int24", false),
+        ],
+        summary: "Swap {amount0}/{amount1} from {sender} to {recipient}",
+    },
+    EventSpec {
+        name: "Mint",
+        signature: "Mint(address,address,int24,int24,uint128,uint256,uint256)",
+        standard: "Uniswap V3",
+        description: "Pool liquidity added event",
+        params: &[
+            ("sender", "address", false),
+            ("owner", "address", true),
+            ("tickLower", "int24", true),
+            ("tickUpper", "int24", true),
+            ("amount", "uint128", false),
+            ("amount0", "uint256", false),
+            ("amount1", "uint256", false),
+        ],
+        summary: "Add {amount} liquidity in ticks [{tickLower}, {tickUpper}] for {owner}",
+    },
+    EventSpec {
+        name: "Burn",
+        signature: "Burn(address,int24,int24,uint128,uint256,uint256)",
+        standard: "Uniswap V3",
+        description: "Pool liquidity removed event",
+        params: &[
+            ("owner", "address", true),
+            ("tickLower", "int24", true),
+            ("tickUpper", "int24", true),
+            ("amount", "uint128", false),
+            ("amount0", "uint256", false),
+            ("amount1", "uint256", false),
+        ],
+        summary: "Remove {amount} liquidity in ticks [{tickLower}, {tickUpper}] for {owner}",
+    },
+];
+
+/// Registry of well-known events keyed by `topic0`.
+static REGISTRY: LazyLock<Vec<(B256, &'static EventSpec)>> = LazyLock::new(|| {
+    EVENTS
+        .iter()
+        .map(|spec| (keccak256(spec.signature), spec))
+        .collect()
+});
+
+/// Decodes a log against the built-in registry of well-known events.
+///
+/// Returns `None` if the event is unknown or its data does not match the
+/// expected layout.
+pub fn decode_event(topics: &[B256], data: &[u8]) -> Option<DecodedEvent> {
+    let (topic0, indexed_topics) = topics.split_first()?;
+    let indexed_count = indexed_topics.len();
+    let spec = REGISTRY
+        .iter()
+        .find(|(hash, spec)| hash == topic0 && spec.indexed_count() == indexed_count)
+        .map(|(_, spec)| *spec)?;
+
+    let types = spec
+        .params
+        .iter()
+        .map(|(_, ty, _)| AbiType::parse(ty, &[]))
+        .collect::<Option<Vec<_>>>()?;
+    let data_types: Vec<AbiType> = spec
+        .params
+        .iter()
+        .zip(&types)
+        .filter(|((_, _, indexed), _)| !indexed)
+        .map(|(_, ty)| ty.clone())
+        .collect();
+
+    let mut data_values = decode_params(&data_types, data)?.into_iter();
+    let mut indexed_topics = indexed_topics.iter();
+    let mut params = Vec::with_capacity(spec.params.len());
+
+    for ((name, param_type, indexed), ty) in spec.params.iter().zip(&types) {
+        let value = if *indexed {
+            decode_topic(ty, indexed_topics.next()?)?
+        } else {
+            data_values.next()?
+        };
+
+        params.push(DecodedEventParam {
+            name: (*name).to_string(),
+            param_type: (*param_type).to_string(),
+            value,
+            indexed: *indexed,
+        });
+    }
+
+    Some(DecodedEvent {
+        name: spec.name.to_string(),
+        signature: spec.signature.to_string(),
+        standard: Some(spec.standard.to_string()),
+        description: spec.description.to_string(),
+        summary: render_summary(spec.summary, &params),
+        params,
+    })
+}
+
+/// Renders a summary template, shortening addresses for readability.
+fn render_summary(template: &str, params: &[DecodedEventParam]) -> String {
+    params.iter().fold(template.to_string(), |summary, param| {
+        let value = if param.param_type == "address" {
+            shorten_address(&param.value)
+        } else {
+            param.value.clone()
+        };
+        summary.replace(&format!("{{{}}}", param.name), &value)
+    })
+}
+
+fn shorten_address(address: &str) -> String {
+    if address.len() <= 10 {
+        return address.to_string();
+    }
+    format!("{}...{}", &address[..6], &address[address.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, I256, U256};
+
+    fn address_topic(byte: u8) -> B256 {
+        Address::repeat_byte(byte).into_word()
+    }
+
+    fn word(value: U256) -> Vec<u8> {
+        value.to_be_bytes_vec()
+    }
+
+    #[test]
+    fn test_decode_erc20_transfer() {
+        let topics = [
+            keccak256("Transfer(address,address,uint256)"),
+            address_topic(0x11),
+            address_topic(0x22),
+        ];
+        let decoded = decode_event(&topics, &word(U256::from(1_000_000))).unwrap();
+
+        assert_eq!(decoded.name, "Transfer");
+        assert_eq!(decoded.standard.as_deref(), Some("ERC20"));
+        assert_eq!(decoded.params.len(), 3);
+        assert_eq!(decoded.params[2].value, "1000000");
+        assert!(!decoded.params[2].indexed);
+        assert_eq!(decoded.summary, "Transfer 1000000 from 0x1111...1111 to 0x2222...2222");
+    }
+
+    #[test]
+    fn test_decode_erc721_transfer_by_topic_count() {
+        let topics = [
+            keccak256("Transfer(address,address,uint256)"),
+            address_topic(0x11),
+            address_topic(0x22),
+            B256::from(U256::from(42)),
+        ];
+        let decoded = decode_event(&topics, &[]).unwrap();
+
+        assert_eq!(decoded.standard.as_deref(), Some("ERC721"));
+        assert_eq!(decoded.params[2].value, "42");
+        assert!(decoded.params[2].indexed);
+    }
+
+    #[test]
+    fn test_decode_erc1155_transfer_batch() {
+        let topics = [
+            keccak256("TransferBatch(address,address,address,uint256[],uint256[])"),
+            address_topic(0x01),
+            address_topic(0x11),
+            address_topic(0x22),
+        ];
+        let data = [
+            word(U256::from(64)),
+            word(U256::from(160)),
+            word(U256::from(2)),
+            word(U256::from(1)),
+            word(U256::from(2)),
+            word(U256::from(2)),
+            word(U256::from(10)),
+            word(U256::from(20)),
+        ]
+        .concat();
+
+        let decoded = decode_event(&topics, &data).unwrap();
+        assert_eq!(decoded.params[3].value, "[1, 2]");
+        assert_eq!(decoded.params[4].value, "[10, 20]");
+    }
+
+    #[test]
+    fn test_decode_uniswap_v3_swap_signed_amounts() {
+        let topics = [
+            keccak256("Swap(address,address,int256,int256,uint160,uint128,int24)"),
+            address_topic(0x11),
+            address_topic(0x22),
+        ];
+        let data = [
+            word(I256::try_from(-500).unwrap().into_raw()),
+            word(U256::from(250)),
+            word(U256::from(1) << 96),
+            word(U256::from(1_000)),
+            word(I256::try_from(-887_272).unwrap().into_raw()),
+        ]
+        .concat();
+
+        let decoded = decode_event(&topics, &data).unwrap();
+        assert_eq!(decoded.standard.as_deref(), Some("Uniswap V3"));
+        assert_eq!(decoded.params[2].value, "-500");
+        assert_eq!(decoded.params[6].value, "-887272");
+    }
+
+    #[test]
+    fn test_unknown_or_truncated_event() {
+        assert!(decode_event(&[B256::repeat_byte(0xff)], &[]).is_none());
+
+        let topics = [
+            keccak256("Transfer(address,address,uint256)"),
+            address_topic(0x11),
+            address_topic(0x22),
+        ];
+        assert!(decode_event(&topics, &[0u8; 16]).is_none());
+    }
+}
@@ -0,0 +1,17 @@
+//! Decoding of raw EVM data into human-readable structures.
+//!
+//! This module turns raw log topics and data into [`DecodedEvent`]s using a
+//! built-in registry of well-known events (token standards, WETH, Uniswap), and
+//! decodes calldata, return data, events and custom errors of contracts for
+//! which the caller supplied an ABI. Revert data is decoded into the built-in
+//! `Error(string)` and `Panic(uint256)` errors regardless of any ABI.
+//!
+//! [`DecodedEvent`]: crate::handlers::simulation::response::DecodedEvent
+
+pub mod abi;
+pub mod events;
+pub mod revert;
+
+pub use abi::AbiRegistry;
+pub use events::decode_event;
+pub use revert::{decode_revert, revert_reason};
@@ -0,0 +1,146 @@
+//! Decoding of revert data.
+//!
+//! Solidity reverts with `Error(string)` for `require`/`revert` messages and
+//! with `Panic(uint256)` for compiler-inserted checks. Any other selector is a
+//! custom error, which can only be decoded with the contract ABI (see
+//! [`AbiRegistry::decode_error`](super::AbiRegistry::decode_error)).
+
+use super::abi::{decode_params, AbiType};
+use crate::handlers::simulation::response::{DecodedError, DecodedParam};
+use alloy_primitives::{hex, U256};
+use std::str::FromStr;
+
+/// Selector of `Error(string)`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of `Panic(uint256)`.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Decodes `Error(string)` and `Panic(uint256)` revert data.
+///
+/// Returns `None` for empty revert data and custom errors.
+pub fn decode_revert(data: &[u8]) -> Option<DecodedError> {
+    let (selector, arguments) = data.split_at_checked(4)?;
+
+    let (name, param, ty) = if selector == ERROR_SELECTOR {
+        ("Error", "string", AbiType::String)
+    } else if selector == PANIC_SELECTOR {
+        ("Panic", "uint256", AbiType::Uint(256))
+    } else {
+        return None;
+    };
+
+    let value = decode_params(&[ty], arguments)?.pop()?;
+    let description = (name == "Panic")
+        .then(|| {
+            U256::from_str(&value)
+                .ok()
+                .map(|code| panic_description(code).to_string())
+        })
+        .flatten();
+
+    Some(DecodedError {
+        name: name.to_string(),
+        signature: format!("{}({})", name, param),
+        selector: hex::encode_prefixed(selector),
+        params: vec![DecodedParam {
+            name: if name == "Panic" { "code" } else { "message" }.to_string(),
+            param_type: param.to_string(),
+            value,
+        }],
+        description,
+    })
+}
+
+/// Returns the meaning of a Solidity panic code.
+pub fn panic_description(code: U256) -> &'static str {
+    match code.try_into().unwrap_or(u64::MAX) {
+        0x00 => "Generic compiler panic",
+        0x01 => "Assertion failed",
+        0x11 => "Arithmetic overflow or underflow",
+        0x12 => "Division or modulo by zero",
+        0x21 => "Invalid enum value conversion",
+        0x22 => "Incorrectly encoded storage byte array",
+        0x31 => "Pop on empty array",
+        0x32 => "Array index out of bounds",
+        0x41 => "Too much memory allocated",
+        0x51 => "Call to zero-initialized internal function",
+        _ => "Unknown panic code",
+    }
+}
+
+/// Renders a human-readable revert reason from a decoded error.
+pub fn revert_reason(error: &DecodedError) -> String {
+    match (error.name.as_str(), error.params.first()) {
+        ("Error", Some(message)) if error.signature == "Error(string)" => message.value.clone(),
+        ("Panic", Some(code)) if error.signature == "Panic(uint256)" => format!(
+            "Panic(0x{:02x}): {}",
+            U256::from_str(&code.value).unwrap_or_default(),
+            error.description.as_deref().unwrap_or("Unknown panic code")
+        ),
+        _ => format!(
+            "{}({})",
+            error.name,
+            error
+                .params
+                .iter()
+                .map(|param| param.value.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Fallback reason for revert data that could not be decoded.
+pub fn undecoded_reason(data: &[u8]) -> String {
+    match data.get(..4) {
+        Some(selector) => format!("Unknown custom error {}", hex::encode_prefixed(selector)),
+        None => "Execution reverted without data".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_error(message: &str) -> Vec<u8> {
+        let mut padded = message.as_bytes().to_vec();
+        padded.resize(message.len().div_ceil(32) * 32, 0);
+        [
+            ERROR_SELECTOR.to_vec(),
+            U256::from(32).to_be_bytes_vec(),
+            U256::from(message.len()).to_be_bytes_vec(),
+            padded,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_decode_error_string() {
+        let decoded = decode_revert(&encode_error("Insufficient balance")).unwrap();
+
+        assert_eq!(decoded.signature, "Error(string)");
+        assert_eq!(decoded.selector, "0x08c379a0");
+        assert_eq!(revert_reason(&decoded), "Insufficient balance");
+    }
+
+    #[test]
+    fn test_decode_panic_code() {
+        let data = [PANIC_SELECTOR.to_vec(), U256::from(0x11).to_be_bytes_vec()].concat();
+        let decoded = decode_revert(&data).unwrap();
+
+        assert_eq!(decoded.params[0].value, "17");
+        assert_eq!(decoded.description.as_deref(), Some("Arithmetic overflow or underflow"));
+        assert_eq!(revert_reason(&decoded), "Panic(0x11): Arithmetic overflow or underflow");
+    }
+
+    #[test]
+    fn test_custom_and_empty_revert_data() {
+        let custom = [0xde, 0xad, 0xbe, 0xef, 0x00];
+        assert!(decode_revert(&custom).is_none());
+        assert_eq!(undecoded_reason(&custom), "Unknown custom error 0xdeadbeef");
+
+        assert!(decode_revert(&[]).is_none());
+        assert_eq!(undecoded_reason(&[]), "Execution reverted without data");
+    }
+}
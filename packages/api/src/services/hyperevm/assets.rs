@@ -0,0 +1,305 @@
+//! ERC-20 asset change tracking for simulations.
+//!
+//! Asset changes are computed in two passes: the first simulation tells us which
+//! tokens the tracked account interacted with (through `Transfer` logs), then a
+//! probe simulation wraps the original calls with `balanceOf` reads executed
+//! before and after them, alongside `decimals`/`symbol` lookups for each token.
+
+use super::transfers::NATIVE_TRANSFER_ADDRESS;
+use crate::handlers::simulation::response::{AssetChange, BalanceChange, TokenInfo};
+use alloy_primitives::{address, Address, Bytes, TxKind, U256};
+use alloy_rpc_types_eth::{
+    simulate::{SimBlock, SimCallResult, SimulatePayload, SimulatedBlock},
+    TransactionInput, TransactionRequest as AlloyTransactionRequest,
+};
+use alloy_sol_types::{sol, SolCall, SolEvent};
+
+sol! {
+    function balanceOf(address owner) external view returns (uint256);
+    function decimals() external view returns (uint8);
+    function symbol() external view returns (string);
+
+    event Transfer(address indexed from, address indexed to, uint256 value);
+}
+
+/// Sender of the probe calls.
+///
+/// Probes must not be sent from the tracked account: the reads placed before
+/// the user calls would bump its nonce and shift the addresses of the contracts
+/// it creates. This address holds no balance, which is fine as probes pay no fees.
+pub const PROBE_SENDER: Address = address!("0x00000000000000000000000000000000a1717ace");
+
+/// Gas limit given to every probe call (`balanceOf`, `decimals`, `symbol`).
+const PROBE_CALL_GAS: u64 = 100_000;
+
+/// Number of metadata calls issued per token before the user calls
+/// (`decimals`, `symbol`, `balanceOf`).
+const CALLS_PER_TOKEN_BEFORE: usize = 3;
+
+/// Returns the ERC-20 tokens that emitted a `Transfer` involving `account`.
+///
+/// ERC-721 transfers share the same signature but index the token id as a
+/// third topic, so only logs with exactly three topics are considered. Tokens
+/// are returned in first-seen order without duplicates.
+pub fn touched_tokens<'a>(
+    account: Address,
+    calls: impl IntoIterator<Item = &'a SimCallResult>,
+) -> Vec<Address> {
+    let account_topic = account.into_word();
+    let mut tokens = Vec::new();
+
+    for log in calls.into_iter().flat_map(|call| call.logs.iter()) {
+        let topics = log.topics();
+        if topics.len() != 3 || topics[0] != Transfer::SIGNATURE_HASH {
+            continue;
+        }
+        if topics[1] != account_topic && topics[2] != account_topic {
+            continue;
+        }
+
+        let token = log.address();
+        if token != NATIVE_TRANSFER_ADDRESS && !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+
+    tokens
+}
+
+/// Builds the probe payload used to read balances around the original calls.
+///
+/// The blocks of the original payload are kept in place, with their calls and
+/// overrides, and surrounded by the token probes: balances are read before the
+/// calls of the first block and after the calls of the last block, so the
+/// calls execute against the exact same state. Probes are sent from
+/// [`PROBE_SENDER`] with validation disabled, so they neither pay fees nor touch
+/// the nonce of `account`.
+pub fn build_probe_payload(
+    account: Address,
+    tokens: &[Address],
+    original: &SimulatePayload<AlloyTransactionRequest>,
+) -> SimulatePayload<AlloyTransactionRequest> {
+    let balance_call = balanceOfCall { owner: account }.abi_encode();
+
+    let mut block_state_calls = original.block_state_calls.clone();
+    if block_state_calls.is_empty() {
+        block_state_calls.push(SimBlock {
+            block_overrides: None,
+            state_overrides: None,
+            calls: Vec::new(),
+        });
+    }
+
+    let pre_probes = tokens.iter().flat_map(|token| {
+        [
+            probe_call(*token, decimalsCall {}.abi_encode()),
+            probe_call(*token, symbolCall {}.abi_encode()),
+            probe_call(*token, balance_call.clone()),
+        ]
+    });
+    block_state_calls[0].calls.splice(0..0, pre_probes);
+
+    let last = block_state_calls.len() - 1;
+    block_state_calls[last].calls.extend(
+        tokens
+            .iter()
+            .map(|token| probe_call(*token, balance_call.clone())),
+    );
+
+    SimulatePayload {
+        block_state_calls,
+        trace_transfers: false,
+        validation: false,
+        return_full_transactions: false,
+    }
+}
+
+/// Extracts the asset changes from the result of a probe simulation built with
+/// [`build_probe_payload`].
+///
+/// Tokens whose balance could not be read on both sides, or whose balance did
+/// not change, are omitted.
+pub fn decode_probe_results(tokens: &[Address], probe: &[SimulatedBlock]) -> Vec<AssetChange> {
+    let (Some(first_block), Some(last_block)) = (probe.first(), probe.last()) else {
+        return Vec::new();
+    };
+    let post_offset = last_block.calls.len().saturating_sub(tokens.len());
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(index, token)| {
+            let base = index * CALLS_PER_TOKEN_BEFORE;
+            let decimals = decode_return::<decimalsCall>(first_block.calls.get(base)?);
+            let symbol = decode_return::<symbolCall>(first_block.calls.get(base + 1)?);
+            let pre = decode_return::<balanceOfCall>(first_block.calls.get(base + 2)?)?;
+            let post = decode_return::<balanceOfCall>(last_block.calls.get(post_offset + index)?)?;
+
+            (pre != post).then(|| AssetChange {
+                token: TokenInfo { address: format!("0x{:x}", token), decimals, symbol },
+                value: BalanceChange {
+                    pre: format!("0x{:x}", pre),
+                    post: format!("0x{:x}", post),
+                    diff: format_signed_diff(pre, post),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Formats `post - pre` as a signed hex string (`-0x...` when the balance decreased).
+pub fn format_signed_diff(pre: U256, post: U256) -> String {
+    if post >= pre {
+        format!("0x{:x}", post - pre)
+    } else {
+        format!("-0x{:x}", pre - post)
+    }
+}
+
+fn probe_call(token: Address, data: Vec<u8>) -> AlloyTransactionRequest {
+    AlloyTransactionRequest {
+        from: Some(PROBE_SENDER),
+        to: Some(TxKind::Call(token)),
+        gas: Some(PROBE_CALL_GAS),
+        input: TransactionInput { input: Some(Bytes::from(data)), data: None },
+        ..Default::default()
+    }
+}
+
+fn decode_return<C: SolCall>(result: &SimCallResult) -> Option<C::Return> {
+    if !result.status {
+        return None;
+    }
+    C::abi_decode_returns(&result.return_data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Log as PrimitiveLog, LogData};
+    use alloy_rpc_types_eth::Log;
+
+    fn transfer_log(token: Address, from: Address, to: Address) -> Log {
+        Log {
+            inner: PrimitiveLog {
+                address: token,
+                data: LogData::new_unchecked(
+                    vec![Transfer::SIGNATURE_HASH, from.into_word(), to.into_word()],
+                    U256::from(1).to_be_bytes_vec().into(),
+                ),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn call_with_logs(logs: Vec<Log>) -> SimCallResult {
+        SimCallResult { return_data: Bytes::new(), logs, gas_used: 0, status: true, error: None }
+    }
+
+    #[test]
+    fn test_touched_tokens_filters_by_account() {
+        let account = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let token_a = Address::repeat_byte(0xaa);
+        let token_b = Address::repeat_byte(0xbb);
+        let token_c = Address::repeat_byte(0xcc);
+
+        let call = call_with_logs(vec![
+            transfer_log(token_a, account, other),
+            transfer_log(token_b, other, account),
+            transfer_log(token_a, other, account),
+            transfer_log(token_c, other, other),
+            transfer_log(NATIVE_TRANSFER_ADDRESS, account, other),
+        ]);
+
+        assert_eq!(touched_tokens(account, [&call]), vec![token_a, token_b]);
+    }
+
+    #[test]
+    fn test_format_signed_diff() {
+        assert_eq!(format_signed_diff(U256::from(10), U256::from(25)), "0xf");
+        assert_eq!(format_signed_diff(U256::from(25), U256::from(10)), "-0xf");
+        assert_eq!(format_signed_diff(U256::from(7), U256::from(7)), "0x0");
+    }
+
+    #[test]
+    fn test_probe_payload_layout() {
+        let account = Address::repeat_byte(0x11);
+        let tokens = [Address::repeat_byte(0xaa), Address::repeat_byte(0xbb)];
+        let original = SimulatePayload {
+            block_state_calls: vec![SimBlock {
+                block_overrides: None,
+                state_overrides: None,
+                calls: vec![AlloyTransactionRequest::default()],
+            }],
+            trace_transfers: true,
+            validation: true,
+            return_full_transactions: false,
+        };
+
+        let payload = build_probe_payload(account, &tokens, &original);
+        let calls = &payload.block_state_calls[0].calls;
+
+        assert_eq!(calls.len(), 2 * CALLS_PER_TOKEN_BEFORE + 1 + 2);
+        assert!(!payload.validation);
+        assert_eq!(calls[0].from, Some(PROBE_SENDER));
+        assert_eq!(calls[calls.len() - 1].from, Some(PROBE_SENDER));
+        assert_eq!(calls[CALLS_PER_TOKEN_BEFORE].to, Some(TxKind::Call(tokens[1])));
+        assert_eq!(calls[2 * CALLS_PER_TOKEN_BEFORE], AlloyTransactionRequest::default());
+        assert_eq!(calls[calls.len() - 1].to, Some(TxKind::Call(tokens[1])));
+    }
+
+    #[test]
+    fn test_probe_payload_spans_blocks() {
+        let account = Address::repeat_byte(0x11);
+        let tokens = [Address::repeat_byte(0xaa)];
+        let block = SimBlock {
+            block_overrides: None,
+            state_overrides: None,
+            calls: vec![AlloyTransactionRequest::default()],
+        };
+        let original = SimulatePayload {
+            block_state_calls: vec![block.clone(), block],
+            trace_transfers: false,
+            validation: false,
+            return_full_transactions: false,
+        };
+
+        let payload = build_probe_payload(account, &tokens, &original);
+
+        assert_eq!(payload.block_state_calls.len(), 2);
+        assert_eq!(payload.block_state_calls[0].calls.len(), CALLS_PER_TOKEN_BEFORE + 1);
+        assert_eq!(payload.block_state_calls[1].calls.len(), 1 + 1);
+    }
+
+    #[test]
+    fn test_decode_probe_results() {
+        let token = Address::repeat_byte(0xaa);
+        let ok = |data: Vec<u8>| SimCallResult {
+            return_data: data.into(),
+            logs: vec![],
+            gas_used: 0,
+            status: true,
+            error: None,
+        };
+
+        let probe = SimulatedBlock {
+            inner: Default::default(),
+            calls: vec![
+                ok(decimalsCall::abi_encode_returns(&6u8)),
+                ok(symbolCall::abi_encode_returns(&"USDC".to_string())),
+                ok(balanceOfCall::abi_encode_returns(&U256::from(1_000))),
+                call_with_logs(vec![]),
+                ok(balanceOfCall::abi_encode_returns(&U256::from(400))),
+            ],
+        };
+
+        let changes = decode_probe_results(&[token], &[probe]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].token.decimals, Some(6));
+        assert_eq!(changes[0].token.symbol.as_deref(), Some("USDC"));
+        assert_eq!(changes[0].value.pre, "0x3e8");
+        assert_eq!(changes[0].value.post, "0x190");
+        assert_eq!(changes[0].value.diff, "-0x258");
+    }
+}
@@ -0,0 +1,178 @@
+//! Gas limit estimation helpers.
+//!
+//! The minimal gas limit of a transaction is not its gas used: refunds are only
+//! applied at the end of the execution, and the EIP-150 63/64 rule withholds
+//! 1/64 of the remaining gas from every nested call. The estimate is therefore
+//! found by executing the transaction with different gas limits, starting from
+//! an optimistic limit derived from the gas used and narrowing it down with a
+//! binary search ([`GasSearch`]) bounded by a limit known to succeed, e.g. the
+//! estimate of the node.
+
+use crate::handlers::simulation::response::{GasSuggestion, GasSuggestionKind};
+
+/// Gas stipend granted to value-transferring calls.
+const CALL_STIPEND: u64 = 2_300;
+
+/// Safety margin applied on top of the estimate for the recommended gas limit.
+pub const SAFETY_MARGIN_PERCENT: u64 = 20;
+
+/// Maximum number of executions performed by a gas search.
+const MAX_SEARCH_ITERATIONS: u32 = 32;
+
+/// Calldata cost per zero byte.
+const ZERO_BYTE_GAS: u64 = 4;
+
+/// Calldata cost per non-zero byte.
+const NON_ZERO_BYTE_GAS: u64 = 16;
+
+/// Share of the gas used above which the calldata cost is worth reducing.
+const CALLDATA_SUGGESTION_PERCENT: u64 = 10;
+
+/// Gas limit likely to be sufficient for a transaction that used `gas_used`,
+/// accounting for the stipend and the gas withheld from nested calls.
+pub const fn optimistic_gas_limit(gas_used: u64) -> u64 {
+    (gas_used + CALL_STIPEND) * 64 / 63
+}
+
+/// Recommended gas limit for an estimate, capped to the block gas limit.
+pub const fn recommended_gas_limit(estimate: u64, cap: u64) -> u64 {
+    let limit = estimate + estimate * SAFETY_MARGIN_PERCENT / 100;
+    if limit < cap {
+        limit
+    } else {
+        cap
+    }
+}
+
+/// Intrinsic gas paid for the calldata.
+pub fn calldata_gas(data: &[u8]) -> u64 {
+    data.iter()
+        .map(|byte| if *byte == 0 { ZERO_BYTE_GAS } else { NON_ZERO_BYTE_GAS })
+        .sum()
+}
+
+/// Suggests reducing the calldata when it accounts for a significant share of
+/// the gas used.
+pub fn calldata_suggestion(data: &[u8], gas_used: u64) -> Option<GasSuggestion> {
+    let cost = calldata_gas(data);
+    if gas_used == 0 || cost * 100 < gas_used * CALLDATA_SUGGESTION_PERCENT {
+        return None;
+    }
+
+    Some(GasSuggestion {
+        kind: GasSuggestionKind::Calldata,
+        description: format!(
+            "Calldata costs {} gas ({}% of the gas used), packing arguments or passing less data \
+             lowers the cost",
+            cost,
+            cost * 100 / gas_used
+        ),
+        estimated_savings: None,
+        access_list: None,
+    })
+}
+
+/// Binary search of the minimal gas limit for which a transaction succeeds.
+///
+/// The search keeps an exclusive lower bound (a limit known or assumed to fail)
+/// and an inclusive upper bound (a limit known to succeed). The optimistic limit
+/// is probed first as it is usually sufficient, which narrows the search to a
+/// few iterations.
+#[derive(Debug, Clone)]
+pub struct GasSearch {
+    lo: u64,
+    hi: u64,
+    optimistic: Option<u64>,
+    iterations: u32,
+}
+
+impl GasSearch {
+    /// Creates a search for a transaction that used `gas_used` and is known to
+    /// succeed with the `cap` gas limit.
+    pub const fn new(gas_used: u64, cap: u64) -> Self {
+        let optimistic = optimistic_gas_limit(gas_used);
+        Self {
+            lo: gas_used.saturating_sub(1),
+            hi: cap,
+            optimistic: if optimistic < cap { Some(optimistic) } else { None },
+            iterations: 0,
+        }
+    }
+
+    /// Returns the next gas limit to execute, or `None` once the search is done.
+    pub const fn next_probe(&mut self) -> Option<u64> {
+        if let Some(optimistic) = self.optimistic.take() {
+            return Some(optimistic);
+        }
+        if self.hi - self.lo <= 1 || self.iterations >= MAX_SEARCH_ITERATIONS {
+            return None;
+        }
+        Some(self.lo + (self.hi - self.lo) / 2)
+    }
+
+    /// Records the outcome of executing the transaction with `gas_limit`.
+    pub const fn record(&mut self, gas_limit: u64, success: bool) {
+        self.iterations += 1;
+        if success {
+            self.hi = gas_limit;
+        } else {
+            self.lo = gas_limit;
+        }
+    }
+
+    /// Minimal successful gas limit found so far.
+    pub const fn estimate(&self) -> u64 {
+        self.hi
+    }
+
+    /// Number of executions recorded.
+    pub const fn iterations(&self) -> u32 {
+        self.iterations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(mut search: GasSearch, required: u64) -> GasSearch {
+        while let Some(gas_limit) = search.next_probe() {
+            search.record(gas_limit, gas_limit >= required);
+        }
+        search
+    }
+
+    #[test]
+    fn test_gas_search_finds_minimal_limit() {
+        // Nested calls make the transaction need more than its gas used
+        let search = run(GasSearch::new(50_000, 30_000_000), 50_812);
+        assert_eq!(search.estimate(), 50_812);
+
+        // The optimistic limit narrows the search to a handful of iterations
+        assert!(search.iterations() <= 14);
+    }
+
+    #[test]
+    fn test_gas_search_bounded_by_node_estimate() {
+        // The node estimate is slightly above the minimal limit
+        let search = run(GasSearch::new(50_000, 51_500), 50_812);
+        assert_eq!(search.estimate(), 50_812);
+        assert!(search.iterations() <= 12);
+    }
+
+    #[test]
+    fn test_gas_search_when_optimistic_limit_fails() {
+        let search = run(GasSearch::new(21_000, 30_000_000), 100_000);
+        assert_eq!(search.estimate(), 100_000);
+    }
+
+    #[test]
+    fn test_recommended_limit_and_calldata_gas() {
+        assert_eq!(recommended_gas_limit(100_000, 30_000_000), 120_000);
+        assert_eq!(recommended_gas_limit(100_000, 110_000), 110_000);
+        assert_eq!(calldata_gas(&[0xa9, 0x05, 0x00, 0x00]), 40);
+
+        assert!(calldata_suggestion(&[0xff; 32], 21_512).is_none());
+        assert!(calldata_suggestion(&[0xff; 256], 25_096).is_some());
+    }
+}
@@ -0,0 +1,320 @@
+//! Gas breakdown of an execution, computed from its struct logs.
+//!
+//! Every opcode is charged the gas it consumed exclusively, which is the
+//! difference of remaining gas between two consecutive opcodes of the same
+//! frame. Opcodes entering a nested frame (calls and creates) are charged when
+//! the frame returns, minus everything consumed inside it, so nested executions
+//! are not counted twice. Each charge is then attributed to a category based on
+//! the opcode, with the memory expansion split out.
+//!
+//! The breakdown sums to the gas used: the refund is derived from the gas
+//! consumed minus the gas used, within the EIP-3529 cap, and gas used that the
+//! execution does not explain (e.g. the EIP-7623 calldata floor) is reported as
+//! `unexplained`. The storage `initialization`, `modifications` and `cleanupRefund`
+//! fields are subsets of `writes` and `refund`, reported for information.
+
+use super::gas::calldata_gas;
+use crate::{handlers::trace::StructLog, types::GasBreakdown};
+use alloy_consensus::Transaction as _;
+use alloy_eip2930::AccessList;
+use alloy_primitives::TxKind;
+use alloy_rpc_types_eth::{Transaction, TransactionRequest};
+
+/// Base cost of every transaction.
+const TX_BASE_GAS: u64 = 21_000;
+
+/// Additional cost of contract creation transactions.
+const TX_CREATE_GAS: u64 = 32_000;
+
+/// Cost per word of init code (EIP-3860).
+const INITCODE_WORD_GAS: u64 = 2;
+
+/// Cost per address of the access list (EIP-2930).
+const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+
+/// Cost per storage key of the access list (EIP-2930).
+const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+
+/// Minimal charge of a `SSTORE` setting a zero slot to a non-zero value.
+const SSTORE_SET_GAS: u64 = 20_000;
+
+/// Minimal charge of a `SSTORE` modifying a non-zero slot.
+const SSTORE_RESET_GAS: u64 = 2_900;
+
+/// The refund is capped to this fraction of the gas consumed (EIP-3529).
+const MAX_REFUND_QUOTIENT: u64 = 5;
+
+/// Static cost of `MLOAD`, `MSTORE` and `MSTORE8`.
+const MEMORY_OPCODE_GAS: u64 = 3;
+
+/// The only halting error that returns the remaining gas. Other errors, e.g.
+/// running out of gas, burn it.
+const REVERT_OPCODE: &str = "REVERT";
+
+/// Intrinsic cost of a transaction, paid before its execution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IntrinsicGas {
+    /// Base, calldata and contract creation cost.
+    pub base: u64,
+    /// Access list cost.
+    pub access_list: u64,
+}
+
+impl IntrinsicGas {
+    /// Computes the intrinsic cost of a transaction.
+    pub fn new(input: &[u8], is_create: bool, access_list: Option<&AccessList>) -> Self {
+        let mut base = TX_BASE_GAS + calldata_gas(input);
+        if is_create {
+            base += TX_CREATE_GAS + INITCODE_WORD_GAS * (input.len() as u64).div_ceil(32);
+        }
+
+        let access_list = access_list.map_or(0, |list| {
+            list.iter()
+                .map(|item| {
+                    ACCESS_LIST_ADDRESS_GAS +
+                        ACCESS_LIST_STORAGE_KEY_GAS * item.storage_keys.len() as u64
+                })
+                .sum()
+        });
+
+        Self { base, access_list }
+    }
+}
+
+impl From<&TransactionRequest> for IntrinsicGas {
+    fn from(tx: &TransactionRequest) -> Self {
+        Self::new(
+            tx.input.input().map_or(&[][..], |input| input.as_ref()),
+            !matches!(tx.to, Some(TxKind::Call(_))),
+            tx.access_list.as_ref(),
+        )
+    }
+}
+
+impl From<&Transaction> for IntrinsicGas {
+    fn from(tx: &Transaction) -> Self {
+        Self::new(tx.input(), tx.kind().is_create(), tx.access_list())
+    }
+}
+
+/// Computes the gas breakdown of an execution from its struct logs.
+///
+/// `gas_used` is the gas used by the transaction, after refunds.
+pub fn compute_gas_breakdown(
+    logs: &[StructLog],
+    intrinsic: IntrinsicGas,
+    gas_used: u64,
+) -> GasBreakdown {
+    let mut breakdown = GasBreakdown {
+        intrinsic: intrinsic.base,
+        access_list: intrinsic.access_list,
+        ..Default::default()
+    };
+
+    // Frames being executed: index of the opcode that entered the frame and gas
+    // consumed inside the frame so far. The bottom entry is the transaction.
+    let mut frames: Vec<(Option<usize>, u64)> = vec![(None, 0)];
+
+    for (index, log) in logs.iter().enumerate() {
+        let next = logs.get(index + 1);
+        match next {
+            Some(next) if next.depth > log.depth => frames.push((Some(index), 0)),
+            Some(next) if next.depth == log.depth => {
+                let charge = log.gas.saturating_sub(next.gas);
+                attribute(&mut breakdown, log, Some(next), charge);
+                consume(&mut frames, charge);
+            }
+            _ => {
+                // Last opcode of a frame, charged its cost or all the remaining gas
+                // when it halted exceptionally
+                let burned = log.error.is_some() && log.op != REVERT_OPCODE;
+                if burned {
+                    breakdown.computation += log.gas;
+                    consume(&mut frames, log.gas);
+                } else {
+                    attribute(&mut breakdown, log, None, log.gas_cost);
+                    consume(&mut frames, log.gas_cost);
+                }
+
+                // Charge the opcodes that entered the returning frames
+                while frames.len() > 1 {
+                    let (Some(entry), inner) = frames[frames.len() - 1] else { break };
+                    let entry_log = &logs[entry];
+                    if next.is_some_and(|next| next.depth > entry_log.depth) {
+                        break;
+                    }
+
+                    frames.pop();
+                    let charge = next.map_or(0, |next| {
+                        entry_log.gas.saturating_sub(next.gas).saturating_sub(inner)
+                    });
+                    attribute(&mut breakdown, entry_log, next, charge);
+                    consume(&mut frames, inner + charge);
+                }
+            }
+        }
+    }
+
+    let execution = frames.iter().map(|(_, consumed)| consumed).sum::<u64>();
+    let charged = intrinsic.base + intrinsic.access_list + execution;
+    breakdown.refund = charged
+        .saturating_sub(gas_used)
+        .min(charged / MAX_REFUND_QUOTIENT);
+    breakdown.unexplained = gas_used.saturating_sub(charged - breakdown.refund);
+    breakdown.storage.cleanup_refund = breakdown.storage.cleanup_refund.min(breakdown.refund);
+
+    breakdown
+}
+
+/// Adds gas consumed to the innermost frame.
+const fn consume(frames: &mut [(Option<usize>, u64)], gas: u64) {
+    if let Some((_, consumed)) = frames.last_mut() {
+        *consumed += gas;
+    }
+}
+
+/// Attributes the charge of an opcode to its category.
+///
+/// `next` is the next opcode executed in the same frame, if any.
+fn attribute(breakdown: &mut GasBreakdown, log: &StructLog, next: Option<&StructLog>, charge: u64) {
+    let op = log.op.as_str();
+    let memory = match (log.memory_size, next.and_then(|next| next.memory_size)) {
+        (Some(before), Some(after)) => memory_gas(after).saturating_sub(memory_gas(before)),
+        _ if matches!(op, "MLOAD" | "MSTORE" | "MSTORE8") => {
+            charge.saturating_sub(MEMORY_OPCODE_GAS)
+        }
+        _ => 0,
+    }
+    .min(charge);
+    breakdown.memory += memory;
+
+    let charge = charge - memory;
+    match op {
+        "SLOAD" => breakdown.storage.reads += charge,
+        "SSTORE" => {
+            breakdown.storage.writes += charge;
+            if charge >= SSTORE_SET_GAS {
+                breakdown.storage.initialization += charge;
+            } else if charge >= SSTORE_RESET_GAS {
+                breakdown.storage.modifications += charge;
+            }
+
+            let refund_before = log.refund_counter.unwrap_or_default();
+            let refund_after = next
+                .and_then(|next| next.refund_counter)
+                .unwrap_or(refund_before);
+            breakdown.storage.cleanup_refund += refund_after.saturating_sub(refund_before);
+        }
+        "LOG0" | "LOG1" | "LOG2" | "LOG3" | "LOG4" => breakdown.logs += charge,
+        "CALL" | "CALLCODE" | "DELEGATECALL" | "STATICCALL" => breakdown.calls += charge,
+        "CREATE" | "CREATE2" => breakdown.creates += charge,
+        _ => breakdown.computation += charge,
+    }
+}
+
+/// Total cost of a memory of `size` bytes.
+const fn memory_gas(size: u64) -> u64 {
+    let words = size.div_ceil(32);
+    3 * words + words * words / 512
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(op: &str, gas: u64, gas_cost: u64, depth: u64) -> StructLog {
+        StructLog { op: op.to_string(), gas, gas_cost, depth, ..Default::default() }
+    }
+
+    #[test]
+    fn test_breakdown_by_category() {
+        let mut mstore = log("MSTORE", 78_994, 6, 1);
+        mstore.memory_size = Some(0);
+        let mut sload = log("SLOAD", 78_988, 2_100, 1);
+        sload.memory_size = Some(32);
+        let logs = vec![
+            log("PUSH1", 79_000, 3, 1),
+            log("PUSH1", 78_997, 3, 1),
+            mstore,
+            sload,
+            log("SSTORE", 76_888, 22_100, 1),
+            log("LOG1", 54_788, 1_006, 1),
+            log("STOP", 53_782, 0, 1),
+        ];
+        let intrinsic = IntrinsicGas::new(&[0xa9, 0x05, 0x9c, 0xbb], false, None);
+        let breakdown = compute_gas_breakdown(&logs, intrinsic, 21_064 + 25_218);
+
+        assert_eq!(breakdown.intrinsic, 21_064);
+        assert_eq!(breakdown.computation, 9);
+        assert_eq!(breakdown.memory, 3);
+        assert_eq!(breakdown.storage.reads, 2_100);
+        assert_eq!(breakdown.storage.writes, 22_100);
+        assert_eq!(breakdown.storage.initialization, 22_100);
+        assert_eq!(breakdown.logs, 1_006);
+        assert_eq!(breakdown.refund, 0);
+        assert_eq!(breakdown.total(), 21_064 + 25_218);
+    }
+
+    #[test]
+    fn test_nested_frames_are_not_counted_twice() {
+        let mut clear = log("SSTORE", 40_000, 5_000, 2);
+        clear.refund_counter = Some(0);
+        let mut stop = log("STOP", 35_000, 0, 2);
+        stop.refund_counter = Some(4_800);
+        let logs = vec![
+            log("PUSH1", 100_000, 3, 1),
+            // Forwards 40,000 gas to the callee and pays 2,600 to access it
+            log("CALL", 99_997, 42_600, 1),
+            clear,
+            stop,
+            log("STOP", 92_397, 0, 1),
+        ];
+        let intrinsic = IntrinsicGas::new(&[], false, None);
+        let gas_used = 21_000 + 7_603 - 1_520;
+        let breakdown = compute_gas_breakdown(&logs, intrinsic, gas_used);
+
+        assert_eq!(breakdown.calls, 2_600);
+        assert_eq!(breakdown.storage.writes, 5_000);
+        assert_eq!(breakdown.storage.modifications, 5_000);
+        assert_eq!(breakdown.computation, 3);
+        assert_eq!(breakdown.refund, 1_520);
+        assert_eq!(breakdown.storage.cleanup_refund, 1_520);
+        assert_eq!(breakdown.total(), gas_used);
+    }
+
+    #[test]
+    fn test_exceptional_halt_burns_remaining_gas() {
+        let mut invalid = log("INVALID", 50_000, 0, 1);
+        invalid.error = Some("invalid opcode: INVALID".to_string());
+        let logs = vec![log("PUSH1", 50_003, 3, 1), invalid];
+
+        // Contract creation with 64 bytes of init code and an access list entry
+        let access_list: AccessList = serde_json::from_str(
+            r#"[{"address":"0x0000000000000000000000000000000000000001","storageKeys":[]}]"#,
+        )
+        .unwrap();
+        let intrinsic = IntrinsicGas::new(&[0xff; 64], true, Some(&access_list));
+        assert_eq!(intrinsic, IntrinsicGas { base: 21_000 + 1_024 + 32_004, access_list: 2_400 });
+
+        // Gas used above the execution cost, e.g. a calldata floor
+        let gas_used = 54_028 + 2_400 + 50_003 + 100;
+        let breakdown = compute_gas_breakdown(&logs, intrinsic, gas_used);
+
+        assert_eq!(breakdown.computation, 50_003);
+        assert_eq!(breakdown.intrinsic, 54_028);
+        assert_eq!(breakdown.unexplained, 100);
+        assert_eq!(breakdown.refund, 0);
+        assert_eq!(breakdown.total(), gas_used);
+    }
+
+    #[test]
+    fn test_refund_is_capped() {
+        let logs = vec![log("PUSH1", 79_000, 3, 1), log("STOP", 78_997, 0, 1)];
+        let intrinsic = IntrinsicGas::new(&[], false, None);
+
+        // Gas used below the consumed gas net of the maximal refund is not a refund
+        let breakdown = compute_gas_breakdown(&logs, intrinsic, 15_000);
+        assert_eq!(breakdown.refund, 21_003 / 5);
+        assert_eq!(breakdown.unexplained, 0);
+    }
+}
@@ -3,7 +3,15 @@
 //! This module integrates with `HyperEVM` through Alloy RPC types and provides
 //! high-level simulation services for the API layer.
 
+pub mod assets;
+pub mod gas;
+pub mod gas_breakdown;
+pub mod preflight;
 pub mod service;
+pub mod state_diff;
+pub mod storage_access;
+pub mod token_slots;
+pub mod transfers;
 
 use super::RpcProvider;
 
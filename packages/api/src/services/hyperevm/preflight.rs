@@ -0,0 +1,365 @@
+//! Pre-flight validation of simulated calls.
+//!
+//! When `validation` is enabled, the calls are checked against the state of the
+//! accounts they touch and the environment of the block before being simulated.
+//! The checks mirror the ones a validating node performs (balance, nonce, gas
+//! limit and fees) and add a few heuristics for calls that execute but likely
+//! not as intended. The state is fetched by the service and the checks below
+//! are pure, so the report does not depend on the outcome of the simulation.
+
+use crate::handlers::simulation::response::{
+    ValidationCode, ValidationFinding, ValidationReport, ValidationSeverity,
+};
+use alloy_primitives::{Address, TxKind, U256};
+use alloy_rpc_types_eth::{
+    state::StateOverride, BlockOverrides, TransactionRequest as AlloyTransactionRequest,
+};
+use std::collections::{BTreeSet, HashMap};
+
+/// State of an account before the calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountState {
+    /// Native balance.
+    pub balance: U256,
+    /// Next nonce.
+    pub nonce: u64,
+    /// Whether the account has code.
+    pub has_code: bool,
+}
+
+/// Block environment the calls are validated against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockEnv {
+    /// Gas limit of the block.
+    pub gas_limit: u64,
+    /// Base fee per gas of the block.
+    pub base_fee: u128,
+}
+
+impl BlockEnv {
+    /// Applies the block overrides of the simulation.
+    pub fn apply_overrides(&mut self, overrides: &BlockOverrides) {
+        if let Some(gas_limit) = overrides.gas_limit {
+            self.gas_limit = gas_limit;
+        }
+        if let Some(base_fee) = overrides.base_fee {
+            self.base_fee = base_fee.saturating_to();
+        }
+    }
+}
+
+/// Accounts whose state is needed to validate the calls: the senders and the
+/// recipients.
+pub fn touched_accounts(calls: &[AlloyTransactionRequest]) -> Vec<Address> {
+    calls
+        .iter()
+        .flat_map(|call| [Some(call.from.unwrap_or_default()), recipient(call)])
+        .flatten()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Applies the state overrides of the simulation on top of the fetched accounts.
+pub fn apply_state_overrides(
+    accounts: &mut HashMap<Address, AccountState>,
+    overrides: &StateOverride,
+) {
+    for (address, account_override) in overrides {
+        let account = accounts.entry(*address).or_default();
+        if let Some(balance) = account_override.balance {
+            account.balance = balance;
+        }
+        if let Some(nonce) = account_override.nonce {
+            account.nonce = nonce;
+        }
+        if let Some(code) = &account_override.code {
+            account.has_code = !code.is_empty();
+        }
+    }
+}
+
+/// Validates the calls in order, on top of the given account states.
+///
+/// Balances and nonces are tracked across the calls: every call increments the
+/// nonce of its sender and moves its value, and the maximum gas cost is charged
+/// to the sender as the gas actually used is unknown before the execution.
+/// Accounts missing from `accounts` are assumed empty.
+pub fn validate(
+    calls: &[AlloyTransactionRequest],
+    accounts: &HashMap<Address, AccountState>,
+    block: BlockEnv,
+) -> ValidationReport {
+    let mut accounts = accounts.clone();
+    let mut findings = Vec::new();
+
+    for (index, call) in calls.iter().enumerate() {
+        let mut finding = |code, severity, message| {
+            findings.push(ValidationFinding { code, severity, call_index: index as u32, message })
+        };
+
+        let from = call.from.unwrap_or_default();
+        let gas = call.gas.unwrap_or_default();
+        let value = call.value.unwrap_or_default();
+        let input_empty = call.input.input().is_none_or(|input| input.is_empty());
+
+        // Gas limit
+        if gas > block.gas_limit {
+            finding(
+                ValidationCode::GasLimitExceeded,
+                ValidationSeverity::Error,
+                format!(
+                    "Gas limit 0x{:x} exceeds the block gas limit 0x{:x}",
+                    gas, block.gas_limit
+                ),
+            );
+        }
+
+        // Fees
+        let max_fee = call.max_fee_per_gas.or(call.gas_price);
+        if let Some(max_fee) = max_fee.filter(|max_fee| *max_fee < block.base_fee) {
+            finding(
+                ValidationCode::FeeBelowBaseFee,
+                ValidationSeverity::Error,
+                format!(
+                    "Max fee per gas 0x{:x} is below the block base fee 0x{:x}",
+                    max_fee, block.base_fee
+                ),
+            );
+        }
+
+        // Nonce
+        let sender = accounts.entry(from).or_default();
+        let nonce = call.nonce.unwrap_or(sender.nonce);
+        if nonce != sender.nonce {
+            finding(
+                ValidationCode::NonceMismatch,
+                ValidationSeverity::Error,
+                format!("Nonce {} does not match the sender nonce {}", nonce, sender.nonce),
+            );
+        }
+        sender.nonce = nonce.saturating_add(1);
+
+        // Balance
+        let gas_cost = U256::from(gas) * U256::from(max_fee.unwrap_or(block.base_fee));
+        let cost = gas_cost.saturating_add(value);
+        if sender.balance < cost {
+            finding(
+                ValidationCode::InsufficientBalance,
+                ValidationSeverity::Error,
+                format!(
+                    "Sender balance 0x{:x} does not cover the value and gas cost 0x{:x}",
+                    sender.balance, cost
+                ),
+            );
+        }
+        sender.balance = sender.balance.saturating_sub(cost);
+
+        match recipient(call) {
+            Some(to) => {
+                let recipient = accounts.entry(to).or_default();
+                recipient.balance = recipient.balance.saturating_add(value);
+
+                if !input_empty && !recipient.has_code {
+                    finding(
+                        ValidationCode::NoCode,
+                        ValidationSeverity::Warning,
+                        format!("Calldata is sent to {} which has no code", to),
+                    );
+                }
+            }
+            None if input_empty => finding(
+                ValidationCode::MissingInitCode,
+                ValidationSeverity::Error,
+                "Contract creation without init code".to_string(),
+            ),
+            None => {}
+        }
+    }
+
+    ValidationReport {
+        valid: findings
+            .iter()
+            .all(|finding| finding.severity != ValidationSeverity::Error),
+        findings,
+    }
+}
+
+/// Returns the recipient of a call, `None` for contract creations.
+const fn recipient(call: &AlloyTransactionRequest) -> Option<Address> {
+    match call.to {
+        Some(TxKind::Call(to)) => Some(to),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Bytes;
+    use alloy_rpc_types_eth::{state::AccountOverride, TransactionInput};
+
+    const BLOCK: BlockEnv = BlockEnv { gas_limit: 30_000_000, base_fee: 100 };
+
+    fn call(from: Address, to: Address, value: u64) -> AlloyTransactionRequest {
+        AlloyTransactionRequest {
+            from: Some(from),
+            to: Some(TxKind::Call(to)),
+            value: Some(U256::from(value)),
+            gas: Some(21_000),
+            ..Default::default()
+        }
+    }
+
+    fn funded(balance: u64) -> AccountState {
+        AccountState { balance: U256::from(balance), ..Default::default() }
+    }
+
+    fn codes(report: &ValidationReport) -> Vec<(u32, ValidationCode)> {
+        report
+            .findings
+            .iter()
+            .map(|finding| (finding.call_index, finding.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_calls() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        let accounts = HashMap::from([(sender, funded(10_000_000))]);
+
+        let report = validate(&[call(sender, recipient, 1_000)], &accounts, BLOCK);
+
+        assert!(report.valid);
+        assert!(report.findings.is_empty());
+    }
+
+    #[test]
+    fn test_balance_tracked_across_calls() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        // Covers the first call (21000 * 100 + 1000) but not the second
+        let accounts = HashMap::from([(sender, funded(2_101_000 + 2_000_000))]);
+
+        let calls = [call(sender, recipient, 1_000), call(sender, recipient, 1_000)];
+        let report = validate(&calls, &accounts, BLOCK);
+
+        assert!(!report.valid);
+        assert_eq!(codes(&report), vec![(1, ValidationCode::InsufficientBalance)]);
+    }
+
+    #[test]
+    fn test_received_value_is_spendable() {
+        let alice = Address::repeat_byte(0x11);
+        let bob = Address::repeat_byte(0x22);
+        let accounts = HashMap::from([(alice, funded(10_000_000))]);
+
+        let calls = [call(alice, bob, 5_000_000), call(bob, alice, 1_000_000)];
+        let report = validate(&calls, &accounts, BLOCK);
+
+        assert!(report.valid);
+    }
+
+    #[test]
+    fn test_nonce_mismatch() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        let accounts = HashMap::from([(sender, AccountState { nonce: 5, ..funded(10_000_000) })]);
+
+        let mut first = call(sender, recipient, 0);
+        first.nonce = Some(5);
+        let mut second = call(sender, recipient, 0);
+        second.nonce = Some(5);
+        let report = validate(&[first, second], &accounts, BLOCK);
+
+        assert_eq!(codes(&report), vec![(1, ValidationCode::NonceMismatch)]);
+    }
+
+    #[test]
+    fn test_block_limits() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        let accounts = HashMap::from([(sender, funded(u64::MAX))]);
+
+        let mut too_much_gas = call(sender, recipient, 0);
+        too_much_gas.gas = Some(BLOCK.gas_limit + 1);
+        let mut underpriced = call(sender, recipient, 0);
+        underpriced.max_fee_per_gas = Some(99);
+        let report = validate(&[too_much_gas, underpriced], &accounts, BLOCK);
+
+        assert_eq!(
+            codes(&report),
+            vec![(0, ValidationCode::GasLimitExceeded), (1, ValidationCode::FeeBelowBaseFee)]
+        );
+    }
+
+    #[test]
+    fn test_code_checks() {
+        let sender = Address::repeat_byte(0x11);
+        let eoa = Address::repeat_byte(0x22);
+        let contract = Address::repeat_byte(0x33);
+        let accounts = HashMap::from([
+            (sender, funded(10_000_000)),
+            (contract, AccountState { has_code: true, ..Default::default() }),
+        ]);
+        let input = TransactionInput::new(Bytes::from_static(&[0xa9, 0x05, 0x9c, 0xbb]));
+
+        let mut to_eoa = call(sender, eoa, 0);
+        to_eoa.input = input.clone();
+        let mut to_contract = call(sender, contract, 0);
+        to_contract.input = input;
+        let mut empty_create = call(sender, eoa, 0);
+        empty_create.to = None;
+        let report = validate(&[to_eoa, to_contract, empty_create], &accounts, BLOCK);
+
+        assert!(!report.valid);
+        assert_eq!(
+            codes(&report),
+            vec![(0, ValidationCode::NoCode), (2, ValidationCode::MissingInitCode)]
+        );
+        assert_eq!(report.findings[0].severity, ValidationSeverity::Warning);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let sender = Address::repeat_byte(0x11);
+        let mut accounts = HashMap::from([(sender, funded(0))]);
+        let overrides = StateOverride::from_iter([(
+            sender,
+            AccountOverride {
+                balance: Some(U256::from(1_000)),
+                nonce: Some(3),
+                code: Some(Bytes::from_static(&[0x00])),
+                ..Default::default()
+            },
+        )]);
+        apply_state_overrides(&mut accounts, &overrides);
+
+        assert_eq!(
+            accounts[&sender],
+            AccountState { balance: U256::from(1_000), nonce: 3, has_code: true }
+        );
+
+        let mut block = BLOCK;
+        block.apply_overrides(&BlockOverrides {
+            base_fee: Some(U256::from(7)),
+            ..Default::default()
+        });
+        assert_eq!(block.base_fee, 7);
+        assert_eq!(block.gas_limit, BLOCK.gas_limit);
+    }
+
+    #[test]
+    fn test_touched_accounts() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        let mut create = call(sender, recipient, 0);
+        create.to = Some(TxKind::Create);
+
+        assert_eq!(
+            touched_accounts(&[call(sender, recipient, 0), create]),
+            vec![sender, recipient]
+        );
+    }
+}
@@ -7,26 +7,57 @@
 use crate::{
     error::{RpcError, ServiceError},
     handlers::{
-        simulation::{dto::*, response::*},
+        simulation::{conversion, dto::*, response::*},
         trace::*,
     },
-    services::hyperevm::RpcProvider,
-    types::{TraceResponse, TransactionReceiptInfo},
+    services::{
+        decoder::AbiRegistry,
+        hyperevm::{
+            assets,
+            gas::{self, GasSearch},
+            gas_breakdown::{compute_gas_breakdown, IntrinsicGas},
+            preflight::{self, AccountState, BlockEnv},
+            state_diff::merge_state_diffs,
+            token_slots::{self, MappingSlot, TokenQuery},
+            transfers, RpcProvider,
+        },
+        usage, Cache,
+    },
+    types::{SlotWrite, StateOverride, TraceResponse, TransactionCall, TransactionReceiptInfo},
     utils::{
-        generate_access_list_id, generate_batch_id, generate_trace_id,
+        generate_access_list_id, generate_batch_id, generate_bundle_id, generate_trace_id,
         validation::parse_block_number,
     },
+    CacheResult,
 };
 
-use alloy_primitives::B256;
+use alloy_consensus::Transaction as _;
+use alloy_primitives::{hex, Address, Bytes, TxKind, B256, U256};
 use alloy_provider::{ext::DebugApi, Provider};
-use alloy_rpc_types::BlockId;
+use alloy_rpc_types::{
+    BlockId, BlockNumberOrTag, Bundle as AlloyBundle, StateContext as AlloyStateContext,
+    TransactionIndex as AlloyTransactionIndex,
+};
 use alloy_rpc_types_eth::{
-    simulate::{SimulatePayload, SimulatedBlock},
-    TransactionRequest as AlloyTransactionRequest,
+    simulate::{SimBlock, SimulatePayload, SimulatedBlock},
+    state::{AccountOverride, StateOverride as AlloyStateOverride},
+    TransactionInput, TransactionRequest as AlloyTransactionRequest,
+};
+use alloy_rpc_types_trace::geth::{
+    CallConfig, CallFrame as AlloyCallFrame, DefaultFrame, DiffMode, GethDebugTracingCallOptions,
+    GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace, PreStateConfig,
+};
+use alloy_transport::{TransportError, TransportErrorKind};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::IntoFuture,
+    num::NonZeroU64,
+    str::FromStr,
+    time::Instant,
 };
-use std::{str::FromStr, time::Instant};
-use tracing::{debug, error, trace};
+use tracing::{debug, error, field::Empty, instrument, trace, warn, Span};
 use uuid::Uuid;
 
 /// Trait for types that can provide block context information.
@@ -56,6 +87,20 @@ impl std::default::Default for BlockContext {
     }
 }
 
+impl From<&BlockContext> for BlockId {
+    fn from(context: &BlockContext) -> Self {
+        match context {
+            BlockContext::Number(block_num) => Self::number(*block_num),
+            BlockContext::Tag(block_tag) => match block_tag {
+                BlockTag::Latest => Self::latest(),
+                BlockTag::Earliest => Self::earliest(),
+                BlockTag::Finalized => Self::finalized(),
+                BlockTag::Safe => Self::safe(),
+            },
+        }
+    }
+}
+
 /// Implementation of `BlockContextProvider` for `SimulationParams`.
 impl BlockContextProvider for SimulationParams {
     fn block_number(&self) -> Option<u64> {
@@ -69,14 +114,68 @@ impl BlockContextProvider for SimulationParams {
     }
 }
 
+/// Implementation of `BlockContextProvider` for `MultiBlockSimulationRequest`.
+impl BlockContextProvider for MultiBlockSimulationRequest {
+    fn block_number(&self) -> Option<u64> {
+        self.block_number
+            .as_ref()
+            .and_then(|bn| parse_block_number(bn).ok())
+    }
+
+    fn block_tag(&self) -> Option<&BlockTag> {
+        self.block_tag.as_ref()
+    }
+}
+
+/// Implementation of `BlockContextProvider` for `GasEstimationRequest`.
+impl BlockContextProvider for GasEstimationRequest {
+    fn block_number(&self) -> Option<u64> {
+        self.block_number
+            .as_ref()
+            .and_then(|bn| parse_block_number(bn).ok())
+    }
+
+    fn block_tag(&self) -> Option<&BlockTag> {
+        self.block_tag.as_ref()
+    }
+}
+
+/// Implementation of `BlockContextProvider` for `BundleSimulationRequest`.
+impl BlockContextProvider for BundleSimulationRequest {
+    fn block_number(&self) -> Option<u64> {
+        self.block_number
+            .as_ref()
+            .and_then(|bn| parse_block_number(bn).ok())
+    }
+
+    fn block_tag(&self) -> Option<&BlockTag> {
+        self.block_tag.as_ref()
+    }
+}
+
 #[derive(Clone)]
 pub struct HyperEvmService {
     provider: RpcProvider,
+    cache: Option<Cache>,
+    simulation_ttl: Option<NonZeroU64>,
 }
 
 impl HyperEvmService {
     pub const fn new(provider: RpcProvider) -> Self {
-        Self { provider }
+        Self { provider, cache: None, simulation_ttl: None }
+    }
+
+    /// Caches the results that do not depend on the request (e.g. token storage slots)
+    /// and stores the simulations.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Expires the stored simulations after `ttl` seconds, instead of keeping them.
+    pub const fn with_simulation_ttl(mut self, ttl: NonZeroU64) -> Self {
+        self.simulation_ttl = Some(ttl);
+        self
     }
 
     /// Determines the appropriate block context for any type that implements
@@ -131,12 +230,126 @@ impl HyperEvmService {
         }
     }
 
-    /// Simulate a single transaction or batch of calls using `eth_simulateV1`
+    /// Returns whether a block is finalized, so that the results pinned to it are
+    /// immutable. Errors are logged and treated as not finalized.
+    #[instrument(target = "altitrace::service", skip(self))]
+    pub async fn is_finalized(&self, block_number: u64) -> bool {
+        match self
+            .provider
+            .inner
+            .get_block_by_number(BlockNumberOrTag::Finalized)
+            .await
+        {
+            Ok(Some(finalized)) => block_number <= finalized.header.number,
+            Ok(None) => false,
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    block_number,
+                    error = ?e,
+                    "Failed to fetch the finalized block"
+                );
+                false
+            }
+        }
+    }
+
+    /// Simulate a single transaction or batch of calls using `eth_simulateV1`, and
+    /// store the result with its request (see [`Self::stored_simulation`]).
+    #[instrument(target = "altitrace::service", skip_all, fields(simulation_id = Empty))]
     pub async fn simulate_transaction(
         &self,
         request: SimulationRequest,
     ) -> Result<SimulationResult, ServiceError> {
         let simulation_id = Uuid::new_v4().to_string();
+        let result = self
+            .execute_simulation(simulation_id, request.clone())
+            .await?;
+        self.store_simulation(request, &result, None).await;
+        Ok(result)
+    }
+
+    /// Re-executes a stored simulation at another parent block, the latest by
+    /// default. The rerun is stored as a new simulation referencing the original.
+    #[instrument(
+        target = "altitrace::service",
+        skip_all,
+        fields(original_id = %stored.result.simulation_id, simulation_id = Empty)
+    )]
+    pub async fn rerun_simulation(
+        &self,
+        stored: StoredSimulation,
+        rerun: RerunRequest,
+    ) -> Result<SimulationResult, ServiceError> {
+        let original_id = stored.result.simulation_id;
+        let request = stored.request.at_block(rerun.block_number, rerun.block_tag);
+
+        debug!(
+            target: "altitrace::simulation",
+            original_id = %original_id,
+            block_number = ?request.params.block_number,
+            block_tag = ?request.params.block_tag,
+            "Re-executing stored simulation"
+        );
+
+        let simulation_id = Uuid::new_v4().to_string();
+        let result = self
+            .execute_simulation(simulation_id, request.clone())
+            .await?;
+        self.store_simulation(request, &result, Some(original_id))
+            .await;
+        Ok(result)
+    }
+
+    /// Returns the stored simulation with the given ID, `None` if it does not
+    /// exist or has expired.
+    #[instrument(target = "altitrace::service", skip(self))]
+    pub async fn stored_simulation(
+        &self,
+        simulation_id: &str,
+    ) -> CacheResult<Option<StoredSimulation>> {
+        match &self.cache {
+            Some(cache) => cache.pull(&simulation_key(simulation_id)).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a simulation with its request. Storage failures are logged and do
+    /// not fail the simulation.
+    async fn store_simulation(
+        &self,
+        request: SimulationRequest,
+        result: &SimulationResult,
+        rerun_of: Option<String>,
+    ) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let key = simulation_key(&result.simulation_id);
+        let stored =
+            StoredSimulation { request, result: result.clone(), created_at: Utc::now(), rerun_of };
+
+        let ttl = self
+            .simulation_ttl
+            .map(|ttl| i64::try_from(ttl.get()).unwrap_or(i64::MAX));
+        if let Err(e) = cache.push(&key, stored, ttl).await {
+            warn!(
+                target: "altitrace::simulation",
+                simulation_id = %result.simulation_id,
+                error = ?e,
+                "Failed to store simulation"
+            );
+        }
+    }
+
+    /// Executes a simulation under the given ID, see [`Self::simulate_transaction`].
+    async fn execute_simulation(
+        &self,
+        simulation_id: String,
+        mut request: SimulationRequest,
+    ) -> Result<SimulationResult, ServiceError> {
+        Span::current().record("simulation_id", simulation_id.as_str());
         let start_time = Instant::now();
 
         let call_count = request.call_count();
@@ -151,6 +364,25 @@ impl HyperEvmService {
             "Starting transaction simulation"
         );
 
+        // Determine the block context for simulation
+        let block_context = self.determine_block_context(&request.params, &simulation_id)?;
+
+        if let Some(options) = request.options.as_mut() {
+            if let Some(token_overrides) = options.token_overrides.take() {
+                let state_overrides = options.state_overrides.get_or_insert_default();
+                self.apply_token_overrides(
+                    &token_overrides,
+                    state_overrides,
+                    BlockId::from(&block_context),
+                )
+                .await?;
+            }
+            if let Some(state_overrides) = options.state_overrides.as_mut() {
+                self.resolve_storage_overrides(state_overrides, BlockId::from(&block_context))
+                    .await?;
+            }
+        }
+
         // Convert API request to Alloy's SimulatePayload
         let simulate_payload: SimulatePayload<AlloyTransactionRequest> =
             request.clone().try_into().map_err(|e| {
@@ -170,79 +402,52 @@ impl HyperEvmService {
             "Converted request to Alloy format"
         );
 
-        // Determine the block context for simulation
-        let simulation_rpc_req = match self
-            .determine_block_context(&request.params, &simulation_id)?
-        {
-            BlockContext::Number(block_num) => self
-                .provider
-                .inner
-                .simulate(&simulate_payload)
-                .number(block_num),
-            BlockContext::Tag(block_tag) => match block_tag {
-                BlockTag::Latest => self.provider.inner.simulate(&simulate_payload).latest(),
-                BlockTag::Earliest => self.provider.inner.simulate(&simulate_payload).earliest(),
-                BlockTag::Finalized => self.provider.inner.simulate(&simulate_payload).finalized(),
-                BlockTag::Safe => self.provider.inner.simulate(&simulate_payload).safe(),
-            },
+        // Validate the calls before simulating them
+        let validation_report = if simulate_payload.validation {
+            self.validate_calls(
+                &simulate_payload.block_state_calls[0],
+                &block_context,
+                &simulation_id,
+            )
+            .await
+        } else {
+            None
         };
 
         // Execute simulation via eth_simulateV1
-        let simulated_blocks: Vec<SimulatedBlock> = match simulation_rpc_req.await {
-            Ok(blocks) => blocks,
-            Err(e) => {
-                // Convert RPC errors into failed simulation results
-                debug!(
-                    target: "altitrace::simulation",
-                    simulation_id = %simulation_id,
-                    error = ?e,
-                    "Simulation RPC call failed"
-                );
-
-                // Convert to RPC error and get sanitized message
-                let rpc_error = RpcError::from(e);
-                let sanitized_reason = match &rpc_error {
-                    RpcError::Timeout { .. } => "RPC request timeout, check if RPC is running and \
-                                                 accepting connections"
-                        .to_string(),
-                    RpcError::ConnectionFailed { .. } => "RPC connection failed, check if RPC is \
-                                                          running and accepting connections"
-                        .to_string(),
-                    RpcError::ExecutionReverted { reason, .. } => reason.clone(),
-                    _ => rpc_error.to_string(),
-                };
-
-                let call_error = CallError {
-                    reason: sanitized_reason.clone(),
-                    error_type: rpc_error.error_code().to_lowercase().replace('_', "-"),
-                    message: Some(sanitized_reason),
-                    contract_address: None,
-                };
-
-                // Create a failed CallResult for each call in the request
-                let call_results: Vec<CallResult> = (0..call_count)
-                    .map(|index| CallResult {
-                        call_index: index as u32,
-                        status: CallStatus::Reverted,
-                        return_data: "0x".to_string(),
+        let simulated_blocks: Vec<SimulatedBlock> =
+            match self.simulate_at(&simulate_payload, &block_context).await {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    // Convert RPC errors into failed simulation results
+                    debug!(
+                        target: "altitrace::simulation",
+                        simulation_id = %simulation_id,
+                        error = ?e,
+                        "Simulation RPC call failed"
+                    );
+
+                    // Create a failed CallResult for each call in the request
+                    let call_error = rpc_call_error(&RpcError::from(e));
+                    let call_results: Vec<CallResult> = (0..call_count)
+                        .map(|index| failed_call_result(index, call_error.clone()))
+                        .collect();
+
+                    // Return a failed simulation result instead of propagating the error
+                    return Ok(SimulationResult {
+                        simulation_id,
+                        status: SimulationStatus::Failed,
+                        block_number: "0x0".to_string(),
+                        calls: call_results,
                         gas_used: "0x0".to_string(),
-                        logs: vec![],
-                        error: Some(call_error.clone()),
-                    })
-                    .collect();
-
-                // Return a failed simulation result instead of propagating the error
-                return Ok(SimulationResult {
-                    simulation_id,
-                    status: SimulationStatus::Failed,
-                    block_number: "0x0".to_string(),
-                    calls: call_results,
-                    gas_used: "0x0".to_string(),
-                    block_gas_used: "0x0".to_string(),
-                    asset_changes: None,
-                });
-            }
-        };
+                        block_gas_used: "0x0".to_string(),
+                        asset_changes: None,
+                        native_balance_changes: None,
+                        validation_report,
+                        state_diff: None,
+                    });
+                }
+            };
 
         if simulated_blocks.is_empty() {
             error!(
@@ -266,13 +471,67 @@ impl HyperEvmService {
             "Simulation completed successfully"
         );
 
+        // Decode calls to contracts with a user-supplied ABI
+        let registry = AbiRegistry::new(
+            request
+                .options
+                .as_ref()
+                .and_then(|options| options.abis.as_ref()),
+        );
+
         // Convert results back to our API format
-        let call_results: Vec<CallResult> = simulated_block
-            .calls
-            .iter()
-            .enumerate()
-            .map(|(index, sim_call_result)| CallResult::from((index, sim_call_result.clone())))
-            .collect();
+        let (mut call_results, native_balance_changes) = process_block_results(
+            simulated_block,
+            &simulate_payload.block_state_calls[0],
+            &registry,
+            request.params.trace_transfers,
+            simulate_payload.validation,
+        );
+
+        if request
+            .options
+            .as_ref()
+            .is_some_and(|options| options.gas_breakdown)
+        {
+            self.compute_gas_breakdowns(
+                &mut call_results,
+                &simulate_payload.block_state_calls,
+                &block_context,
+                &simulation_id,
+            )
+            .await;
+        }
+
+        if let Some(trace_config) = request
+            .options
+            .as_ref()
+            .and_then(|options| options.tracers.as_ref())
+        {
+            self.attach_traces(
+                &mut call_results,
+                &simulate_payload.block_state_calls[0],
+                &block_context,
+                trace_config,
+                &registry,
+                &simulation_id,
+            )
+            .await;
+        }
+
+        let state_diff = if request
+            .options
+            .as_ref()
+            .is_some_and(|options| options.state_diff)
+        {
+            self.compute_state_diff(
+                &simulate_payload.block_state_calls[0],
+                &block_context,
+                &simulation_id,
+            )
+            .await
+        } else {
+            None
+        };
 
         // Determine overall simulation status
         let status = if call_results
@@ -291,6 +550,22 @@ impl HyperEvmService {
                 u64::from_str_radix(result.gas_used.trim_start_matches("0x"), 16).unwrap_or(0)
             })
             .sum();
+        usage::record_simulation(call_results.len(), total_gas_used);
+
+        // Track token balance changes for the requested account
+        let asset_changes = match (&request.params.account, request.params.trace_asset_changes) {
+            (Some(account), true) => {
+                self.compute_asset_changes(
+                    account,
+                    &simulated_blocks,
+                    &simulate_payload,
+                    &block_context,
+                    &simulation_id,
+                )
+                .await
+            }
+            _ => None,
+        };
 
         let simulation_result = SimulationResult {
             simulation_id,
@@ -299,118 +574,1716 @@ impl HyperEvmService {
             gas_used: format!("0x{:x}", total_gas_used),
             calls: call_results,
             block_gas_used: format!("0x{:x}", simulated_block.inner.header.gas_used),
-            asset_changes: None,
+            asset_changes,
+            native_balance_changes,
+            validation_report,
+            state_diff,
         };
 
         Ok(simulation_result)
     }
 
-    /// Simulate multiple independent transactions
-    pub async fn simulate_batch(
+    /// Simulate calls across several consecutive blocks using `eth_simulateV1`.
+    ///
+    /// Every block of the request maps to one simulated block, executed on top of
+    /// the previous one with its own calls and overrides.
+    #[instrument(target = "altitrace::service", skip_all, fields(simulation_id = Empty))]
+    pub async fn simulate_multi_block(
         &self,
-        requests: Vec<SimulationRequest>,
-    ) -> Result<Vec<SimulationResult>, ServiceError> {
-        let batch_id = generate_batch_id();
+        mut request: MultiBlockSimulationRequest,
+    ) -> Result<MultiBlockSimulationResult, ServiceError> {
+        let simulation_id = Uuid::new_v4().to_string();
+        Span::current().record("simulation_id", simulation_id.as_str());
 
         debug!(
             target: "altitrace::simulation",
-            batch_id = %batch_id,
-            batch_size = requests.len(),
-            "Starting batch simulation"
+            simulation_id = %simulation_id,
+            blocks = request.blocks.len(),
+            validation = request.validation,
+            trace_transfers = request.trace_transfers,
+            trace_asset_changes = request.trace_asset_changes,
+            "Starting multi-block simulation"
         );
 
-        if requests.is_empty() {
-            return Err(ServiceError::simulation_failed(
-                "Batch simulation requires at least one request",
-            ));
-        }
-
-        // Process simulations concurrently, collecting both successes and failures
-        let mut futures = Vec::new();
+        let block_context = self.determine_block_context(&request, &simulation_id)?;
 
-        for req in requests {
-            let simulation_id = Uuid::new_v4().to_string();
-            let service = self.clone();
+        for block in &mut request.blocks {
+            if let Some(state_overrides) = &mut block.state_overrides {
+                self.resolve_storage_overrides(state_overrides, BlockId::from(&block_context))
+                    .await?;
+            }
+        }
 
-            let future = async move {
-                match service.simulate_transaction(req).await {
-                    Ok(mut result) => {
-                        // Update the simulation_id to be unique for this batch item
-                        result.simulation_id = simulation_id;
-                        result
-                    }
-                    Err(e) => {
-                        // This should rarely happen since simulate_transaction now handles most
-                        // errors but we still need to handle conversion
-                        // errors or other unexpected issues
-                        error!(
-                            target: "altitrace::simulation",
-                            error = ?e,
-                            "Unexpected error in batch simulation"
-                        );
+        let simulate_payload: SimulatePayload<AlloyTransactionRequest> =
+            request.clone().try_into().map_err(|e| {
+                error!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to convert request to Alloy format"
+                );
+                ServiceError::simulation_failed(format!("Request conversion failed: {}", e))
+            })?;
 
-                        // Sanitize the error message
-                        let sanitized_reason = "Simulation service error occurred".to_string();
+        let simulated_blocks = self
+            .simulate_at(&simulate_payload, &block_context)
+            .await
+            .map_err(RpcError::from)?;
+        if simulated_blocks.len() != simulate_payload.block_state_calls.len() {
+            return Err(ServiceError::simulation_failed(format!(
+                "Expected {} simulated blocks, got {}",
+                simulate_payload.block_state_calls.len(),
+                simulated_blocks.len()
+            )));
+        }
 
-                        SimulationResult {
-                            simulation_id,
-                            status: SimulationStatus::Failed,
-                            block_number: "0x0".to_string(),
-                            calls: vec![CallResult {
-                                call_index: 0,
-                                status: CallStatus::Reverted,
-                                return_data: "0x".to_string(),
-                                gas_used: "0x0".to_string(),
-                                logs: vec![],
-                                error: Some(CallError {
-                                    reason: sanitized_reason.clone(),
-                                    error_type: "simulation-error".to_string(),
-                                    message: Some(sanitized_reason),
-                                    contract_address: None,
-                                }),
-                            }],
-                            gas_used: "0x0".to_string(),
-                            block_gas_used: "0x0".to_string(),
-                            asset_changes: None,
-                        }
-                    }
+        let registry = AbiRegistry::new(request.abis.as_ref());
+        let mut blocks: Vec<SimulatedBlockResult> = simulated_blocks
+            .iter()
+            .zip(&simulate_payload.block_state_calls)
+            .map(|(simulated_block, sim_block)| {
+                let (calls, native_balance_changes) = process_block_results(
+                    simulated_block,
+                    sim_block,
+                    &registry,
+                    request.trace_transfers,
+                    simulate_payload.validation,
+                );
+                let header = &simulated_block.inner.header;
+
+                SimulatedBlockResult {
+                    block_number: format!("0x{:x}", header.number),
+                    timestamp: format!("0x{:x}", header.timestamp),
+                    base_fee_per_gas: header
+                        .base_fee_per_gas
+                        .map(|base_fee| format!("0x{:x}", base_fee)),
+                    gas_used: format!("0x{:x}", header.gas_used),
+                    calls,
+                    native_balance_changes,
                 }
-            };
+            })
+            .collect();
 
-            futures.push(future);
+        if request.gas_breakdown {
+            self.compute_gas_breakdowns(
+                blocks.iter_mut().flat_map(|block| &mut block.calls),
+                &simulate_payload.block_state_calls,
+                &block_context,
+                &simulation_id,
+            )
+            .await;
         }
 
-        let results: Vec<SimulationResult> = futures::future::join_all(futures).await;
+        let status = if blocks
+            .iter()
+            .flat_map(|block| &block.calls)
+            .all(|r| matches!(r.status, CallStatus::Success))
+        {
+            SimulationStatus::Success
+        } else {
+            SimulationStatus::Failed
+        };
 
-        let successful_count = results
+        let total_gas_used: u64 = simulated_blocks
             .iter()
-            .filter(|r| matches!(r.status, SimulationStatus::Success))
-            .count();
+            .flat_map(|block| &block.calls)
+            .map(|call| call.gas_used)
+            .sum();
+        usage::record_simulation(
+            simulated_blocks.iter().map(|block| block.calls.len()).sum(),
+            total_gas_used,
+        );
+
+        let asset_changes = match (&request.account, request.trace_asset_changes) {
+            (Some(account), true) => {
+                self.compute_asset_changes(
+                    account,
+                    &simulated_blocks,
+                    &simulate_payload,
+                    &block_context,
+                    &simulation_id,
+                )
+                .await
+            }
+            _ => None,
+        };
 
         debug!(
             target: "altitrace::simulation",
-            batch_id = %batch_id,
-            successful_simulations = successful_count,
-            failed_simulations = results.len() - successful_count,
-            "Batch simulation completed"
+            simulation_id = %simulation_id,
+            blocks = blocks.len(),
+            status = ?status,
+            gas_used = total_gas_used,
+            "Multi-block simulation completed"
         );
 
-        Ok(results)
+        Ok(MultiBlockSimulationResult {
+            simulation_id,
+            status,
+            blocks,
+            gas_used: format!("0x{:x}", total_gas_used),
+            asset_changes,
+        })
     }
 
-    pub async fn create_access_list(
+    /// Executes `eth_simulateV1` for the given payload on top of the given block context.
+    async fn simulate_at(
         &self,
-        request: &AccessListRequest,
-    ) -> Result<AccessListResponse, ServiceError> {
-        let start_time = Instant::now();
-        let request_id = generate_access_list_id();
-        let access_list_request = request.clone();
+        payload: &SimulatePayload<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+    ) -> Result<Vec<SimulatedBlock>, TransportError> {
+        let simulate = self.provider.inner.simulate(payload);
+        match block_context {
+            BlockContext::Number(block_num) => simulate.number(*block_num).await,
+            BlockContext::Tag(block_tag) => match block_tag {
+                BlockTag::Latest => simulate.latest().await,
+                BlockTag::Earliest => simulate.earliest().await,
+                BlockTag::Finalized => simulate.finalized().await,
+                BlockTag::Safe => simulate.safe().await,
+            },
+        }
+    }
 
-        debug!(
-            target: "altitrace::simulation",
-            request_id = %request_id,
-            "Processing access list request"
-        );
+    /// Computes ERC-20 balance changes of `account` caused by the simulated calls.
+    ///
+    /// Tokens are discovered from the `Transfer` logs of the first simulation, then
+    /// their balances are read around the same calls with a probe simulation (see
+    /// [`assets`]). Returns `None` if the balances could not be probed.
+    async fn compute_asset_changes(
+        &self,
+        account: &str,
+        simulated_blocks: &[SimulatedBlock],
+        payload: &SimulatePayload<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+        simulation_id: &str,
+    ) -> Option<Vec<AssetChange>> {
+        let account = Address::from_str(account).ok()?;
+        let tokens =
+            assets::touched_tokens(account, simulated_blocks.iter().flat_map(|block| &block.calls));
+        if tokens.is_empty() {
+            return Some(Vec::new());
+        }
+
+        trace!(
+            target: "altitrace::simulation",
+            simulation_id = %simulation_id,
+            tokens = tokens.len(),
+            "Probing token balances for asset changes"
+        );
+
+        let probe_payload = assets::build_probe_payload(account, &tokens, payload);
+        match self.simulate_at(&probe_payload, block_context).await {
+            Ok(blocks) => Some(assets::decode_probe_results(&tokens, &blocks)),
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to probe token balances, skipping asset changes"
+                );
+                None
+            }
+        }
+    }
+
+    /// Writes the ERC-20 balances and allowances of the token overrides to the
+    /// storage of the tokens, merged into the state overrides.
+    async fn apply_token_overrides(
+        &self,
+        token_overrides: &[TokenOverride],
+        state_overrides: &mut Vec<StateOverride>,
+        block_id: BlockId,
+    ) -> Result<(), ServiceError> {
+        let parse_address = |address: &str| {
+            Address::from_str(address).map_err(|_| {
+                ServiceError::invalid_state_override(format!("Invalid address '{}'", address))
+            })
+        };
+        let parse_amount = |amount: &str| {
+            U256::from_str(amount).map_err(|_| {
+                ServiceError::invalid_state_override(format!("Invalid token amount '{}'", amount))
+            })
+        };
+
+        for token_override in token_overrides {
+            let token = parse_address(&token_override.token)?;
+            let holder = parse_address(&token_override.holder)?;
+
+            let mut writes = Vec::new();
+            if let Some(balance) = &token_override.balance {
+                writes.push((TokenQuery::Balance { holder }, parse_amount(balance)?));
+            }
+            match (&token_override.spender, &token_override.allowance) {
+                (Some(spender), Some(allowance)) => writes.push((
+                    TokenQuery::Allowance { owner: holder, spender: parse_address(spender)? },
+                    parse_amount(allowance)?,
+                )),
+                (None, None) => {}
+                _ => {
+                    return Err(ServiceError::invalid_state_override(
+                        "Token allowance overrides require both 'spender' and 'allowance'",
+                    ))
+                }
+            }
+
+            // Merge with the existing overrides of the token, if any
+            let position = state_overrides.iter().position(|state_override| {
+                state_override
+                    .address
+                    .as_deref()
+                    .and_then(|address| Address::from_str(address).ok()) ==
+                    Some(token)
+            });
+            let position = position.unwrap_or_else(|| {
+                state_overrides
+                    .push(StateOverride { address: Some(token.to_string()), ..Default::default() });
+                state_overrides.len() - 1
+            });
+            let state_override = &mut state_overrides[position];
+
+            for (query, value) in writes {
+                let mapping = self.find_token_slot(token, &query, block_id).await?;
+                state_override.set_slot(query.slot(mapping), B256::from(value));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the storage mapping a token reads a balance or an allowance from.
+    ///
+    /// The mapping is looked up in the cache first, then probed with a single
+    /// `eth_call` (see [`token_slots`]) and cached, as it only depends on the token.
+    async fn find_token_slot(
+        &self,
+        token: Address,
+        query: &TokenQuery,
+        block_id: BlockId,
+    ) -> Result<MappingSlot, ServiceError> {
+        let cache_key = query.cache_key(token);
+        if let Some(cache) = &self.cache {
+            match cache.pull::<MappingSlot>(&cache_key).await {
+                Ok(Some(mapping)) => return Ok(mapping),
+                Ok(None) => {}
+                Err(e) => warn!(
+                    target: "altitrace::simulation",
+                    %token,
+                    error = ?e,
+                    "Failed to read token slot from cache"
+                ),
+            }
+        }
+
+        let call = AlloyTransactionRequest {
+            to: Some(TxKind::Call(token)),
+            input: query.calldata().into(),
+            ..Default::default()
+        };
+        let overrides =
+            AlloyStateOverride::from_iter([(token, token_slots::probe_override(query))]);
+        let output = self
+            .provider
+            .inner
+            .call(call)
+            .overrides(overrides)
+            .block(block_id)
+            .await
+            .map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?;
+
+        let mapping = token_slots::decode_probe(&output).ok_or_else(|| {
+            ServiceError::invalid_state_override(format!(
+                "Could not find the storage slot of {} for token {}",
+                match query {
+                    TokenQuery::Balance { .. } => "balances",
+                    TokenQuery::Allowance { .. } => "allowances",
+                },
+                token
+            ))
+        })?;
+
+        debug!(
+            target: "altitrace::simulation",
+            %token,
+            ?mapping,
+            "Token storage slot found"
+        );
+
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.push(&cache_key, mapping, None).await {
+                warn!(
+                    target: "altitrace::simulation",
+                    %token,
+                    error = ?e,
+                    "Failed to cache token slot"
+                );
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    /// Resolves the storage variables of a list of state overrides (see
+    /// [`Self::resolve_storage_variables`]).
+    async fn resolve_storage_overrides(
+        &self,
+        overrides: &mut [StateOverride],
+        state: impl Into<SlotState>,
+    ) -> Result<(), ServiceError> {
+        let state = state.into();
+        for state_override in overrides {
+            let address = state_override.address.clone().unwrap_or_default();
+            self.resolve_storage_variables(&address, state_override, state)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the storage variables of a state override into storage slots.
+    ///
+    /// The variables are resolved with the storage layout of the contract (see
+    /// [`storage_layout`](crate::types::storage_layout)) and merged into the
+    /// storage overrides. Slots partially written by packed variables keep the
+    /// bytes of their neighbours, read from the overrides or from `state`.
+    async fn resolve_storage_variables(
+        &self,
+        address: &str,
+        state_override: &mut StateOverride,
+        state: impl Into<SlotState>,
+    ) -> Result<(), ServiceError> {
+        let writes = state_override.storage_variable_writes().map_err(|e| {
+            ServiceError::invalid_state_override(format!(
+                "Invalid storage variables for {}: {}",
+                address, e
+            ))
+        })?;
+        if writes.is_empty() {
+            return Ok(());
+        }
+        let address = Address::from_str(address).map_err(|_| {
+            ServiceError::invalid_state_override(format!("Invalid address '{}'", address))
+        })?;
+
+        let mut slots: BTreeMap<B256, Vec<SlotWrite>> = BTreeMap::new();
+        for write in writes {
+            slots.entry(write.slot).or_default().push(write);
+        }
+
+        // Only the slots partially written and not overridden are read
+        let missing: Vec<B256> = slots
+            .iter()
+            .filter(|(slot, writes)| {
+                !writes.iter().any(SlotWrite::is_full_slot) &&
+                    state_override.overridden_slot(**slot).is_none()
+            })
+            .map(|(slot, _)| *slot)
+            .collect();
+        let current: HashMap<B256, B256> =
+            self.read_slots(address, &missing, state.into())
+                .await
+                .map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?;
+
+        for (slot, writes) in slots {
+            let word = state_override
+                .overridden_slot(slot)
+                .or_else(|| current.get(&slot).copied())
+                .unwrap_or_default();
+            let value = writes.iter().fold(word, |word, write| write.apply(word));
+            state_override.set_slot(slot, value);
+        }
+        state_override.storage_variables = None;
+
+        Ok(())
+    }
+
+    /// Reads storage slots of `address` in `state`.
+    ///
+    /// The state right before a transaction is not exposed by `eth_getStorageAt`,
+    /// so the slots are read there by calls to the account with its code replaced
+    /// by [`SLOAD_PROBE_CODE`], traced with `debug_traceCallMany` at the position
+    /// of the transaction.
+    async fn read_slots(
+        &self,
+        address: Address,
+        slots: &[B256],
+        state: SlotState,
+    ) -> Result<HashMap<B256, B256>, TransportError> {
+        if slots.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let values = match state {
+            SlotState::Block(block_id) => {
+                futures::future::try_join_all(slots.iter().map(|slot| async move {
+                    let value = self
+                        .provider
+                        .inner
+                        .get_storage_at(address, (*slot).into())
+                        .block_id(block_id)
+                        .await?;
+                    Ok::<_, TransportError>(B256::from(value))
+                }))
+                .await?
+            }
+            SlotState::BeforeTransaction { block_number, transaction_index } => {
+                let bundle = AlloyBundle {
+                    transactions: slots
+                        .iter()
+                        .map(|slot| AlloyTransactionRequest {
+                            to: Some(TxKind::Call(address)),
+                            input: TransactionInput::new(Bytes::copy_from_slice(slot.as_slice())),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    block_override: None,
+                };
+                let state_context = AlloyStateContext {
+                    block_number: Some(BlockId::number(block_number)),
+                    transaction_index: Some(AlloyTransactionIndex::Index(transaction_index)),
+                };
+                let probe = AccountOverride {
+                    code: Some(Bytes::from_static(SLOAD_PROBE_CODE)),
+                    ..Default::default()
+                };
+                let options =
+                    GethDebugTracingCallOptions::new(GethDebugTracingOptions::call_tracer(
+                        CallConfig { only_top_call: Some(true), with_log: Some(false) },
+                    ))
+                    .with_state_overrides(AlloyStateOverride::from_iter([(address, probe)]));
+
+                let frames: Vec<AlloyCallFrame> = self
+                    .trace_bundles(vec![bundle], state_context, options)
+                    .await?;
+                if frames.len() != slots.len() {
+                    return Err(TransportErrorKind::custom_str(
+                        "Unexpected number of storage probe traces",
+                    ));
+                }
+                frames
+                    .iter()
+                    .map(|frame| {
+                        frame
+                            .output
+                            .as_deref()
+                            .and_then(|output| B256::try_from(output.as_ref()).ok())
+                            .ok_or_else(|| {
+                                TransportErrorKind::custom_str("Invalid storage probe output")
+                            })
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        Ok(slots.iter().copied().zip(values).collect())
+    }
+
+    /// Builds the pre-flight validation report of the calls.
+    ///
+    /// The block environment and the state of the touched accounts are fetched at
+    /// the block context, then the overrides of the simulation are applied on top
+    /// (see [`preflight`]). Returns `None` if the state could not be fetched.
+    async fn validate_calls(
+        &self,
+        sim_block: &SimBlock<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+        simulation_id: &str,
+    ) -> Option<ValidationReport> {
+        let block_id = BlockId::from(block_context);
+        let addresses = preflight::touched_accounts(&sim_block.calls);
+
+        let fetch_account = |address: Address| async move {
+            let (balance, nonce, code) = tokio::try_join!(
+                self.provider.inner.get_balance(address).block_id(block_id),
+                self.provider
+                    .inner
+                    .get_transaction_count(address)
+                    .block_id(block_id),
+                self.provider.inner.get_code_at(address).block_id(block_id),
+            )?;
+            Ok::<_, TransportError>((
+                address,
+                AccountState { balance, nonce, has_code: !code.is_empty() },
+            ))
+        };
+
+        let fetched = tokio::try_join!(
+            self.provider.inner.get_block(block_id),
+            futures::future::try_join_all(addresses.into_iter().map(fetch_account)),
+        );
+        let (block, accounts) = match fetched {
+            Ok((Some(block), accounts)) => (block, accounts),
+            Ok((None, _)) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    "Block not found, skipping validation report"
+                );
+                return None;
+            }
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to fetch account states, skipping validation report"
+                );
+                return None;
+            }
+        };
+
+        let mut accounts: HashMap<Address, AccountState> = accounts.into_iter().collect();
+        if let Some(state_overrides) = &sim_block.state_overrides {
+            preflight::apply_state_overrides(&mut accounts, state_overrides);
+        }
+
+        let mut block_env = BlockEnv {
+            gas_limit: block.header.gas_limit,
+            base_fee: block.header.base_fee_per_gas.unwrap_or_default().into(),
+        };
+        if let Some(block_overrides) = &sim_block.block_overrides {
+            block_env.apply_overrides(block_overrides);
+        }
+
+        let report = preflight::validate(&sim_block.calls, &accounts, block_env);
+
+        debug!(
+            target: "altitrace::simulation",
+            simulation_id = %simulation_id,
+            valid = report.valid,
+            findings = report.findings.len(),
+            "Validation report built"
+        );
+
+        Some(report)
+    }
+
+    /// Breaks the gas used by each call of the blocks down by category.
+    ///
+    /// The calls of all the blocks are traced with the struct logger on top of the
+    /// same block context (see [`gas_breakdown`](super::gas_breakdown)), each with its
+    /// own intrinsic cost. The breakdowns are left unset if the calls could not be
+    /// traced.
+    async fn compute_gas_breakdowns<'a>(
+        &self,
+        call_results: impl IntoIterator<Item = &'a mut CallResult>,
+        sim_blocks: &[SimBlock<AlloyTransactionRequest>],
+        block_context: &BlockContext,
+        simulation_id: &str,
+    ) {
+        // Only the opcodes and the gas are needed
+        let config = StructLoggerConfig {
+            disable_stack: true,
+            disable_storage: true,
+            disable_return_data: true,
+            ..Default::default()
+        };
+        let options = GethDebugTracingOptions {
+            config: GethDefaultTracingOptions::from(&config),
+            ..Default::default()
+        };
+
+        let frames = match self
+            .trace_sim_blocks::<DefaultFrame>(sim_blocks, block_context, options)
+            .await
+        {
+            Ok(frames) => frames,
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to trace calls, skipping gas breakdown"
+                );
+                return;
+            }
+        };
+
+        let tx_requests = sim_blocks.iter().flat_map(|sim_block| &sim_block.calls);
+        for ((call_result, frame), tx_request) in
+            call_results.into_iter().zip(frames).zip(tx_requests)
+        {
+            let logs: Vec<StructLog> = frame.struct_logs.into_iter().map(StructLog::from).collect();
+            let gas_used =
+                u64::from_str_radix(call_result.gas_used.trim_start_matches("0x"), 16).unwrap_or(0);
+            let breakdown = compute_gas_breakdown(&logs, IntrinsicGas::from(tx_request), gas_used);
+            call_result.gas_breakdown = Some(breakdown.to_hex_breakdown());
+        }
+    }
+
+    /// Computes the state changes caused by the calls.
+    ///
+    /// The calls are traced with the prestate tracer in diff mode on top of the same
+    /// block context, and their diffs merged (see [`state_diff`](super::state_diff)).
+    /// Returns `None` if the calls could not be traced.
+    async fn compute_state_diff(
+        &self,
+        sim_block: &SimBlock<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+        simulation_id: &str,
+    ) -> Option<StateDiff> {
+        let options = GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+            diff_mode: Some(true),
+            ..Default::default()
+        });
+
+        match self
+            .trace_sim_blocks::<DiffMode>(std::slice::from_ref(sim_block), block_context, options)
+            .await
+        {
+            Ok(diffs) => Some(merge_state_diffs(diffs.into_iter().map(PrestateDiffMode::from))),
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to trace calls, skipping state diff"
+                );
+                None
+            }
+        }
+    }
+
+    /// Traces the calls with the requested tracers and attaches the traces to their
+    /// results.
+    ///
+    /// All the calls are traced on top of the same block context, as they depend on
+    /// each other, but only the traces of the selected calls are kept. The traces
+    /// are left unset if the calls could not be traced.
+    async fn attach_traces(
+        &self,
+        call_results: &mut [CallResult],
+        sim_block: &SimBlock<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+        config: &SimulationTraceConfig,
+        registry: &AbiRegistry<'_>,
+        simulation_id: &str,
+    ) {
+        let is_traced = |call_result: &CallResult| {
+            config.trace_calls == TracedCalls::All ||
+                matches!(call_result.status, CallStatus::Reverted)
+        };
+        if !call_results.iter().any(is_traced) {
+            return;
+        }
+
+        let strategy = TracingStrategy::from_config(&config.tracer_config.with_storage_prestate());
+        let (bundle, state_context) = sim_block_bundle(sim_block, block_context);
+        let trace_result = match strategy
+            .execute_bundles(
+                vec![bundle],
+                state_context,
+                sim_block.state_overrides.clone(),
+                |bundles, state_context, options| {
+                    self.trace_bundles::<GethTrace>(bundles, state_context, options)
+                },
+            )
+            .await
+        {
+            Ok(trace_result) => trace_result,
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    simulation_id = %simulation_id,
+                    error = ?e,
+                    "Failed to trace calls, skipping traces"
+                );
+                return;
+            }
+        };
+
+        for ((call_result, result), tx_request) in call_results
+            .iter_mut()
+            .zip(trace_result.into_individual_results())
+            .zip(&sim_block.calls)
+        {
+            if !is_traced(call_result) {
+                continue;
+            }
+
+            let mut trace = TracerResponse::from(result);
+            trace.compute_gas_breakdown(IntrinsicGas::from(tx_request));
+            if let Some(TxKind::Call(target)) = tx_request.to {
+                trace.compute_storage_accesses(target, tx_request.access_list.as_ref());
+            }
+            trace.drop_storage_prestate(&config.tracer_config);
+            if config.tracer_config.should_clean_struct_logger() {
+                trace.clean_struct_logger();
+            }
+            if !registry.is_empty() {
+                trace.apply_abis(registry);
+            }
+            call_result.trace = Some(trace);
+        }
+    }
+
+    /// Traces the calls of consecutive simulated blocks with `debug_traceCallMany`, one
+    /// bundle per block, on top of the block context and with the overrides of the
+    /// blocks.
+    ///
+    /// Returns one trace per call of the blocks, deserialized according to the tracer
+    /// of `options`. The node applies the state overrides before all the bundles, so
+    /// only the first block can override the state.
+    async fn trace_sim_blocks<T>(
+        &self,
+        sim_blocks: &[SimBlock<AlloyTransactionRequest>],
+        block_context: &BlockContext,
+        options: GethDebugTracingOptions,
+    ) -> Result<Vec<T>, TransportError>
+    where
+        T: DeserializeOwned + std::fmt::Debug + Send + Sync + Unpin + 'static,
+    {
+        if sim_blocks
+            .iter()
+            .skip(1)
+            .any(|sim_block| sim_block.state_overrides.is_some())
+        {
+            return Err(TransportErrorKind::custom_str(
+                "State overrides of blocks after the first cannot be traced",
+            ));
+        }
+
+        let mut call_options = GethDebugTracingCallOptions::new(options);
+        if let Some(state_overrides) = sim_blocks
+            .first()
+            .and_then(|sim_block| sim_block.state_overrides.clone())
+        {
+            call_options = call_options.with_state_overrides(state_overrides);
+        }
+        let (bundles, state_contexts): (Vec<_>, Vec<_>) = sim_blocks
+            .iter()
+            .map(|sim_block| sim_block_bundle(sim_block, block_context))
+            .unzip();
+        let Some(state_context) = state_contexts.into_iter().next() else {
+            return Ok(Vec::new());
+        };
+
+        self.trace_bundles(bundles, state_context, call_options)
+            .await
+    }
+
+    /// Calls `debug_traceCallMany` and returns the traces of the calls of all the
+    /// bundles, deserialized according to the tracer of `options`.
+    async fn trace_bundles<T>(
+        &self,
+        bundles: Vec<AlloyBundle>,
+        state_context: AlloyStateContext,
+        options: GethDebugTracingCallOptions,
+    ) -> Result<Vec<T>, TransportError>
+    where
+        T: DeserializeOwned + std::fmt::Debug + Send + Sync + Unpin + 'static,
+    {
+        // The node returns the traces of each bundle, typed here as the tracer output
+        let traces: Vec<Vec<T>> = self
+            .provider
+            .inner
+            .client()
+            .request("debug_traceCallMany", (bundles, state_context, options))
+            .await?;
+
+        Ok(traces.into_iter().flatten().collect())
+    }
+
+    /// Simulate multiple independent transactions
+    #[instrument(target = "altitrace::service", skip_all, fields(batch_id = Empty))]
+    pub async fn simulate_batch(
+        &self,
+        requests: Vec<SimulationRequest>,
+    ) -> Result<Vec<SimulationResult>, ServiceError> {
+        let batch_id = generate_batch_id();
+        Span::current().record("batch_id", batch_id.as_str());
+
+        debug!(
+            target: "altitrace::simulation",
+            batch_id = %batch_id,
+            batch_size = requests.len(),
+            "Starting batch simulation"
+        );
+
+        if requests.is_empty() {
+            return Err(ServiceError::simulation_failed(
+                "Batch simulation requires at least one request",
+            ));
+        }
+
+        // Process simulations concurrently, collecting both successes and failures
+        let mut futures = Vec::new();
+
+        for req in requests {
+            let simulation_id = Uuid::new_v4().to_string();
+            let service = self.clone();
+
+            let future = async move {
+                match service
+                    .execute_simulation(simulation_id.clone(), req.clone())
+                    .await
+                {
+                    Ok(result) => {
+                        service.store_simulation(req, &result, None).await;
+                        result
+                    }
+                    Err(e) => {
+                        // This should rarely happen since simulate_transaction now handles most
+                        // errors but we still need to handle conversion
+                        // errors or other unexpected issues
+                        error!(
+                            target: "altitrace::simulation",
+                            error = ?e,
+                            "Unexpected error in batch simulation"
+                        );
+
+                        // Sanitize the error message
+                        let sanitized_reason = "Simulation service error occurred".to_string();
+
+                        SimulationResult {
+                            simulation_id,
+                            status: SimulationStatus::Failed,
+                            block_number: "0x0".to_string(),
+                            calls: vec![CallResult {
+                                call_index: 0,
+                                status: CallStatus::Reverted,
+                                return_data: "0x".to_string(),
+                                gas_used: "0x0".to_string(),
+                                gas_breakdown: None,
+                                logs: vec![],
+                                decoded: None,
+                                native_transfers: vec![],
+                                error: Some(CallError {
+                                    reason: sanitized_reason.clone(),
+                                    error_type: "simulation-error".to_string(),
+                                    message: Some(sanitized_reason),
+                                    contract_address: None,
+                                    data: None,
+                                    decoded: None,
+                                }),
+                                trace: None,
+                            }],
+                            gas_used: "0x0".to_string(),
+                            block_gas_used: "0x0".to_string(),
+                            asset_changes: None,
+                            native_balance_changes: None,
+                            validation_report: None,
+                            state_diff: None,
+                        }
+                    }
+                }
+            };
+
+            futures.push(future);
+        }
+
+        let results: Vec<SimulationResult> = futures::future::join_all(futures).await;
+
+        let successful_count = results
+            .iter()
+            .filter(|r| matches!(r.status, SimulationStatus::Success))
+            .count();
+
+        debug!(
+            target: "altitrace::simulation",
+            batch_id = %batch_id,
+            successful_simulations = successful_count,
+            failed_simulations = results.len() - successful_count,
+            "Batch simulation completed"
+        );
+
+        Ok(results)
+    }
+
+    /// Simulate a bundle of interdependent transactions.
+    ///
+    /// The transactions are executed sequentially in a single `eth_simulateV1`
+    /// block, so that each one sees the state changes of the previous ones. The
+    /// bundle halts at the first reverted or rejected transaction that is not
+    /// allowed to fail, and is executed again up to it so that the following
+    /// transactions do not count. Rejected transactions that are allowed to fail
+    /// are left out, and RPC failures are reported in the transactions.
+    #[instrument(target = "altitrace::service", skip_all, fields(bundle_id = Empty))]
+    pub async fn simulate_bundle(
+        &self,
+        mut request: BundleSimulationRequest,
+    ) -> Result<BundleSimulationResult, ServiceError> {
+        let bundle_id = generate_bundle_id();
+        Span::current().record("bundle_id", bundle_id.as_str());
+
+        debug!(
+            target: "altitrace::simulation",
+            bundle_id = %bundle_id,
+            bundle_size = request.bundle.len(),
+            trace_transfers = request.trace_transfers,
+            trace_asset_changes = request.trace_asset_changes,
+            "Starting bundle simulation"
+        );
+
+        let block_context = self.determine_block_context(&request, &bundle_id)?;
+
+        if let Some(state_overrides) = &mut request.state_overrides {
+            self.resolve_storage_overrides(state_overrides, BlockId::from(&block_context))
+                .await?;
+        }
+
+        let simulate_payload: SimulatePayload<AlloyTransactionRequest> =
+            request.clone().try_into().map_err(|e| {
+                error!(
+                    target: "altitrace::simulation",
+                    bundle_id = %bundle_id,
+                    error = ?e,
+                    "Failed to convert bundle to Alloy format"
+                );
+                ServiceError::bundle_validation_failed(format!("Bundle conversion failed: {}", e))
+            })?;
+
+        // Transactions that could not be executed, with the reason
+        let mut rejected: BTreeMap<usize, CallError> = BTreeMap::new();
+        // Transactions from `end` on are not executed, the bundle halting before them
+        let mut end = request.bundle.len();
+        let execution = loop {
+            let included: Vec<usize> = (0..end)
+                .filter(|index| !rejected.contains_key(index))
+                .collect();
+            let payload = conversion::select_bundle_transactions(
+                &simulate_payload,
+                &request.bundle,
+                &included,
+            );
+            let error = match self.simulate_block_at(&payload, &block_context).await {
+                Ok(simulated_block) => {
+                    let mut statuses = simulated_block.calls.iter().map(|call| call.status);
+                    let halted_at = included.iter().copied().find(|&index| {
+                        let bundle_tx = &request.bundle[index];
+                        // All the statuses of the transaction are consumed
+                        let reverted = statuses
+                            .by_ref()
+                            .take(bundle_tx.calls.len())
+                            .filter(|success| !success)
+                            .count() >
+                            0;
+                        reverted && !bundle_tx.allow_failure
+                    });
+
+                    // The transactions after the halting one are executed again without
+                    // it, for them not to count towards the gas and state of the block
+                    match halted_at {
+                        Some(index) if included.last().is_some_and(|&last| last > index) => {
+                            end = index + 1;
+                            continue;
+                        }
+                        _ => break Some((payload, simulated_block)),
+                    }
+                }
+                Err(e) => e,
+            };
+
+            match self
+                .find_rejected_transaction(
+                    &simulate_payload,
+                    &request.bundle,
+                    &included,
+                    &block_context,
+                    error,
+                )
+                .await
+            {
+                Ok((index, e)) => {
+                    debug!(
+                        target: "altitrace::simulation",
+                        bundle_id = %bundle_id,
+                        transaction_index = index,
+                        error = ?e,
+                        "Bundle transaction rejected"
+                    );
+                    rejected.insert(index, rpc_call_error(&RpcError::from(e)));
+                    if !request.bundle[index].allow_failure {
+                        end = index + 1;
+                    }
+                }
+                Err(e) => {
+                    // Report the failure of the RPC call in the transactions instead of
+                    // failing the request
+                    warn!(
+                        target: "altitrace::simulation",
+                        bundle_id = %bundle_id,
+                        error = ?e,
+                        "Bundle simulation RPC call failed"
+                    );
+                    let call_error = rpc_call_error(&RpcError::from(e));
+                    for index in included {
+                        rejected.insert(index, call_error.clone());
+                    }
+                    break None;
+                }
+            }
+        };
+
+        let mut executed_results: Vec<CallResult> = Vec::new();
+        if let Some((payload, simulated_block)) = &execution {
+            executed_results = simulated_block
+                .calls
+                .iter()
+                .enumerate()
+                .map(|(index, sim_call_result)| CallResult::from((index, sim_call_result.clone())))
+                .collect();
+            if request.trace_transfers {
+                for ((call_result, sim_call_result), tx_request) in executed_results
+                    .iter_mut()
+                    .zip(&simulated_block.calls)
+                    .zip(&payload.block_state_calls[0].calls)
+                {
+                    call_result.native_transfers =
+                        transfers::native_transfers(tx_request, &sim_call_result.logs);
+                }
+            }
+        }
+
+        // Results of the calls of the transactions up to the halting one, the calls
+        // of the transactions that could not be executed failing with the reason
+        let mut executed_results = executed_results.into_iter();
+        let mut call_results: Vec<CallResult> = Vec::new();
+        for (index, bundle_tx) in request.bundle[..end].iter().enumerate() {
+            match rejected.get(&index) {
+                Some(call_error) => call_results.extend(
+                    bundle_tx
+                        .calls
+                        .iter()
+                        .map(|_| failed_call_result(0, call_error.clone())),
+                ),
+                None => call_results.extend(executed_results.by_ref().take(bundle_tx.calls.len())),
+            }
+        }
+        for (index, call_result) in call_results.iter_mut().enumerate() {
+            call_result.call_index = index as u32;
+        }
+
+        let (transactions, halted_at) =
+            conversion::group_bundle_results(&request.bundle, call_results);
+
+        let total_gas_used: u64 = transactions
+            .iter()
+            .map(|tx| u64::from_str_radix(tx.gas_used.trim_start_matches("0x"), 16).unwrap_or(0))
+            .sum();
+        let executed_calls = execution
+            .as_ref()
+            .map_or(0, |(_, simulated_block)| simulated_block.calls.len());
+        usage::record_simulation(executed_calls, total_gas_used);
+
+        let status = if execution.is_none() || halted_at.is_some() {
+            SimulationStatus::Failed
+        } else if transactions
+            .iter()
+            .any(|tx| tx.status == BundleTransactionStatus::Reverted)
+        {
+            SimulationStatus::Reverted
+        } else {
+            SimulationStatus::Success
+        };
+
+        let native_balance_changes = match &execution {
+            Some((payload, simulated_block)) if request.trace_transfers => {
+                let base_fee = simulated_block
+                    .inner
+                    .header
+                    .base_fee_per_gas
+                    .unwrap_or_default();
+                Some(transfers::native_balance_changes(
+                    payload.block_state_calls[0]
+                        .calls
+                        .iter()
+                        .zip(&simulated_block.calls),
+                    base_fee,
+                ))
+            }
+            _ => None,
+        };
+
+        // Asset changes are tracked for the account of the first transaction
+        let account = request
+            .bundle
+            .iter()
+            .find_map(|bundle_tx| bundle_tx.account.as_deref());
+        let asset_changes = match (account, request.trace_asset_changes, &execution) {
+            (Some(account), true, Some((payload, simulated_block))) => {
+                self.compute_asset_changes(
+                    account,
+                    std::slice::from_ref(simulated_block),
+                    payload,
+                    &block_context,
+                    &bundle_id,
+                )
+                .await
+            }
+            _ => None,
+        };
+
+        debug!(
+            target: "altitrace::simulation",
+            bundle_id = %bundle_id,
+            status = ?status,
+            halted_at = ?halted_at,
+            gas_used = total_gas_used,
+            "Bundle simulation completed"
+        );
+
+        let header = execution
+            .as_ref()
+            .map(|(_, simulated_block)| &simulated_block.inner.header);
+        Ok(BundleSimulationResult {
+            bundle_id,
+            block_number: format!("0x{:x}", header.map_or(0, |header| header.number)),
+            status,
+            halted_at,
+            transactions,
+            gas_used: format!("0x{:x}", total_gas_used),
+            block_gas_used: format!("0x{:x}", header.map_or(0, |header| header.gas_used)),
+            asset_changes,
+            native_balance_changes,
+        })
+    }
+
+    /// Executes the single block of `payload`, see [`Self::simulate_at`].
+    async fn simulate_block_at(
+        &self,
+        payload: &SimulatePayload<AlloyTransactionRequest>,
+        block_context: &BlockContext,
+    ) -> Result<SimulatedBlock, TransportError> {
+        self.simulate_at(payload, block_context)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| TransportErrorKind::custom_str("No simulation results returned"))
+    }
+
+    /// Finds the transaction of a bundle rejected by the node, e.g. for an invalid
+    /// nonce or an insufficient balance, knowing that executing the `included`
+    /// transactions fails with `error`.
+    ///
+    /// The node rejects the whole simulation without telling which transaction is
+    /// invalid, so the shortest failing prefix of the transactions is searched.
+    /// Returns the index of the transaction and the error it is rejected with, or
+    /// the error of the RPC call if it did not come from the transactions.
+    async fn find_rejected_transaction(
+        &self,
+        payload: &SimulatePayload<AlloyTransactionRequest>,
+        bundle: &[BundleTransaction],
+        included: &[usize],
+        block_context: &BlockContext,
+        error: TransportError,
+    ) -> Result<(usize, TransportError), TransportError> {
+        if error.as_error_resp().is_none() || included.is_empty() {
+            return Err(error);
+        }
+
+        // Executing the first `succeeded` transactions succeeds, the first `failed` fails
+        let (mut succeeded, mut failed, mut error) = (0, included.len(), error);
+        while failed - succeeded > 1 {
+            let count = (succeeded + failed) / 2;
+            let prefix =
+                conversion::select_bundle_transactions(payload, bundle, &included[..count]);
+            match self.simulate_block_at(&prefix, block_context).await {
+                Ok(_) => succeeded = count,
+                Err(e) if e.as_error_resp().is_some() => {
+                    failed = count;
+                    error = e;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if succeeded == 0 {
+            let empty = conversion::select_bundle_transactions(payload, bundle, &[]);
+            self.simulate_block_at(&empty, block_context).await?;
+        }
+
+        Ok((included[failed - 1], error))
+    }
+
+    /// Estimate the minimal gas limit of a transaction.
+    ///
+    /// The transaction is first executed with the block gas limit, on the block
+    /// and with the overrides of the request, while the node estimates it with
+    /// `eth_estimateGas`. The minimal successful gas limit is then searched with
+    /// `eth_simulateV1` between the gas used and the node estimate (see [`gas`]).
+    /// Suggestions are added when an optimization level or alternatives are
+    /// requested.
+    #[instrument(target = "altitrace::service", skip_all, fields(estimation_id = Empty))]
+    pub async fn estimate_gas(
+        &self,
+        mut request: GasEstimationRequest,
+    ) -> Result<GasEstimationResult, ServiceError> {
+        let estimation_id = Uuid::new_v4().to_string();
+        Span::current().record("estimation_id", estimation_id.as_str());
+        let optimization_level = request.optimization_level;
+        let include_alternatives = request.include_alternatives;
+
+        debug!(
+            target: "altitrace::simulation",
+            estimation_id = %estimation_id,
+            ?optimization_level,
+            include_alternatives,
+            "Starting gas estimation"
+        );
+
+        let block_context = self.determine_block_context(&request, &estimation_id)?;
+        let block_id = BlockId::from(&block_context);
+
+        if let Some(state_overrides) = &mut request.state_overrides {
+            self.resolve_storage_overrides(state_overrides, block_id)
+                .await?;
+        }
+
+        let sim_block: SimBlock = request.try_into().map_err(|e| {
+            ServiceError::gas_estimation_failed(format!("Invalid transaction call: {}", e))
+        })?;
+        let tx_request = sim_block.calls[0].clone();
+
+        // Execute with the block gas limit to get the gas used and the search cap, while
+        // the node estimate bounds the search
+        let (simulated_block, node_estimate) = tokio::join!(
+            self.simulate_with_gas_limit(&sim_block, None, &block_context),
+            self.provider
+                .inner
+                .estimate_gas(tx_request.clone())
+                .block(block_id)
+                .overrides_opt(sim_block.state_overrides.clone())
+                .with_block_overrides_opt(sim_block.block_overrides.clone())
+                .into_future()
+        );
+        let simulated_block = simulated_block?;
+        let call_result =
+            simulated_block.calls.first().cloned().ok_or_else(|| {
+                ServiceError::gas_estimation_failed("No simulation results returned")
+            })?;
+        if !call_result.status {
+            let reason = CallResult::from((0, call_result))
+                .error
+                .map(|error| error.reason)
+                .unwrap_or_else(|| "Transaction reverted".to_string());
+            return Err(ServiceError::gas_estimation_failed(format!(
+                "Transaction fails with the block gas limit: {}",
+                reason
+            )));
+        }
+
+        let gas_used = call_result.gas_used;
+        let cap = simulated_block.inner.header.gas_limit;
+
+        // The node estimate succeeds, the search only narrows it down
+        let upper = match node_estimate {
+            Ok(estimate) => estimate.clamp(gas_used, cap),
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    estimation_id = %estimation_id,
+                    error = ?e,
+                    "Failed to estimate gas with the node, searching up to the block gas limit"
+                );
+                cap
+            }
+        };
+
+        let mut search = GasSearch::new(gas_used, upper);
+        while let Some(gas_limit) = search.next_probe() {
+            let probe = self
+                .simulate_with_gas_limit(&sim_block, Some(gas_limit), &block_context)
+                .await?;
+            let success = probe.calls.first().is_some_and(|call| call.status);
+            trace!(
+                target: "altitrace::simulation",
+                estimation_id = %estimation_id,
+                gas_limit,
+                success,
+                "Gas limit probed"
+            );
+            search.record(gas_limit, success);
+        }
+
+        let gas_estimate = search.estimate();
+
+        // eth_createAccessList takes no overrides, its access list would not match the
+        // estimated execution
+        let overridden = sim_block.block_overrides.is_some() ||
+            sim_block
+                .state_overrides
+                .as_ref()
+                .is_some_and(|overrides| !overrides.is_empty());
+
+        let mut suggestions = Vec::new();
+        if overridden {
+            debug!(
+                target: "altitrace::simulation",
+                estimation_id = %estimation_id,
+                "Skipping the access list suggestion of an estimation with overrides"
+            );
+        } else if include_alternatives || !matches!(optimization_level, OptimizationLevel::None) {
+            suggestions.extend(
+                self.access_list_suggestion(&tx_request, block_id, gas_used, include_alternatives)
+                    .await,
+            );
+        }
+        if matches!(optimization_level, OptimizationLevel::Aggressive) {
+            suggestions.extend(gas::calldata_suggestion(
+                tx_request
+                    .input
+                    .input()
+                    .map(|input| input.as_ref())
+                    .unwrap_or_default(),
+                gas_used,
+            ));
+        }
+
+        debug!(
+            target: "altitrace::simulation",
+            estimation_id = %estimation_id,
+            gas_used,
+            gas_estimate,
+            iterations = search.iterations(),
+            suggestions = suggestions.len(),
+            "Gas estimation completed"
+        );
+
+        Ok(GasEstimationResult {
+            block_number: format!("0x{:x}", simulated_block.inner.header.number),
+            gas_estimate: format!("0x{:x}", gas_estimate),
+            gas_used: format!("0x{:x}", gas_used),
+            recommended_gas_limit: format!("0x{:x}", gas::recommended_gas_limit(gas_estimate, cap)),
+            iterations: search.iterations(),
+            suggestions,
+        })
+    }
+
+    /// Executes the call of a gas estimation block on top of the block context with
+    /// the given gas limit, or the block gas limit if `None`.
+    async fn simulate_with_gas_limit(
+        &self,
+        sim_block: &SimBlock,
+        gas_limit: Option<u64>,
+        block_context: &BlockContext,
+    ) -> Result<SimulatedBlock, ServiceError> {
+        let mut sim_block = sim_block.clone();
+        for call in &mut sim_block.calls {
+            call.gas = gas_limit;
+        }
+
+        let payload = SimulatePayload {
+            block_state_calls: vec![sim_block],
+            trace_transfers: false,
+            validation: false,
+            return_full_transactions: false,
+        };
+
+        self.simulate_at(&payload, block_context)
+            .await
+            .map_err(RpcError::from)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ServiceError::gas_estimation_failed("No simulation results returned"))
+    }
+
+    /// Suggests attaching an access list when `eth_createAccessList` reports a
+    /// lower gas usage with it. The request is sent without overrides.
+    async fn access_list_suggestion(
+        &self,
+        tx_request: &AlloyTransactionRequest,
+        block_id: BlockId,
+        gas_used: u64,
+        include_access_list: bool,
+    ) -> Option<GasSuggestion> {
+        let result = match self
+            .provider
+            .inner
+            .create_access_list(tx_request)
+            .block_id(block_id)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(
+                    target: "altitrace::simulation",
+                    error = ?e,
+                    "Failed to create access list, skipping suggestion"
+                );
+                return None;
+            }
+        };
+
+        let savings = gas_used.checked_sub(result.gas_used.saturating_to::<u64>())?;
+        if savings == 0 || result.error.is_some() {
+            return None;
+        }
+
+        let accounts = result.access_list.len();
+        let slots: usize = result
+            .access_list
+            .iter()
+            .map(|item| item.storage_keys.len())
+            .sum();
+
+        Some(GasSuggestion {
+            kind: GasSuggestionKind::AccessList,
+            description: format!(
+                "Attach an access list to pre-warm {} accounts and {} storage slots",
+                accounts, slots
+            ),
+            estimated_savings: Some(format!("0x{:x}", savings)),
+            access_list: include_access_list.then(|| result.access_list.into()),
+        })
+    }
+
+    /// Replay a mined transaction at its position in its block.
+    ///
+    /// The transaction is re-executed with `debug_traceCallMany` on the state right
+    /// before it, with the modifications of the request applied, and its outcome
+    /// is compared against the original receipt.
+    #[instrument(
+        target = "altitrace::service",
+        skip_all,
+        fields(replay_id = Empty, tx_hash = %request.transaction_hash)
+    )]
+    pub async fn replay_transaction(
+        &self,
+        mut request: ReplayRequest,
+    ) -> Result<ReplayResult, ServiceError> {
+        let replay_id = Uuid::new_v4().to_string();
+        Span::current().record("replay_id", replay_id.as_str());
+        let tx_hash = B256::from_str(&request.transaction_hash).map_err(|_| {
+            ServiceError::replay_failed(format!(
+                "Invalid transaction hash: {}",
+                request.transaction_hash
+            ))
+        })?;
+
+        debug!(
+            target: "altitrace::simulation",
+            replay_id = %replay_id,
+            %tx_hash,
+            "Starting transaction replay"
+        );
+
+        let (transaction_result, receipt_result) = tokio::join!(
+            self.provider.inner.get_transaction_by_hash(tx_hash),
+            self.provider.inner.get_transaction_receipt(tx_hash)
+        );
+        let transaction = transaction_result
+            .map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?
+            .ok_or_else(|| ServiceError::replay_failed("Transaction not found"))?;
+        let receipt = receipt_result
+            .map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?
+            .ok_or_else(|| ServiceError::replay_failed("Transaction is not mined yet"))?;
+        let (Some(block_number), Some(transaction_index)) =
+            (transaction.block_number, transaction.transaction_index)
+        else {
+            return Err(ServiceError::replay_failed("Transaction is not mined yet"));
+        };
+
+        // Packed slots are completed with the state the transaction runs on
+        if let Some(state_overrides) = &mut request.state_overrides {
+            let state = SlotState::BeforeTransaction {
+                block_number,
+                transaction_index: transaction_index as usize,
+            };
+            self.resolve_storage_overrides(state_overrides, state)
+                .await?;
+        }
+
+        let registry = AbiRegistry::new(request.abis.as_ref());
+        let tx_request = transaction.into_request();
+        let (bundle, state_overrides) = request
+            .clone()
+            .try_into_bundle(tx_request)
+            .map_err(|e| ServiceError::replay_failed(format!("Invalid modifications: {}", e)))?;
+
+        // The transactions before it in the block are executed first
+        let state_context = AlloyStateContext {
+            block_number: Some(BlockId::number(block_number)),
+            transaction_index: Some(AlloyTransactionIndex::Index(transaction_index as usize)),
+        };
+        let mut call_options = GethDebugTracingCallOptions::new(replay_tracing_options());
+        if let Some(state_overrides) = state_overrides {
+            call_options = call_options.with_state_overrides(state_overrides);
+        }
+
+        let tx_request = bundle.transactions[0].clone();
+        let frame: AlloyCallFrame = self
+            .trace_bundles(vec![bundle], state_context, call_options)
+            .await
+            .map_err(RpcError::from)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ServiceError::replay_failed("No trace returned for the transaction"))?;
+
+        let mut replay = CallResult::from(frame);
+        if !registry.is_empty() {
+            replay.apply_abis(&registry, &tx_request);
+        }
+        let comparison = ReplayComparison::new(&receipt, &replay);
+        let original = TransactionReceiptInfo::from(receipt);
+
+        debug!(
+            target: "altitrace::simulation",
+            replay_id = %replay_id,
+            status = ?replay.status,
+            ?comparison,
+            "Transaction replay completed"
+        );
+
+        Ok(ReplayResult {
+            transaction_hash: format!("{:?}", tx_hash),
+            block_number: format!("0x{:x}", block_number),
+            transaction_index,
+            original,
+            replay,
+            comparison,
+        })
+    }
+
+    /// Re-execute the transactions of a historical block in order.
+    ///
+    /// The transactions, without the removed ones and with the injected calls, are
+    /// executed with `debug_traceCallMany` on the state before the first transaction
+    /// of the block, and each original transaction is compared against its receipt.
+    #[instrument(
+        target = "altitrace::service",
+        skip_all,
+        fields(replay_id = Empty, block_number = %request.block_number)
+    )]
+    pub async fn replay_block(
+        &self,
+        request: BlockReplayRequest,
+    ) -> Result<BlockReplayResult, ServiceError> {
+        let replay_id = Uuid::new_v4().to_string();
+        Span::current().record("replay_id", replay_id.as_str());
+        let block_number = parse_block_number(&request.block_number)
+            .map_err(|e| ServiceError::replay_failed(e.to_string()))?;
+
+        debug!(
+            target: "altitrace::simulation",
+            replay_id = %replay_id,
+            block_number,
+            insertions = request.insertions.len(),
+            removals = request.removals.len(),
+            "Starting block replay"
+        );
+
+        let (block, receipts) = tokio::try_join!(
+            self.provider.get_block_by_number(block_number, true),
+            self.provider.get_block_receipts(block_number)
+        )
+        .map_err(|e| ServiceError::replay_failed(e.to_string()))?;
+        let transactions: Vec<AlloyTransactionRequest> = block
+            .transactions
+            .into_transactions()
+            .map(|transaction| transaction.into_request())
+            .collect();
+        if transactions.len() != receipts.len() {
+            return Err(ServiceError::replay_failed(format!(
+                "Expected {} receipts, got {}",
+                transactions.len(),
+                receipts.len()
+            )));
+        }
+
+        let registry = AbiRegistry::new(request.abis.as_ref());
+        let (bundle, entries) = request
+            .clone()
+            .try_into_bundle(transactions)
+            .map_err(|e| ServiceError::replay_failed(format!("Invalid modifications: {}", e)))?;
+        let tx_requests = bundle.transactions.clone();
+
+        let state_context = AlloyStateContext {
+            block_number: Some(BlockId::number(block_number)),
+            transaction_index: Some(AlloyTransactionIndex::Index(0)),
+        };
+        let call_options = GethDebugTracingCallOptions::new(replay_tracing_options());
+        let frames: Vec<AlloyCallFrame> = self
+            .trace_bundles(vec![bundle], state_context, call_options)
+            .await
+            .map_err(RpcError::from)?;
+        if frames.len() != entries.len() {
+            return Err(ServiceError::replay_failed(format!(
+                "Expected {} traces, got {}",
+                entries.len(),
+                frames.len()
+            )));
+        }
+
+        let mut original_results: Vec<Option<CallResult>> = vec![None; receipts.len()];
+        let mut injected = Vec::new();
+        let mut total_gas_used = 0u64;
+        for (position, ((entry, frame), tx_request)) in entries
+            .into_iter()
+            .zip(frames)
+            .zip(&tx_requests)
+            .enumerate()
+        {
+            total_gas_used += frame.gas_used.saturating_to::<u64>();
+            let mut result = CallResult::from(frame);
+            result.call_index = position as u32;
+            if !registry.is_empty() {
+                result.apply_abis(&registry, tx_request);
+            }
+
+            match entry {
+                conversion::BlockReplayEntry::Original(index) => {
+                    original_results[index] = Some(result)
+                }
+                conversion::BlockReplayEntry::Injected(index) => {
+                    injected.push(InjectedCallResult { index, result })
+                }
+            }
+        }
+
+        let transactions: Vec<BlockReplayTransaction> = receipts
+            .iter()
+            .zip(original_results)
+            .enumerate()
+            .map(|(index, (receipt, result))| BlockReplayTransaction {
+                transaction_index: index as u64,
+                transaction_hash: format!("{:?}", receipt.transaction_hash),
+                removed: result.is_none(),
+                comparison: result
+                    .as_ref()
+                    .map(|result| ReplayComparison::new(receipt, result)),
+                result,
+            })
+            .collect();
+        let changed_transactions = transactions
+            .iter()
+            .filter(|tx| {
+                tx.comparison
+                    .as_ref()
+                    .is_some_and(ReplayComparison::has_changed)
+            })
+            .count() as u32;
+
+        debug!(
+            target: "altitrace::simulation",
+            replay_id = %replay_id,
+            block_number,
+            changed_transactions,
+            gas_used = total_gas_used,
+            "Block replay completed"
+        );
+
+        Ok(BlockReplayResult {
+            block_number: format!("0x{:x}", block_number),
+            gas_used: format!("0x{:x}", total_gas_used),
+            changed_transactions,
+            transactions,
+            injected,
+        })
+    }
+
+    #[instrument(target = "altitrace::service", skip_all, fields(access_list_id = Empty))]
+    pub async fn create_access_list(
+        &self,
+        request: &AccessListRequest,
+    ) -> Result<AccessListResponse, ServiceError> {
+        let start_time = Instant::now();
+        let access_list_id = generate_access_list_id();
+        Span::current().record("access_list_id", access_list_id.as_str());
+        let access_list_request = request.clone();
+
+        debug!(
+            target: "altitrace::simulation",
+            access_list_id = %access_list_id,
+            "Processing access list request"
+        );
 
         let block_id = BlockId::from_str(&access_list_request.block).map_err(|_| {
             ServiceError::invalid_block_context(format!(
@@ -434,7 +2307,7 @@ impl HyperEvmService {
         let execution_time = start_time.elapsed();
         debug!(
             target: "altitrace::simulation",
-            request_id = %request_id,
+            access_list_id = %access_list_id,
             ?execution_time,
             "Access list request completed"
         );
@@ -442,11 +2315,17 @@ impl HyperEvmService {
         Ok(access_list_response.into())
     }
 
+    #[instrument(
+        target = "altitrace::service",
+        skip_all,
+        fields(trace_id = Empty, tx_hash = %request.transaction_hash)
+    )]
     pub async fn trace_transaction(
         &self,
         request: &TraceTransactionRequest,
     ) -> Result<TraceResponse, ServiceError> {
         let trace_id = generate_trace_id();
+        Span::current().record("trace_id", trace_id.as_str());
         let tx_hash = B256::from_str(&request.transaction_hash).map_err(|_| {
             ServiceError::trace_failed(format!(
                 "Invalid transaction hash: {}",
@@ -463,7 +2342,7 @@ impl HyperEvmService {
         );
 
         // Create tracing strategy based on configuration
-        let strategy = TracingStrategy::from_config(&request.tracer_config);
+        let strategy = TracingStrategy::from_config(&request.tracer_config.with_storage_prestate());
 
         debug!(
             target: "altitrace::trace",
@@ -472,18 +2351,39 @@ impl HyperEvmService {
             "Tracing strategy"
         );
 
-        let (trace_result, receipt_result) = tokio::join!(
+        // The transaction is only needed for the gas breakdown and the storage accesses
+        // of the struct logs
+        let with_struct_logger = request.tracer_config.tracers.struct_logger.is_some();
+        let (trace_result, receipt_result, transaction_result) = tokio::join!(
             strategy.execute(tx_hash, |hash, options| {
                 let provider = &self.provider.inner;
                 async move { provider.debug_trace_transaction(hash, options).await }
             }),
-            self.provider.inner.get_transaction_receipt(tx_hash)
+            self.provider.inner.get_transaction_receipt(tx_hash),
+            async {
+                if with_struct_logger {
+                    self.provider.inner.get_transaction_by_hash(tx_hash).await
+                } else {
+                    Ok(None)
+                }
+            }
         );
 
         let trace_result = trace_result.map_err(ServiceError::NodeCommunication)?;
         let receipt = receipt_result
             .map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?
             .ok_or_else(|| ServiceError::trace_failed("No receipt found for transaction"))?;
+        let transaction =
+            transaction_result.map_err(|e| ServiceError::NodeCommunication(RpcError::from(e)))?;
+        let intrinsic_gas = transaction.as_ref().map(IntrinsicGas::from);
+        let access_list = transaction
+            .as_ref()
+            .and_then(|transaction| transaction.access_list().cloned());
+        let target = transaction
+            .as_ref()
+            .and_then(|transaction| transaction.to())
+            .or(receipt.contract_address);
+        let block_number = receipt.block_number;
         let receipt = TransactionReceiptInfo::from(receipt);
 
         let elapsed_time = start_time.elapsed();
@@ -496,11 +2396,21 @@ impl HyperEvmService {
             "Tracing completed"
         );
 
-        let trace_response = TraceResponse::new(trace_result).with_receipt(receipt);
+        let trace_response = TraceResponse::new(trace_result)
+            .with_receipt(receipt)
+            .with_intrinsic_gas(intrinsic_gas)
+            .with_target(target)
+            .with_access_list(access_list)
+            .with_block_number(block_number);
 
         Ok(trace_response)
     }
 
+    #[instrument(
+        target = "altitrace::service",
+        skip_all,
+        fields(trace_id = Empty, block = %request.block)
+    )]
     pub async fn trace_call(
         &self,
         request: &TraceCallRequest,
@@ -514,6 +2424,7 @@ impl HyperEvmService {
 
         let start_time = Instant::now();
         let trace_id = generate_trace_id();
+        Span::current().record("trace_id", trace_id.as_str());
 
         debug!(
             target: "altitrace::trace",
@@ -522,7 +2433,16 @@ impl HyperEvmService {
             "Starting call trace"
         );
 
-        let tracing_strategy = TracingStrategy::from_config(&request.tracer_config);
+        let mut request = request.clone();
+        if let Some(state_overrides) = &mut request.state_overrides {
+            for (address, state_override) in state_overrides.iter_mut() {
+                self.resolve_storage_variables(address, state_override, block_id)
+                    .await?;
+            }
+        }
+
+        let tracing_strategy =
+            TracingStrategy::from_config(&request.tracer_config.with_storage_prestate());
 
         let trace_result = tracing_strategy
             .execute_call(request.clone(), |tx_request, block_id, options| {
@@ -546,17 +2466,23 @@ impl HyperEvmService {
             "Tracing completed"
         );
 
-        let trace_response = TraceResponse::new(trace_result);
+        let tx = AlloyTransactionRequest::try_from(request.call.clone()).ok();
+        let trace_response = TraceResponse::new(trace_result)
+            .with_intrinsic_gas(tx.as_ref().map(IntrinsicGas::from))
+            .with_target(call_target(&request.call))
+            .with_access_list(tx.and_then(|tx| tx.access_list));
 
         Ok(trace_response)
     }
 
+    #[instrument(target = "altitrace::service", skip_all, fields(trace_id = Empty))]
     pub async fn trace_call_many(
         &self,
         request: &TraceCallManyRequest,
     ) -> Result<Vec<TraceResponse>, ServiceError> {
         let start_time = Instant::now();
         let trace_id = generate_trace_id();
+        Span::current().record("trace_id", trace_id.as_str());
 
         debug!(
             target: "altitrace::trace",
@@ -565,16 +2491,12 @@ impl HyperEvmService {
             "Starting call many trace"
         );
 
-        let tracing_strategy = TracingStrategy::from_config(&request.tracer_config);
+        let tracing_strategy =
+            TracingStrategy::from_config(&request.tracer_config.with_storage_prestate());
 
         let trace_result = tracing_strategy
             .execute_call_many(request.clone(), |bundles, state_context, options| {
-                let provider = &self.provider.inner;
-                async move {
-                    provider
-                        .debug_trace_call_many(bundles, state_context, options)
-                        .await
-                }
+                self.trace_bundles::<GethTrace>(bundles, state_context, options)
             })
             .await
             .map_err(ServiceError::NodeCommunication)?;
@@ -588,13 +2510,169 @@ impl HyperEvmService {
             "Call many trace completed"
         );
 
-        // Convert TracingResultMany to Vec<TraceResponse>
+        // Convert TracingResultMany to Vec<TraceResponse>, one per transaction of the bundles
+        let calls = request
+            .bundles
+            .iter()
+            .flat_map(|bundle| &bundle.transactions);
         let trace_responses = trace_result
             .into_individual_results()
             .into_iter()
-            .map(TraceResponse::new)
+            .zip(calls)
+            .map(|(result, call)| {
+                let tx = AlloyTransactionRequest::try_from(call.clone()).ok();
+                TraceResponse::new(result)
+                    .with_intrinsic_gas(tx.as_ref().map(IntrinsicGas::from))
+                    .with_target(call_target(call))
+                    .with_access_list(tx.and_then(|tx| tx.access_list))
+            })
             .collect();
 
         Ok(trace_responses)
     }
 }
+
+/// Builds the `debug_traceCallMany` bundle executing the calls of a simulated block
+/// on top of the block context.
+fn sim_block_bundle(
+    sim_block: &SimBlock<AlloyTransactionRequest>,
+    block_context: &BlockContext,
+) -> (AlloyBundle, AlloyStateContext) {
+    let bundle = AlloyBundle {
+        transactions: sim_block.calls.clone(),
+        block_override: sim_block.block_overrides.clone(),
+    };
+    let state_context = AlloyStateContext {
+        block_number: Some(block_context.into()),
+        transaction_index: Some(AlloyTransactionIndex::All),
+    };
+    (bundle, state_context)
+}
+
+/// Runtime code returning the storage slot given as calldata:
+/// `PUSH1 0 CALLDATALOAD SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`.
+const SLOAD_PROBE_CODE: &[u8] = &hex!("6000355460005260206000f3");
+
+/// State storage slots are read from.
+#[derive(Debug, Clone, Copy)]
+enum SlotState {
+    /// State at the end of a block.
+    Block(BlockId),
+    /// State right before a transaction, after the previous ones of its block.
+    BeforeTransaction { block_number: u64, transaction_index: usize },
+}
+
+impl From<BlockId> for SlotState {
+    fn from(block_id: BlockId) -> Self {
+        Self::Block(block_id)
+    }
+}
+
+/// Cache key of a stored simulation.
+fn simulation_key(simulation_id: &str) -> String {
+    format!("simulation:{}", simulation_id)
+}
+
+/// Tracing options of replayed transactions: the full call tree with the logs.
+fn replay_tracing_options() -> GethDebugTracingOptions {
+    GethDebugTracingOptions::call_tracer(CallConfig {
+        only_top_call: Some(false),
+        with_log: Some(true),
+    })
+}
+
+/// Returns the recipient of a traced call, `None` for contract creations.
+fn call_target(call: &TransactionCall) -> Option<Address> {
+    call.to.as_deref().and_then(|to| Address::from_str(to).ok())
+}
+
+/// Call error reporting an RPC error, with a sanitized reason.
+fn rpc_call_error(rpc_error: &RpcError) -> CallError {
+    let sanitized_reason = match rpc_error {
+        RpcError::Timeout { .. } => {
+            "RPC request timeout, check if RPC is running and accepting connections".to_string()
+        }
+        RpcError::ConnectionFailed { .. } => {
+            "RPC connection failed, check if RPC is running and accepting connections".to_string()
+        }
+        RpcError::ExecutionReverted { reason, .. } => reason.clone(),
+        _ => rpc_error.to_string(),
+    };
+
+    CallError {
+        reason: sanitized_reason.clone(),
+        error_type: rpc_error.error_code().to_lowercase().replace('_', "-"),
+        message: Some(sanitized_reason),
+        contract_address: None,
+        data: None,
+        decoded: None,
+    }
+}
+
+/// Result of a call that could not be executed.
+fn failed_call_result(call_index: usize, error: CallError) -> CallResult {
+    CallResult {
+        call_index: call_index as u32,
+        status: CallStatus::Reverted,
+        return_data: "0x".to_string(),
+        gas_used: "0x0".to_string(),
+        gas_breakdown: None,
+        logs: vec![],
+        decoded: None,
+        native_transfers: vec![],
+        error: Some(error),
+        trace: None,
+    }
+}
+
+/// Converts the calls of a simulated block to API call results.
+///
+/// Calls are decoded with the supplied ABIs and, when transfer tracing is
+/// enabled, their native transfers are split from the logs and aggregated into
+/// the native balance changes of the block. Fees are only charged when the node
+/// validates the transactions.
+fn process_block_results(
+    simulated_block: &SimulatedBlock,
+    sim_block: &SimBlock<AlloyTransactionRequest>,
+    registry: &AbiRegistry<'_>,
+    trace_transfers: bool,
+    validation: bool,
+) -> (Vec<CallResult>, Option<Vec<NativeBalanceChange>>) {
+    let requests = &sim_block.calls;
+    let mut call_results: Vec<CallResult> = simulated_block
+        .calls
+        .iter()
+        .enumerate()
+        .map(|(index, sim_call_result)| CallResult::from((index, sim_call_result.clone())))
+        .collect();
+
+    if !registry.is_empty() {
+        for (call_result, tx_request) in call_results.iter_mut().zip(requests) {
+            call_result.apply_abis(registry, tx_request);
+        }
+    }
+
+    let native_balance_changes = trace_transfers.then(|| {
+        for ((call_result, sim_call_result), tx_request) in call_results
+            .iter_mut()
+            .zip(&simulated_block.calls)
+            .zip(requests)
+        {
+            call_result.native_transfers =
+                transfers::native_transfers(tx_request, &sim_call_result.logs);
+        }
+
+        let base_fee = if validation {
+            simulated_block
+                .inner
+                .header
+                .base_fee_per_gas
+                .unwrap_or_default()
+        } else {
+            0
+        };
+        transfers::native_balance_changes(requests.iter().zip(&simulated_block.calls), base_fee)
+    });
+
+    (call_results, native_balance_changes)
+}
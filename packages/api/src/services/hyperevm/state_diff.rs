@@ -0,0 +1,331 @@
+//! State diff of simulations.
+//!
+//! The calls are traced with the prestate tracer in diff mode, which reports for
+//! every call the state of the accounts it modified before (`pre`) and after
+//! (`post`) its execution. `post` only holds the fields that changed, without
+//! the storage slots that were zeroed, and the accounts missing from it were
+//! deleted. The per-call diffs are merged into a
+//! single diff from the state before the first call to the state after the last
+//! one, keeping only the values that actually changed.
+
+use std::collections::{hash_map::Entry, BTreeMap};
+
+use crate::handlers::{
+    simulation::response::{
+        AccountDiff, ContractStorageDiff, StateDiff, StorageSlotDiff, ValueDiff,
+    },
+    trace::{AccountState, PrestateDiffMode},
+};
+
+/// Balance of an account that does not exist.
+const ZERO_BALANCE: &str = "0x0";
+
+/// Code of an account without code.
+const EMPTY_CODE: &str = "0x";
+
+/// Value of an unset storage slot.
+const ZERO_SLOT: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Account fields known at some point of the simulation.
+#[derive(Debug, Default)]
+struct Fields {
+    balance: Option<String>,
+    nonce: Option<u64>,
+    code: Option<String>,
+    storage: BTreeMap<String, String>,
+}
+
+impl Fields {
+    /// Records the fields of `state` that are not known yet.
+    fn record_missing(&mut self, state: &AccountState) {
+        if self.balance.is_none() {
+            self.balance.clone_from(&state.balance);
+        }
+        if self.nonce.is_none() {
+            self.nonce = state.nonce;
+        }
+        if self.code.is_none() {
+            self.code.clone_from(&state.code);
+        }
+        for (slot, value) in &state.storage {
+            self.storage
+                .entry(slot.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Overwrites the fields set in `state`.
+    fn update(&mut self, state: AccountState) {
+        if state.balance.is_some() {
+            self.balance = state.balance;
+        }
+        if state.nonce.is_some() {
+            self.nonce = state.nonce;
+        }
+        if state.code.is_some() {
+            self.code = state.code;
+        }
+        self.storage.extend(state.storage);
+    }
+}
+
+/// State of an account before the first call and after the last one.
+#[derive(Debug, Default)]
+struct AccountChanges {
+    before: Fields,
+    after: Fields,
+}
+
+impl AccountChanges {
+    /// Converts the changes to an account diff, `None` if nothing changed.
+    fn into_diff(self, address: String) -> Option<AccountDiff> {
+        let Self { before, after } = self;
+
+        let balance = changed(before.balance, after.balance, ZERO_BALANCE.to_string());
+        let nonce = changed(before.nonce, after.nonce, 0);
+        let code = changed(before.code, after.code, EMPTY_CODE.to_string());
+
+        let mut before_storage = before.storage;
+        let storage: Vec<StorageSlotDiff> = after
+            .storage
+            .into_iter()
+            .filter_map(|(slot, after)| {
+                let before = before_storage
+                    .remove(&slot)
+                    .unwrap_or_else(|| ZERO_SLOT.to_string());
+                (before != after).then_some(StorageSlotDiff { slot, before, after })
+            })
+            .collect();
+
+        if balance.is_none() && nonce.is_none() && code.is_none() && storage.is_empty() {
+            return None;
+        }
+
+        Some(AccountDiff { address, balance, nonce, code, storage })
+    }
+}
+
+/// Returns the value change, `None` if the value did not change.
+///
+/// A missing `before` value is the default of an account that did not exist,
+/// and a missing `after` value was left unchanged.
+fn changed<T: PartialEq + Clone>(
+    before: Option<T>,
+    after: Option<T>,
+    default: T,
+) -> Option<ValueDiff<T>> {
+    let before = before.unwrap_or(default);
+    let after = after.unwrap_or_else(|| before.clone());
+    (before != after).then_some(ValueDiff { before, after })
+}
+
+/// Merges the per-call prestate diffs of consecutive calls into the state diff
+/// of the whole simulation.
+///
+/// Accounts whose balance, nonce or code changed are reported with their
+/// storage changes, while contracts whose storage only changed are grouped
+/// separately. Both lists are sorted by address.
+pub fn merge_state_diffs(diffs: impl IntoIterator<Item = PrestateDiffMode>) -> StateDiff {
+    let mut accounts: BTreeMap<String, AccountChanges> = BTreeMap::new();
+
+    for diff in diffs {
+        let PrestateDiffMode { pre, mut post } = diff;
+
+        for (address, state) in pre {
+            let changes = accounts.entry(address.clone()).or_default();
+            changes.before.record_missing(&state);
+
+            match post.entry(address) {
+                // Zeroed slots are missing from the post state
+                Entry::Occupied(mut entry) => {
+                    let storage = &mut entry.get_mut().storage;
+                    for slot in state.storage.into_keys() {
+                        storage.entry(slot).or_insert_with(|| ZERO_SLOT.to_string());
+                    }
+                }
+                // Deleted accounts are missing from the post state
+                Entry::Vacant(entry) => {
+                    entry.insert(AccountState {
+                        balance: Some(ZERO_BALANCE.to_string()),
+                        nonce: Some(0),
+                        code: Some(EMPTY_CODE.to_string()),
+                        storage: state
+                            .storage
+                            .into_keys()
+                            .map(|slot| (slot, ZERO_SLOT.to_string()))
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        for (address, state) in post {
+            accounts.entry(address).or_default().after.update(state);
+        }
+    }
+
+    let mut state_diff = StateDiff::default();
+    for diff in accounts
+        .into_iter()
+        .filter_map(|(address, changes)| changes.into_diff(address))
+    {
+        if diff.balance.is_none() && diff.nonce.is_none() && diff.code.is_none() {
+            state_diff
+                .storage
+                .push(ContractStorageDiff { address: diff.address, storage: diff.storage });
+        } else {
+            state_diff.accounts.push(diff);
+        }
+    }
+
+    state_diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const SENDER: &str = "0x1111111111111111111111111111111111111111";
+    const TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    const CREATED: &str = "0x3333333333333333333333333333333333333333";
+
+    fn slot(byte: u8) -> String {
+        format!("0x{:064x}", byte)
+    }
+
+    fn account(balance: Option<&str>, nonce: Option<u64>) -> AccountState {
+        AccountState {
+            balance: balance.map(str::to_string),
+            nonce,
+            code: None,
+            storage: HashMap::new(),
+        }
+    }
+
+    fn storage(slots: &[(u8, u8)]) -> AccountState {
+        AccountState {
+            storage: slots
+                .iter()
+                .map(|(key, value)| (slot(*key), slot(*value)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_consecutive_calls() {
+        // First call: transfer tokens, balance of slot 1 goes from 5 to 3
+        let first = PrestateDiffMode {
+            pre: HashMap::from([
+                (SENDER.to_string(), account(Some("0x100"), Some(0))),
+                (TOKEN.to_string(), storage(&[(1, 5)])),
+            ]),
+            post: HashMap::from([
+                (SENDER.to_string(), account(Some("0xf0"), Some(1))),
+                (TOKEN.to_string(), storage(&[(1, 3)])),
+            ]),
+        };
+        // Second call: transfer them back and deploy a contract
+        let second = PrestateDiffMode {
+            pre: HashMap::from([
+                (SENDER.to_string(), account(Some("0xf0"), Some(1))),
+                (TOKEN.to_string(), storage(&[(1, 3)])),
+            ]),
+            post: HashMap::from([
+                (SENDER.to_string(), account(Some("0xe0"), Some(2))),
+                (TOKEN.to_string(), storage(&[(1, 5), (2, 7)])),
+                (
+                    CREATED.to_string(),
+                    AccountState { code: Some("0x6000".to_string()), ..account(None, Some(1)) },
+                ),
+            ]),
+        };
+
+        let state_diff = merge_state_diffs([first, second]);
+
+        assert_eq!(
+            state_diff.accounts,
+            vec![
+                AccountDiff {
+                    address: SENDER.to_string(),
+                    balance: Some(ValueDiff {
+                        before: "0x100".to_string(),
+                        after: "0xe0".to_string()
+                    }),
+                    nonce: Some(ValueDiff { before: 0, after: 2 }),
+                    code: None,
+                    storage: vec![],
+                },
+                AccountDiff {
+                    address: CREATED.to_string(),
+                    balance: None,
+                    nonce: Some(ValueDiff { before: 0, after: 1 }),
+                    code: Some(ValueDiff { before: "0x".to_string(), after: "0x6000".to_string() }),
+                    storage: vec![],
+                },
+            ]
+        );
+        // Slot 1 is back to its original value
+        assert_eq!(
+            state_diff.storage,
+            vec![ContractStorageDiff {
+                address: TOKEN.to_string(),
+                storage: vec![StorageSlotDiff { slot: slot(2), before: slot(0), after: slot(7) }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_zeroed_slots() {
+        // The post state omits slot 1, cleared by the call, but keeps slot 2
+        let diff = PrestateDiffMode {
+            pre: HashMap::from([
+                (SENDER.to_string(), account(Some("0x100"), Some(0))),
+                (TOKEN.to_string(), storage(&[(1, 5), (2, 6)])),
+            ]),
+            post: HashMap::from([
+                (SENDER.to_string(), account(Some("0xf0"), Some(1))),
+                (TOKEN.to_string(), storage(&[(2, 7)])),
+            ]),
+        };
+
+        let state_diff = merge_state_diffs([diff]);
+
+        assert_eq!(
+            state_diff.storage,
+            vec![ContractStorageDiff {
+                address: TOKEN.to_string(),
+                storage: vec![
+                    StorageSlotDiff { slot: slot(1), before: slot(5), after: slot(0) },
+                    StorageSlotDiff { slot: slot(2), before: slot(6), after: slot(7) },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_deleted_account() {
+        let diff = PrestateDiffMode {
+            pre: HashMap::from([(
+                CREATED.to_string(),
+                AccountState { code: Some("0x6000".to_string()), ..storage(&[(1, 1)]) },
+            )]),
+            post: HashMap::new(),
+        };
+
+        let state_diff = merge_state_diffs([diff]);
+
+        assert!(state_diff.storage.is_empty());
+        let deleted = &state_diff.accounts[0];
+        assert_eq!(deleted.balance, None);
+        assert_eq!(
+            deleted.code,
+            Some(ValueDiff { before: "0x6000".to_string(), after: "0x".to_string() })
+        );
+        assert_eq!(
+            deleted.storage,
+            vec![StorageSlotDiff { slot: slot(1), before: slot(1), after: slot(0) }]
+        );
+    }
+}
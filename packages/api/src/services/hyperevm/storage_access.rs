@@ -0,0 +1,511 @@
+//! Storage accesses of an execution, extracted from its struct logs.
+//!
+//! Struct logs do not carry the address of the executing contract, so it is
+//! tracked from the call opcodes: `CALL` and `STATICCALL` execute the callee
+//! with its own storage, `DELEGATECALL` and `CALLCODE` keep the storage of the
+//! caller, and the address of a created contract is read from the stack once
+//! `CREATE` or `CREATE2` returns. Slots and values are read from the stack,
+//! which must be captured.
+//!
+//! Accesses are classified as cold or warm by tracking the slots accessed by
+//! the transaction (EIP-2929), starting with those of its access list. The
+//! value before a write is the last value read or written in the same
+//! execution, or the value of the slot before the transaction. Frames that
+//! revert undo their accesses: they are dropped from the report, and the slots
+//! they warmed and the values they wrote are restored.
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use alloy_primitives::{Address, B256, U256};
+use alloy_rpc_types_eth::AccessList;
+
+use crate::{
+    handlers::trace::{ContractStorageAccesses, StructLog},
+    types::{
+        RegularStorageSlotAccess, SimpleStorageValue, StorageSlotAccess, StorageValue,
+        TransientSlotAccess,
+    },
+};
+
+/// Owner of the storage accessed by a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StorageOwner {
+    /// Contract at a known address.
+    Address(Address),
+    /// Contract created by the opcode at the given index, whose address is known
+    /// once the creation returns.
+    Created(usize),
+}
+
+/// Storage access attributed to its owner.
+enum Access {
+    Storage(RegularStorageSlotAccess),
+    Transient(TransientSlotAccess),
+}
+
+/// Storage slot of a contract.
+type SlotKey = (StorageOwner, B256);
+
+/// Change to the storage state, undone when the frame that made it reverts.
+enum JournalEntry {
+    /// The slot was accessed for the first time.
+    Warmed(SlotKey),
+    /// The known value of the slot changed from the given one.
+    Value(SlotKey, Option<B256>),
+}
+
+/// Storage state of the execution: accessed slots and last known values.
+#[derive(Default)]
+struct StorageState {
+    /// Slots accessed so far (EIP-2929).
+    warm: HashSet<SlotKey>,
+    /// Last value read or written of each slot.
+    values: HashMap<SlotKey, B256>,
+    /// Changes made so far, in order.
+    journal: Vec<JournalEntry>,
+}
+
+impl StorageState {
+    /// Marks a slot as accessed, returning whether it was cold.
+    fn warm(&mut self, key: SlotKey) -> bool {
+        let cold = self.warm.insert(key);
+        if cold {
+            self.journal.push(JournalEntry::Warmed(key));
+        }
+        cold
+    }
+
+    /// Records the value of a slot, returning the previously known value.
+    fn set_value(&mut self, key: SlotKey, value: B256) -> Option<B256> {
+        let previous = self.values.insert(key, value);
+        self.journal.push(JournalEntry::Value(key, previous));
+        previous
+    }
+
+    /// Undoes the changes made after the journal had `checkpoint` entries.
+    fn revert(&mut self, checkpoint: usize) {
+        for entry in self.journal.drain(checkpoint..).rev() {
+            match entry {
+                JournalEntry::Warmed(key) => {
+                    self.warm.remove(&key);
+                }
+                JournalEntry::Value(key, Some(value)) => {
+                    self.values.insert(key, value);
+                }
+                JournalEntry::Value(key, None) => {
+                    self.values.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Attributes the slots of a created contract to its address, now known.
+    fn resolve(&mut self, entry: usize, address: Address) {
+        let resolve = |key: SlotKey| match key {
+            (StorageOwner::Created(created), slot) if created == entry => {
+                (StorageOwner::Address(address), slot)
+            }
+            key => key,
+        };
+
+        self.warm = self.warm.drain().map(resolve).collect();
+        self.values = self
+            .values
+            .drain()
+            .map(|(key, value)| (resolve(key), value))
+            .collect();
+        for journal_entry in &mut self.journal {
+            match journal_entry {
+                JournalEntry::Warmed(key) | JournalEntry::Value(key, _) => *key = resolve(*key),
+            }
+        }
+    }
+}
+
+/// Frame being executed.
+struct Frame {
+    /// Index of the opcode that entered the frame, `None` for the transaction.
+    entry: Option<usize>,
+    /// Owner of the storage accessed by the frame.
+    owner: StorageOwner,
+    /// Length of the journal when the frame was entered.
+    checkpoint: usize,
+    /// Number of accesses when the frame was entered.
+    accesses: usize,
+}
+
+/// Extracts the storage accesses of an execution, grouped by contract in order
+/// of first access.
+///
+/// `target` is the contract executed by the transaction, `access_list` its
+/// access list and `prestate` the values of the slots before the transaction,
+/// keyed by contract and slot. Returns `None` if the struct logs were captured
+/// without the stack.
+pub fn storage_accesses(
+    logs: &[StructLog],
+    target: Address,
+    access_list: Option<&AccessList>,
+    prestate: &HashMap<(Address, B256), B256>,
+) -> Option<Vec<ContractStorageAccesses>> {
+    let mut frames = vec![Frame {
+        entry: None,
+        owner: StorageOwner::Address(target),
+        checkpoint: 0,
+        accesses: 0,
+    }];
+    let mut created: HashMap<usize, Address> = HashMap::new();
+    let mut state = StorageState::default();
+    let mut accesses: Vec<(StorageOwner, Access)> = Vec::new();
+
+    for item in access_list.iter().flat_map(|list| list.iter()) {
+        for slot in &item.storage_keys {
+            state
+                .warm
+                .insert((StorageOwner::Address(item.address), *slot));
+        }
+    }
+
+    for (index, log) in logs.iter().enumerate() {
+        let owner = frames
+            .last()
+            .map_or(StorageOwner::Address(target), |frame| frame.owner);
+        let next = logs.get(index + 1);
+        let executed = log.error.is_none() && next.is_some_and(|next| next.depth == log.depth);
+
+        match log.op.as_str() {
+            "SLOAD" | "SSTORE" | "TLOAD" | "TSTORE" if executed => {
+                let slot = B256::from(stack_item(log, 0)?);
+                let value = match log.op.as_str() {
+                    "SLOAD" | "TLOAD" => B256::from(stack_item(next?, 0)?),
+                    _ => B256::from(stack_item(log, 1)?),
+                };
+
+                let access = match log.op.as_str() {
+                    "SLOAD" => {
+                        let cold = state.warm((owner, slot));
+                        state.set_value((owner, slot), value);
+                        Access::Storage(slot_access(
+                            log,
+                            slot,
+                            StorageValue { value_before: None, value_after: value.to_string() },
+                            Some(cold),
+                        ))
+                    }
+                    "SSTORE" => {
+                        let cold = state.warm((owner, slot));
+                        let value_before =
+                            state
+                                .set_value((owner, slot), value)
+                                .or_else(|| match owner {
+                                    StorageOwner::Address(address) => {
+                                        prestate.get(&(address, slot)).copied()
+                                    }
+                                    // Contracts created by the transaction start with empty storage
+                                    StorageOwner::Created(_) => Some(B256::ZERO),
+                                });
+                        Access::Storage(slot_access(
+                            log,
+                            slot,
+                            StorageValue {
+                                value_before: value_before.map(|value| value.to_string()),
+                                value_after: value.to_string(),
+                            },
+                            Some(cold),
+                        ))
+                    }
+                    _ => Access::Transient(slot_access(
+                        log,
+                        slot,
+                        SimpleStorageValue { value: value.to_string() },
+                        None,
+                    )),
+                };
+                accesses.push((owner, access));
+            }
+            _ => {}
+        }
+
+        match next {
+            Some(next) if next.depth > log.depth => {
+                let callee = match log.op.as_str() {
+                    "CALL" | "STATICCALL" => {
+                        StorageOwner::Address(Address::from_word(B256::from(stack_item(log, 1)?)))
+                    }
+                    "CREATE" | "CREATE2" => StorageOwner::Created(index),
+                    _ => owner,
+                };
+                frames.push(Frame {
+                    entry: Some(index),
+                    owner: callee,
+                    checkpoint: state.journal.len(),
+                    accesses: accesses.len(),
+                });
+            }
+            Some(next) if next.depth < log.depth => {
+                while let Some(&Frame { entry: Some(entry), checkpoint, accesses: len, .. }) =
+                    frames.last()
+                {
+                    if logs[entry].depth < next.depth {
+                        break;
+                    }
+                    frames.pop();
+
+                    // The caller gets zero on its stack if the frame reverted, the
+                    // success flag of a call or the address of a created contract
+                    // otherwise
+                    let result = stack_item(next, 0)?;
+                    if result.is_zero() {
+                        state.revert(checkpoint);
+                        accesses.truncate(len);
+                    } else if matches!(logs[entry].op.as_str(), "CREATE" | "CREATE2") {
+                        let address = Address::from_word(B256::from(result));
+                        state.resolve(entry, address);
+                        created.insert(entry, address);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A failed transaction reverts all its accesses
+    if logs
+        .last()
+        .is_some_and(|log| log.op == "REVERT" || log.error.is_some())
+    {
+        return Some(Vec::new());
+    }
+
+    // Group the accesses by contract
+    let mut contracts: Vec<ContractStorageAccesses> = Vec::new();
+    let mut positions: HashMap<Address, usize> = HashMap::new();
+    for (owner, access) in accesses {
+        let address = match owner {
+            StorageOwner::Address(address) => address,
+            StorageOwner::Created(entry) => match created.get(&entry) {
+                Some(address) => *address,
+                None => continue,
+            },
+        };
+
+        let position = *positions.entry(address).or_insert_with(|| {
+            contracts.push(ContractStorageAccesses {
+                address: format!("0x{:x}", address),
+                storage: Vec::new(),
+                transient_storage: Vec::new(),
+            });
+            contracts.len() - 1
+        });
+        match access {
+            Access::Storage(access) => contracts[position].storage.push(access),
+            Access::Transient(access) => contracts[position].transient_storage.push(access),
+        }
+    }
+
+    Some(contracts)
+}
+
+/// Returns the `n`-th item from the top of the stack of a struct log.
+fn stack_item(log: &StructLog, n: usize) -> Option<U256> {
+    let stack = log.stack.as_ref()?;
+    let item = stack.get(stack.len().checked_sub(n + 1)?)?;
+    U256::from_str(item).ok()
+}
+
+/// Builds the access of a slot by a struct log.
+fn slot_access<T>(
+    log: &StructLog,
+    slot: B256,
+    value: T,
+    cold: Option<bool>,
+) -> StorageSlotAccess<T> {
+    StorageSlotAccess {
+        slot: slot.to_string(),
+        value,
+        gas_cost: log.gas_cost,
+        cold,
+        operation: log.op.clone(),
+        pc: log.pc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    fn log(op: &str, gas_cost: u64, depth: u64, stack: &[u64]) -> StructLog {
+        StructLog {
+            op: op.to_string(),
+            gas_cost,
+            depth,
+            stack: Some(stack.iter().map(|item| format!("0x{:x}", item)).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn word(value: u64) -> String {
+        B256::from(U256::from(value)).to_string()
+    }
+
+    #[test]
+    fn test_storage_reads_and_writes() {
+        let target = address!("00000000000000000000000000000000000000aa");
+        let logs = vec![
+            log("SLOAD", 2_100, 1, &[1]),
+            // The loaded value is pushed on the stack
+            log("DUP1", 3, 1, &[4]),
+            log("SSTORE", 2_900, 1, &[7, 1]),
+            log("SSTORE", 22_100, 1, &[9, 2]),
+            log("TSTORE", 100, 1, &[5, 3]),
+            log("TLOAD", 100, 1, &[3]),
+            log("STOP", 0, 1, &[5]),
+        ];
+
+        let prestate = HashMap::from([((target, B256::from(U256::from(2))), B256::ZERO)]);
+        let report = storage_accesses(&logs, target, None, &prestate).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].address, "0x00000000000000000000000000000000000000aa");
+
+        let storage = &report[0].storage;
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage[0].operation, "SLOAD");
+        assert_eq!(storage[0].slot, word(1));
+        assert_eq!(storage[0].value.value_after, word(4));
+        assert_eq!(storage[0].cold, Some(true));
+
+        // The write is warm and its previous value is the one read before
+        assert_eq!(storage[1].value.value_before, Some(word(4)));
+        assert_eq!(storage[1].value.value_after, word(7));
+        assert_eq!(storage[1].cold, Some(false));
+
+        // The previous value of a slot first accessed by a write is its prestate
+        assert_eq!(storage[2].value.value_before, Some(word(0)));
+        assert_eq!(storage[2].cold, Some(true));
+
+        let transient = &report[0].transient_storage;
+        assert_eq!(transient.len(), 2);
+        assert_eq!(transient[0].value.value, word(5));
+        assert_eq!(transient[1].value.value, word(5));
+        assert_eq!(transient[1].cold, None);
+    }
+
+    #[test]
+    fn test_storage_owner_follows_calls() {
+        let target = address!("00000000000000000000000000000000000000aa");
+        let callee = 0xbb;
+        let created = 0xcc;
+        let logs = vec![
+            // CALL(gas, 0xbb, ...) then DELEGATECALL(gas, 0xdd, ...) from 0xbb
+            log("CALL", 0, 1, &[0, 0, 0, 0, 0, callee, 100]),
+            log("SSTORE", 2_900, 2, &[1, 1]),
+            log("DELEGATECALL", 0, 2, &[0, 0, 0, 0, 0xdd, 100]),
+            log("SSTORE", 2_900, 3, &[2, 2]),
+            log("STOP", 0, 3, &[]),
+            log("STOP", 0, 2, &[1]),
+            // The success flag of the call is pushed on the stack
+            log("POP", 2, 1, &[1]),
+            log("CREATE", 0, 1, &[0, 0, 0]),
+            log("SSTORE", 22_100, 2, &[3, 3]),
+            log("RETURN", 0, 2, &[0, 0]),
+            // The created address is pushed on the stack
+            log("POP", 2, 1, &[created]),
+            log("SSTORE", 2_900, 1, &[4, 4]),
+            log("STOP", 0, 1, &[]),
+        ];
+
+        let report = storage_accesses(&logs, target, None, &HashMap::new()).unwrap();
+        let addresses: Vec<&str> = report
+            .iter()
+            .map(|contract| contract.address.as_str())
+            .collect();
+        assert_eq!(
+            addresses,
+            [
+                "0x00000000000000000000000000000000000000bb",
+                "0x00000000000000000000000000000000000000cc",
+                "0x00000000000000000000000000000000000000aa",
+            ]
+        );
+
+        // The delegate call writes to the storage of the caller
+        assert_eq!(report[0].storage.len(), 2);
+        assert_eq!(report[1].storage[0].slot, word(3));
+        assert_eq!(report[2].storage[0].slot, word(4));
+    }
+
+    #[test]
+    fn test_storage_accesses_require_stack() {
+        let mut sload = log("SLOAD", 2_100, 1, &[]);
+        sload.stack = None;
+        let logs = vec![sload, log("STOP", 0, 1, &[])];
+
+        assert!(storage_accesses(&logs, Address::ZERO, None, &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_reverted_frames_are_dropped() {
+        let target = address!("00000000000000000000000000000000000000aa");
+        let callee = 0xbb;
+        let logs = vec![
+            log("SSTORE", 22_100, 1, &[1, 1]),
+            // The first call writes and reverts
+            log("CALL", 0, 1, &[0, 0, 0, 0, 0, callee, 100]),
+            log("SLOAD", 2_100, 2, &[2]),
+            log("DUP1", 3, 2, &[0]),
+            log("SSTORE", 2_900, 2, &[5, 2]),
+            log("REVERT", 0, 2, &[0, 0]),
+            log("POP", 2, 1, &[0]),
+            // The second call reads the slot again, which is cold as the first
+            // access was reverted
+            log("CALL", 0, 1, &[0, 0, 0, 0, 0, callee, 100]),
+            log("SLOAD", 2_100, 2, &[2]),
+            log("STOP", 0, 2, &[0]),
+            log("POP", 2, 1, &[1]),
+            log("STOP", 0, 1, &[]),
+        ];
+
+        let report = storage_accesses(&logs, target, None, &HashMap::new()).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].storage.len(), 1);
+
+        let callee_storage = &report[1].storage;
+        assert_eq!(report[1].address, "0x00000000000000000000000000000000000000bb");
+        assert_eq!(callee_storage.len(), 1);
+        assert_eq!(callee_storage[0].operation, "SLOAD");
+        assert_eq!(callee_storage[0].cold, Some(true));
+    }
+
+    #[test]
+    fn test_failed_transaction_has_no_accesses() {
+        let target = address!("00000000000000000000000000000000000000aa");
+        let logs = vec![log("SSTORE", 22_100, 1, &[1, 1]), log("REVERT", 0, 1, &[0, 0])];
+
+        let report = storage_accesses(&logs, target, None, &HashMap::new()).unwrap();
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_access_list_warms_slots() {
+        let target = address!("00000000000000000000000000000000000000aa");
+        let slot = B256::from(U256::from(1));
+        let access_list: AccessList =
+            vec![alloy_rpc_types_eth::AccessListItem { address: target, storage_keys: vec![slot] }]
+                .into();
+        let prestate = HashMap::from([((target, slot), B256::from(U256::from(3)))]);
+        let logs = vec![
+            log("SSTORE", 2_900, 1, &[7, 1]),
+            log("SLOAD", 100, 1, &[2]),
+            log("STOP", 0, 1, &[0]),
+        ];
+
+        let report = storage_accesses(&logs, target, Some(&access_list), &prestate).unwrap();
+        let storage = &report[0].storage;
+        assert_eq!(storage[0].cold, Some(false));
+        assert_eq!(storage[0].value.value_before, Some(word(3)));
+        assert_eq!(storage[1].cold, Some(true));
+    }
+}
@@ -0,0 +1,177 @@
+//! Storage slot discovery of ERC-20 balances and allowances.
+//!
+//! Token balances and allowances live in mappings whose slot index depends on
+//! the contract, and whose key hashing depends on the compiler: Solidity hashes
+//! `key . slot` while Vyper hashes `slot . key`. Instead of probing every
+//! candidate one call at a time, a single `eth_call` writes a distinct marker
+//! to the slot of every candidate (through a state override) and reads the
+//! balance or allowance back: the returned marker tells which candidate the
+//! token read.
+
+use alloy_primitives::{keccak256, map::B256HashMap, Address, Bytes, B256, U256};
+use alloy_rpc_types_eth::state::AccountOverride;
+use alloy_sol_types::{sol, SolCall};
+use serde::{Deserialize, Serialize};
+
+sol! {
+    function balanceOf(address owner) external view returns (uint256);
+    function allowance(address owner, address spender) external view returns (uint256);
+}
+
+/// Number of mapping indexes probed per layout.
+pub const MAX_MAPPING_INDEX: u64 = 32;
+
+/// High-order bytes of the probe markers, the low-order bytes hold the position
+/// of the candidate.
+const MARKER_PREFIX: U256 = U256::from_limbs([0, 0, 0, 0xa171_7ace_a171_7ace]);
+
+/// Key hashing of a storage mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MappingLayout {
+    /// `keccak256(key . slot)`.
+    Solidity,
+    /// `keccak256(slot . key)`.
+    Vyper,
+}
+
+/// Storage mapping of a token, identified by its layout and slot index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingSlot {
+    /// Key hashing of the mapping.
+    pub layout: MappingLayout,
+    /// Slot index of the mapping.
+    pub index: u64,
+}
+
+impl MappingSlot {
+    /// Slot of the value at `key` in a mapping stored at `slot`.
+    fn entry(&self, key: Address, slot: B256) -> B256 {
+        let key = key.into_word();
+        match self.layout {
+            MappingLayout::Solidity => keccak256([key, slot].concat()),
+            MappingLayout::Vyper => keccak256([slot, key].concat()),
+        }
+    }
+}
+
+/// Token value whose storage slot is looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenQuery {
+    /// `balanceOf(holder)`.
+    Balance { holder: Address },
+    /// `allowance(owner, spender)`.
+    Allowance { owner: Address, spender: Address },
+}
+
+impl TokenQuery {
+    /// Calldata reading the value.
+    pub fn calldata(&self) -> Bytes {
+        match *self {
+            Self::Balance { holder } => balanceOfCall { owner: holder }.abi_encode().into(),
+            Self::Allowance { owner, spender } => {
+                allowanceCall { owner, spender }.abi_encode().into()
+            }
+        }
+    }
+
+    /// Storage slot of the value in the given mapping.
+    pub fn slot(&self, mapping: MappingSlot) -> B256 {
+        let index = B256::from(U256::from(mapping.index));
+        match *self {
+            Self::Balance { holder } => mapping.entry(holder, index),
+            Self::Allowance { owner, spender } => {
+                mapping.entry(spender, mapping.entry(owner, index))
+            }
+        }
+    }
+
+    /// Cache key of the mapping of the value for `token`. The mapping does not
+    /// depend on the holder, so it is shared by all the queries of a token.
+    pub fn cache_key(&self, token: Address) -> String {
+        let kind = match self {
+            Self::Balance { .. } => "balance",
+            Self::Allowance { .. } => "allowance",
+        };
+        format!("token_slot:{:x}:{}", token, kind)
+    }
+}
+
+/// Mapping candidates, in probe order.
+pub fn candidates() -> impl Iterator<Item = MappingSlot> {
+    [MappingLayout::Solidity, MappingLayout::Vyper]
+        .into_iter()
+        .flat_map(|layout| (0..MAX_MAPPING_INDEX).map(move |index| MappingSlot { layout, index }))
+}
+
+/// State override of the token writing the marker of every candidate to the
+/// slot the candidate would read.
+pub fn probe_override(query: &TokenQuery) -> AccountOverride {
+    let state_diff: B256HashMap<B256> = candidates()
+        .enumerate()
+        .map(|(position, mapping)| (query.slot(mapping), marker(position)))
+        .collect();
+
+    AccountOverride { state_diff: Some(state_diff), ..Default::default() }
+}
+
+/// Returns the candidate whose marker was read by the probe call, if any.
+pub fn decode_probe(output: &[u8]) -> Option<MappingSlot> {
+    let value = U256::try_from_be_slice(output.get(..32)?)?;
+    let position = value.checked_sub(MARKER_PREFIX)?;
+    let position: usize = position.try_into().ok()?;
+    candidates().nth(position)
+}
+
+/// Marker written to the slot of the candidate at `position`.
+fn marker(position: usize) -> B256 {
+    B256::from(MARKER_PREFIX + U256::from(position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+
+    #[test]
+    fn test_solidity_slots() {
+        let holder = Address::ZERO;
+        let mapping = MappingSlot { layout: MappingLayout::Solidity, index: 0 };
+
+        // keccak256(abi.encode(address(0), uint256(0)))
+        assert_eq!(
+            TokenQuery::Balance { holder }.slot(mapping),
+            b256!("0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5")
+        );
+    }
+
+    #[test]
+    fn test_vyper_slots() {
+        let owner = Address::repeat_byte(0x11);
+        let spender = Address::repeat_byte(0x22);
+        let mapping = MappingSlot { layout: MappingLayout::Vyper, index: 3 };
+
+        let inner = keccak256([B256::from(U256::from(3)), owner.into_word()].concat());
+        assert_eq!(
+            TokenQuery::Allowance { owner, spender }.slot(mapping),
+            keccak256([inner, spender.into_word()].concat())
+        );
+    }
+
+    #[test]
+    fn test_probe_round_trip() {
+        let query = TokenQuery::Balance { holder: Address::repeat_byte(0x11) };
+        let probe = probe_override(&query);
+        let state_diff = probe.state_diff.unwrap();
+        assert_eq!(state_diff.len(), 2 * MAX_MAPPING_INDEX as usize);
+
+        // A Vyper token storing balances at index 5 reads this marker
+        let mapping = MappingSlot { layout: MappingLayout::Vyper, index: 5 };
+        let output = state_diff[&query.slot(mapping)];
+        assert_eq!(decode_probe(output.as_slice()), Some(mapping));
+
+        // Balances read from elsewhere
+        assert_eq!(decode_probe(B256::with_last_byte(1).as_slice()), None);
+        assert_eq!(decode_probe(&[]), None);
+    }
+}
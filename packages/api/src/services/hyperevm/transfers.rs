@@ -0,0 +1,253 @@
+//! Native value transfer tracking for simulations.
+//!
+//! When `traceTransfers` is enabled, `eth_simulateV1` reports every native value
+//! transfer as an ERC-20 like `Transfer` log emitted by [`NATIVE_TRANSFER_ADDRESS`].
+//! These synthetic logs are split from the regular call logs and turned into
+//! [`NativeTransfer`]s, which are then aggregated with the gas fees paid by the
+//! senders into per-account [`NativeBalanceChange`]s.
+
+use super::assets::{format_signed_diff, Transfer};
+use crate::handlers::simulation::response::{NativeBalanceChange, NativeTransfer};
+use alloy_primitives::{address, Address, U256};
+use alloy_rpc_types_eth::{
+    simulate::SimCallResult, Log, TransactionRequest as AlloyTransactionRequest,
+};
+use alloy_sol_types::SolEvent;
+
+/// Pseudo-token address used by `eth_simulateV1` for native transfers when
+/// `traceTransfers` is enabled.
+pub const NATIVE_TRANSFER_ADDRESS: Address = address!("0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+
+/// Returns whether the log is a synthetic native transfer log.
+pub fn is_native_transfer_log(log: &Log) -> bool {
+    let topics = log.topics();
+    log.address() == NATIVE_TRANSFER_ADDRESS &&
+        topics.len() == 3 &&
+        topics[0] == Transfer::SIGNATURE_HASH
+}
+
+/// Extracts the native transfers made by `call` from its simulation logs.
+///
+/// The node does not report frames that do not move value, so depths are
+/// inferred from the transfer chain: the transaction value itself is at depth
+/// 0, and a transfer sent by an account that previously received value at
+/// depth `n` is at depth `n + 1`. Transfers that cannot be attached to the
+/// chain are reported at depth 1.
+pub fn native_transfers(call: &AlloyTransactionRequest, logs: &[Log]) -> Vec<NativeTransfer> {
+    let root_from = call.from.unwrap_or_default();
+    let root_to = call.to.and_then(|kind| kind.to().copied());
+    let root_value = call.value.unwrap_or_default();
+
+    // Accounts that received value, with the depth at which they were called
+    let mut frames: Vec<(Address, u32)> = root_to.map(|to| (to, 0)).into_iter().collect();
+    let mut transfers = Vec::new();
+
+    for (from, to, value) in logs.iter().filter_map(decode_native_transfer) {
+        let is_root =
+            transfers.is_empty() && from == root_from && Some(to) == root_to && value == root_value;
+
+        let depth = if is_root {
+            0
+        } else {
+            frames
+                .iter()
+                .rev()
+                .find(|(account, _)| *account == from)
+                .map_or(1, |(_, depth)| depth + 1)
+        };
+
+        frames.push((to, depth));
+        transfers.push(NativeTransfer {
+            from: format!("0x{:x}", from),
+            to: format!("0x{:x}", to),
+            value: format!("0x{:x}", value),
+            depth,
+        });
+    }
+
+    transfers
+}
+
+/// Aggregates the native balance change of every account touched by the calls.
+///
+/// Each sender is charged `gas_used` at the effective gas price of its call (see
+/// [`effective_gas_price`]) on top of the value it sent.
+pub fn native_balance_changes<'a>(
+    calls: impl IntoIterator<Item = (&'a AlloyTransactionRequest, &'a SimCallResult)>,
+    base_fee: u64,
+) -> Vec<NativeBalanceChange> {
+    // (account, received, sent, gas fee) in first-touched order
+    let mut balances: Vec<(Address, U256, U256, U256)> = Vec::new();
+
+    for (call, result) in calls {
+        let fee = U256::from(result.gas_used) * U256::from(effective_gas_price(call, base_fee));
+        balance_entry(&mut balances, call.from.unwrap_or_default()).3 += fee;
+
+        for (from, to, value) in result.logs.iter().filter_map(decode_native_transfer) {
+            balance_entry(&mut balances, from).2 += value;
+            balance_entry(&mut balances, to).1 += value;
+        }
+    }
+
+    balances
+        .into_iter()
+        .map(|(account, received, sent, gas_fee)| NativeBalanceChange {
+            address: format!("0x{:x}", account),
+            diff: format_signed_diff(sent + gas_fee, received),
+            gas_fee: format!("0x{:x}", gas_fee),
+        })
+        .collect()
+}
+
+/// Price paid per unit of gas by a call: its `gasPrice` for legacy calls, else
+/// the base fee plus its priority fee, capped by its `maxFeePerGas`. Calls
+/// without fee fields pay the base fee.
+fn effective_gas_price(call: &AlloyTransactionRequest, base_fee: u64) -> u128 {
+    let base_fee = u128::from(base_fee);
+    if let Some(gas_price) = call.gas_price {
+        return gas_price;
+    }
+    let priority_fee = call.max_priority_fee_per_gas.unwrap_or_default();
+    let price = base_fee.saturating_add(priority_fee);
+    call.max_fee_per_gas
+        .map_or(price, |max_fee| price.min(max_fee))
+}
+
+fn balance_entry(
+    balances: &mut Vec<(Address, U256, U256, U256)>,
+    account: Address,
+) -> &mut (Address, U256, U256, U256) {
+    let index = match balances.iter().position(|(a, ..)| *a == account) {
+        Some(index) => index,
+        None => {
+            balances.push((account, U256::ZERO, U256::ZERO, U256::ZERO));
+            balances.len() - 1
+        }
+    };
+    &mut balances[index]
+}
+
+fn decode_native_transfer(log: &Log) -> Option<(Address, Address, U256)> {
+    if !is_native_transfer_log(log) {
+        return None;
+    }
+    let topics = log.topics();
+    let value = U256::try_from_be_slice(&log.data().data)?;
+    Some((Address::from_word(topics[1]), Address::from_word(topics[2]), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Bytes, Log as PrimitiveLog, LogData, TxKind};
+
+    fn native_log(from: Address, to: Address, value: u64) -> Log {
+        Log {
+            inner: PrimitiveLog {
+                address: NATIVE_TRANSFER_ADDRESS,
+                data: LogData::new_unchecked(
+                    vec![Transfer::SIGNATURE_HASH, from.into_word(), to.into_word()],
+                    U256::from(value).to_be_bytes_vec().into(),
+                ),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn call(from: Address, to: Address, value: u64) -> AlloyTransactionRequest {
+        AlloyTransactionRequest {
+            from: Some(from),
+            to: Some(TxKind::Call(to)),
+            value: Some(U256::from(value)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_native_transfer_depths() {
+        let sender = Address::repeat_byte(0x11);
+        let router = Address::repeat_byte(0x22);
+        let pool = Address::repeat_byte(0x33);
+        let recipient = Address::repeat_byte(0x44);
+
+        let logs = vec![
+            native_log(sender, router, 100),
+            native_log(router, pool, 60),
+            native_log(pool, recipient, 10),
+            native_log(recipient, sender, 1),
+        ];
+
+        let transfers = native_transfers(&call(sender, router, 100), &logs);
+        let depths: Vec<u32> = transfers.iter().map(|t| t.depth).collect();
+        assert_eq!(depths, vec![0, 1, 2, 3]);
+        assert_eq!(transfers[1].value, "0x3c");
+    }
+
+    #[test]
+    fn test_native_transfer_without_value_root() {
+        let sender = Address::repeat_byte(0x11);
+        let vault = Address::repeat_byte(0x22);
+        let unknown = Address::repeat_byte(0x33);
+
+        let logs = vec![native_log(vault, sender, 5), native_log(unknown, sender, 5)];
+        let transfers = native_transfers(&call(sender, vault, 0), &logs);
+
+        assert_eq!(transfers.iter().map(|t| t.depth).collect::<Vec<_>>(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_native_balance_changes_include_gas() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+
+        let request = call(sender, recipient, 1_000);
+        let result = SimCallResult {
+            return_data: Bytes::new(),
+            logs: vec![native_log(sender, recipient, 1_000)],
+            gas_used: 21_000,
+            status: true,
+            error: None,
+        };
+
+        let changes = native_balance_changes([(&request, &result)], 2);
+        assert_eq!(changes.len(), 2);
+
+        assert_eq!(changes[0].address, format!("0x{:x}", sender));
+        assert_eq!(changes[0].gas_fee, format!("0x{:x}", 42_000));
+        assert_eq!(changes[0].diff, format!("-0x{:x}", 43_000));
+
+        assert_eq!(changes[1].address, format!("0x{:x}", recipient));
+        assert_eq!(changes[1].gas_fee, "0x0");
+        assert_eq!(changes[1].diff, format!("0x{:x}", 1_000));
+    }
+
+    #[test]
+    fn test_native_balance_changes_include_priority_fee() {
+        let sender = Address::repeat_byte(0x11);
+        let recipient = Address::repeat_byte(0x22);
+        let result = SimCallResult {
+            return_data: Bytes::new(),
+            logs: vec![],
+            gas_used: 21_000,
+            status: true,
+            error: None,
+        };
+
+        // Base fee of 2 plus a tip of 3, under the max fee of 10
+        let request = AlloyTransactionRequest {
+            max_fee_per_gas: Some(10),
+            max_priority_fee_per_gas: Some(3),
+            ..call(sender, recipient, 0)
+        };
+        let changes = native_balance_changes([(&request, &result)], 2);
+        assert_eq!(changes[0].gas_fee, format!("0x{:x}", 21_000 * 5));
+
+        // The tip is capped by the max fee
+        let capped = AlloyTransactionRequest { max_fee_per_gas: Some(4), ..request };
+        assert_eq!(effective_gas_price(&capped, 2), 4);
+
+        // Legacy calls pay their gas price
+        let legacy = AlloyTransactionRequest { gas_price: Some(7), ..call(sender, recipient, 0) };
+        assert_eq!(effective_gas_price(&legacy, 2), 7);
+    }
+}
@@ -0,0 +1,316 @@
+//! Prometheus metrics of the API.
+//!
+//! Metrics are collected in a process-wide registry, see [`metrics`], and
+//! exposed in the Prometheus text format by the metrics endpoint.
+
+use crate::{error::RpcError, services::cache::CacheStatus};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{RpcError as AlloyRpcError, TransportError, TransportFut};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use std::{
+    future::Future,
+    sync::LazyLock,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+
+/// Prefix of the metric names.
+const NAMESPACE: &str = "altitrace";
+
+/// Metrics of the API.
+pub struct Metrics {
+    registry: Registry,
+    /// Handled requests, by endpoint, method and status.
+    http_requests: IntCounterVec,
+    /// Latency of the requests, by endpoint, method and status.
+    http_request_duration: HistogramVec,
+    /// Requests being handled.
+    http_requests_in_flight: IntGauge,
+    /// Latency of the upstream RPC calls, by JSON-RPC method.
+    rpc_request_duration: HistogramVec,
+    /// Failed upstream RPC calls, by JSON-RPC method and error code.
+    rpc_errors: IntCounterVec,
+    /// Latency of the Redis operations, by operation.
+    redis_duration: HistogramVec,
+    /// Failed Redis operations, by operation.
+    redis_errors: IntCounterVec,
+    /// Read-through cache lookups, by outcome (`HIT`, `MISS` or `BYPASS`).
+    cache_lookups: IntCounterVec,
+    /// Number of struct logs of the struct logger traces.
+    struct_log_size: Histogram,
+}
+
+/// Returns the metrics of the API.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let opts = |name: &str, help: &str| Opts::new(name, help).namespace(NAMESPACE);
+        let histogram_opts =
+            |name: &str, help: &str| HistogramOpts::new(name, help).namespace(NAMESPACE);
+
+        let http_requests = IntCounterVec::new(
+            opts("http_requests_total", "Handled requests"),
+            &["endpoint", "method", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            histogram_opts("http_request_duration_seconds", "Latency of the requests"),
+            &["endpoint", "method", "status"],
+        )
+        .unwrap();
+        let http_requests_in_flight =
+            IntGauge::with_opts(opts("http_requests_in_flight", "Requests being handled")).unwrap();
+        let rpc_request_duration = HistogramVec::new(
+            histogram_opts("rpc_request_duration_seconds", "Latency of the upstream RPC calls"),
+            &["method"],
+        )
+        .unwrap();
+        let rpc_errors = IntCounterVec::new(
+            opts("rpc_errors_total", "Failed upstream RPC calls"),
+            &["method", "error"],
+        )
+        .unwrap();
+        let redis_duration = HistogramVec::new(
+            histogram_opts("redis_operation_duration_seconds", "Latency of the Redis operations")
+                .buckets(exponential_buckets(0.0005, 2.0, 12).unwrap()),
+            &["operation"],
+        )
+        .unwrap();
+        let redis_errors = IntCounterVec::new(
+            opts("redis_errors_total", "Failed Redis operations"),
+            &["operation"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            opts("cache_lookups_total", "Read-through cache lookups"),
+            &["result"],
+        )
+        .unwrap();
+        let struct_log_size = Histogram::with_opts(
+            histogram_opts("struct_log_size", "Number of struct logs of the struct logger traces")
+                .buckets(exponential_buckets(10.0, 4.0, 10).unwrap()),
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(http_requests_in_flight.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(rpc_request_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(rpc_errors.clone())).unwrap();
+        registry.register(Box::new(redis_duration.clone())).unwrap();
+        registry.register(Box::new(redis_errors.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+        registry
+            .register(Box::new(struct_log_size.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            http_requests_in_flight,
+            rpc_request_duration,
+            rpc_errors,
+            redis_duration,
+            redis_errors,
+            cache_lookups,
+            struct_log_size,
+        }
+    }
+
+    /// Encodes the metrics in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        // Encoding to a buffer cannot fail
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Counts a request being handled until the returned guard is dropped.
+    pub fn track_request(&self) -> InFlightRequest {
+        self.http_requests_in_flight.inc();
+        InFlightRequest(self.http_requests_in_flight.clone())
+    }
+
+    /// Records a handled request.
+    pub fn record_request(&self, endpoint: &str, method: &str, status: u16, duration: Duration) {
+        let status = status.to_string();
+        let labels = [endpoint, method, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Records a read-through cache lookup.
+    pub fn record_cache_lookup(&self, status: CacheStatus) {
+        self.cache_lookups
+            .with_label_values(&[status.as_str()])
+            .inc();
+    }
+
+    /// Records the size of a struct logger trace.
+    pub fn record_struct_logs(&self, count: usize) {
+        self.struct_log_size.observe(count as f64);
+    }
+
+    /// Runs a Redis `operation`, recording its latency and whether it failed.
+    pub async fn redis<T, E>(
+        &self,
+        operation: &'static str,
+        future: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let timer = self
+            .redis_duration
+            .with_label_values(&[operation])
+            .start_timer();
+        let result = future.await;
+        timer.observe_duration();
+
+        if result.is_err() {
+            self.redis_errors.with_label_values(&[operation]).inc();
+        }
+        result
+    }
+}
+
+/// Request being handled, see [`Metrics::track_request`].
+pub struct InFlightRequest(IntGauge);
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Transport layer recording the latency and the errors of the RPC calls.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcMetricsLayer;
+
+impl<S> Layer<S> for RpcMetricsLayer {
+    type Service = RpcMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcMetrics { inner }
+    }
+}
+
+/// Transport recording the RPC metrics, see [`RpcMetricsLayer`].
+#[derive(Debug, Clone)]
+pub struct RpcMetrics<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for RpcMetrics<S>
+where
+    S: Service<
+        RequestPacket,
+        Response = ResponsePacket,
+        Error = TransportError,
+        Future = TransportFut<'static>,
+    >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let method = match &request {
+            RequestPacket::Single(request) => request.method().to_string(),
+            RequestPacket::Batch(_) => "batch".to_string(),
+        };
+        let start = Instant::now();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await;
+            let metrics = metrics();
+            metrics
+                .rpc_request_duration
+                .with_label_values(&[method.as_str()])
+                .observe(start.elapsed().as_secs_f64());
+
+            let record_error = |error: RpcError| {
+                metrics
+                    .rpc_errors
+                    .with_label_values(&[method.as_str(), error.error_code()])
+                    .inc();
+            };
+            match &response {
+                Ok(response) => {
+                    for payload in response.iter_errors() {
+                        record_error(RpcError::from(AlloyRpcError::ErrorResp(payload.clone())));
+                    }
+                }
+                Err(e) => record_error(RpcError::from(e)),
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_rpc_client::ClientBuilder;
+    use alloy_transport::mock::{Asserter, MockTransport};
+
+    #[tokio::test]
+    async fn test_rpc_metrics() {
+        let asserter = Asserter::new();
+        let client = ClientBuilder::default()
+            .layer(RpcMetricsLayer)
+            .transport(MockTransport::new(asserter.clone()), true);
+        let errors = |code: &str| {
+            metrics()
+                .rpc_errors
+                .with_label_values(&["eth_chainId", code])
+                .get()
+        };
+        let before = errors("RPC_INTERNAL_ERROR");
+
+        asserter.push_success(&"0x3e7");
+        let _: String = client.request_noparams("eth_chainId").await.unwrap();
+        asserter.push_failure(ErrorPayload::internal_error());
+        let _ = client
+            .request_noparams::<String>("eth_chainId")
+            .await
+            .unwrap_err();
+
+        assert_eq!(errors("RPC_INTERNAL_ERROR"), before + 1);
+        let encoded = metrics().encode();
+        assert!(encoded
+            .contains("altitrace_rpc_request_duration_seconds_count{method=\"eth_chainId\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_redis_metrics() {
+        let errors = || metrics().redis_errors.with_label_values(&["test"]).get();
+        let before = errors();
+
+        let _ = metrics().redis("test", async { Ok::<_, ()>(()) }).await;
+        let _ = metrics().redis("test", async { Err::<(), _>(()) }).await;
+        assert_eq!(errors(), before + 1);
+    }
+}
@@ -1,6 +1,11 @@
+pub mod api_keys;
 pub mod cache;
 pub use cache::*;
+pub mod decoder;
 pub mod hyperevm;
 pub use hyperevm::*;
+pub mod metrics;
 pub mod provider;
 pub use provider::*;
+pub mod telemetry;
+pub mod usage;
@@ -1,12 +1,14 @@
-use alloy_consensus::ReceiptEnvelope;
-use alloy_primitives::Log;
 use alloy_provider::{builder, DynProvider, Provider};
-use alloy_rpc_types::{Block, BlockId};
+use alloy_rpc_client::ClientBuilder;
+use alloy_rpc_types::{Block, BlockId, TransactionReceipt};
 use alloy_rpc_types_eth::BlockNumberOrTag;
 use alloy_transport_http::reqwest::Url;
 use alloy_transport_ws::WsConnect;
 
-use crate::error::ProviderError;
+use crate::{
+    error::ProviderError,
+    services::{metrics::RpcMetricsLayer, telemetry::RpcTracingLayer, usage::RpcCallCounterLayer},
+};
 
 /// RPC provider connection and request handling.
 #[derive(Debug, Clone)]
@@ -17,16 +19,25 @@ pub struct RpcProvider {
 impl RpcProvider {
     /// Creates a new [`RpcProvider`] instance from a given Url. Can handle websocket and http
     /// connection.
+    ///
+    /// The calls are counted in the usage of the requests making them, see
+    /// [`RpcCallCounterLayer`], and their latency and errors are recorded in the
+    /// metrics, see [`RpcMetricsLayer`]. Each call is traced in a span of its
+    /// own, see [`RpcTracingLayer`].
     pub async fn new(url: Url) -> eyre::Result<Self> {
+        let client = ClientBuilder::default()
+            .layer(RpcTracingLayer)
+            .layer(RpcCallCounterLayer)
+            .layer(RpcMetricsLayer);
         match url.scheme() {
             "http" | "https" => {
-                let http_provider = builder().connect_http(url).erased();
+                let http_provider = builder().connect_client(client.http(url)).erased();
                 Ok(Self { inner: http_provider })
             }
             "ws" | "wss" => {
                 let ws = WsConnect::new(url);
-                let ws_provider = builder().connect_ws(ws).await?.erased();
-                Ok(Self { inner: ws_provider.erased() })
+                let ws_provider = builder().connect_client(client.ws(ws).await?).erased();
+                Ok(Self { inner: ws_provider })
             }
             _ => Err(eyre::eyre!("Unsupported protocol: {}", url.scheme())),
         }
@@ -67,27 +78,17 @@ impl RpcProvider {
             .and_then(|opt| opt.ok_or(ProviderError::BlockNotFound(to_fetch)))
     }
 
-    #[allow(dead_code)]
-    async fn get_block_receipts(
+    pub async fn get_block_receipts(
         &self,
         number: u64,
-    ) -> Result<Vec<ReceiptEnvelope<Log>>, ProviderError> {
+    ) -> Result<Vec<TransactionReceipt>, ProviderError> {
         let to_fetch = BlockNumberOrTag::Number(number);
         let block_id = BlockId::Number(to_fetch);
 
-        let receipts = self
-            .inner
+        self.inner
             .get_block_receipts(block_id)
             .await
             .map_err(|e| ProviderError::fetch_error(to_fetch, e.to_string()))?
-            .ok_or(ProviderError::BlockNotFound(to_fetch))?;
-
-        Ok(receipts
-            .into_iter()
-            .map(|r| {
-                r.into_inner()
-                    .map_logs(alloy_rpc_types_eth::Log::into_inner)
-            })
-            .collect())
+            .ok_or(ProviderError::BlockNotFound(to_fetch))
     }
 }
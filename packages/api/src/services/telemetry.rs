@@ -0,0 +1,260 @@
+//! Request correlation and distributed tracing.
+//!
+//! Each request gets a span, continuing the trace of its W3C `traceparent`
+//! header, and its ID is the ID of that trace. The services and the upstream
+//! RPC calls open child spans, and the RPC calls carry the `traceparent` of
+//! their span, so that one ID finds the request in the logs, the responses
+//! and the traces of the API and its upstreams.
+//!
+//! Spans are exported to an OpenTelemetry collector if one is configured, see
+//! [`TelemetryConfig`](crate::config::TelemetryConfig).
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use alloy_transport_http::reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::{
+    propagation::{Injector, TextMapPropagator},
+    trace::TraceContextExt,
+    Context as OtelContext,
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::{
+    future::Future,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::{info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Runs `future` as the request `request_id`.
+pub fn with_request_id<F: Future>(
+    request_id: String,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    REQUEST_ID.scope(request_id, future)
+}
+
+/// ID of the current request, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// ID of the current request, or a new ID outside of a request.
+pub fn request_id() -> String {
+    current_request_id().unwrap_or_else(|| Uuid::new_v4().simple().to_string())
+}
+
+/// Request ID of a span: the ID of its trace, or of the trace of `parent` when
+/// spans are not recorded, or a new ID if neither is part of a trace.
+pub fn trace_request_id(span: &Span, parent: &OtelContext) -> String {
+    trace_id(&span.context())
+        .or_else(|| trace_id(parent))
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string())
+}
+
+/// ID of the trace of a context, if it is part of one.
+fn trace_id(context: &OtelContext) -> Option<String> {
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// Injects the W3C trace context of `span` into the `headers` of a request.
+fn inject_trace_context(span: &Span, headers: &mut HeaderMap) {
+    TraceContextPropagator::new().inject_context(&span.context(), &mut HeaderInjector(headers));
+}
+
+/// Sets the trace context fields in the headers of an upstream request.
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value))
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Transport layer opening a span for each RPC call, and propagating it to the
+/// node with the `traceparent` header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcTracingLayer;
+
+impl<S> Layer<S> for RpcTracingLayer {
+    type Service = RpcTracing<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcTracing { inner }
+    }
+}
+
+/// Transport tracing the RPC calls, see [`RpcTracingLayer`].
+#[derive(Debug, Clone)]
+pub struct RpcTracing<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for RpcTracing<S>
+where
+    S: Service<
+        RequestPacket,
+        Response = ResponsePacket,
+        Error = TransportError,
+        Future = TransportFut<'static>,
+    >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: RequestPacket) -> Self::Future {
+        let method = match &request {
+            RequestPacket::Single(request) => request.method().to_string(),
+            RequestPacket::Batch(_) => "batch".to_string(),
+        };
+        let span = info_span!(
+            target: "altitrace::rpc",
+            "rpc",
+            otel.name = %method,
+            otel.kind = "client",
+            rpc.system = "jsonrpc",
+            rpc.method = %method,
+            otel.status_code = tracing::field::Empty,
+        );
+
+        // Only single requests carry headers, see `RequestPacket::headers`
+        if let RequestPacket::Single(request) = &mut request {
+            let extensions = request.meta_mut().extensions_mut();
+            if extensions.get::<HeaderMap>().is_none() {
+                extensions.insert(HeaderMap::new());
+            }
+            if let Some(headers) = extensions.get_mut::<HeaderMap>() {
+                inject_trace_context(&span, headers);
+            }
+        }
+
+        let response = span.in_scope(|| self.inner.call(request));
+        Box::pin(
+            async move {
+                let response = response.await;
+                let failed = match &response {
+                    Ok(response) => response.iter_errors().next().is_some(),
+                    Err(_) => true,
+                };
+                if failed {
+                    Span::current().record("otel.status_code", "ERROR");
+                }
+                response
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_json_rpc::{Id, Request};
+    use alloy_transport::TransportErrorKind;
+    use opentelemetry::{propagation::Extractor, trace::TracerProvider};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    struct MapExtractor(HashMap<&'static str, &'static str>);
+
+    impl Extractor for MapExtractor {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).copied()
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().copied().collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        assert_eq!(current_request_id(), None);
+        assert_eq!(request_id().len(), 32);
+
+        let id =
+            with_request_id("4bf92f3577b34da6a3ce929d0e0e4736".to_string(), async { request_id() })
+                .await;
+        assert_eq!(id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[test]
+    fn test_trace_request_id() {
+        // Without a tracing layer, the ID comes from the incoming trace context
+        let parent = TraceContextPropagator::new()
+            .extract(&MapExtractor(HashMap::from([("traceparent", TRACEPARENT)])));
+        assert_eq!(trace_request_id(&Span::none(), &parent), "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        let id = trace_request_id(&Span::none(), &OtelContext::new());
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, trace_request_id(&Span::none(), &OtelContext::new()));
+    }
+
+    /// Transport capturing the headers of the requests.
+    #[derive(Clone, Default)]
+    struct CaptureHeaders(Arc<Mutex<Option<HeaderMap>>>);
+
+    impl Service<RequestPacket> for CaptureHeaders {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            *self.0.lock().unwrap() = Some(request.headers());
+            Box::pin(async { Err(TransportErrorKind::custom_str("captured")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_tracing() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let parent = info_span!("request");
+        let trace_id = parent.context().span().span_context().trace_id();
+
+        let capture = CaptureHeaders::default();
+        let request = Request::new("eth_chainId", Id::Number(1), ())
+            .serialize()
+            .unwrap();
+        let mut transport = RpcTracingLayer.layer(capture.clone());
+        let _ = parent
+            .in_scope(|| transport.call(RequestPacket::Single(request)))
+            .await;
+
+        // The RPC call continues the trace of its parent span, in a span of its own
+        let headers = capture.0.lock().unwrap().take().unwrap();
+        let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", trace_id)));
+    }
+}
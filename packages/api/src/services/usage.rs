@@ -0,0 +1,496 @@
+//! Usage metering of the API keys.
+//!
+//! Every request authenticated with an API key is counted in Redis by endpoint,
+//! along with the work it caused: simulated calls, gas simulated, trace bytes
+//! returned and upstream RPC calls. Counters are kept by key ID, so that a key
+//! created under the name of a revoked one starts afresh, and bucketed by hour
+//! and by day (UTC). They expire once they are older than what can be queried.
+//!
+//! The work of a request is collected while its handler runs (see [`metered`]),
+//! in counters local to the request task, so that the services and the RPC
+//! transport record it without threading the key through every call.
+
+use crate::{
+    config::RedisConfig,
+    error::ApiError,
+    services::{api_keys::ApiKey, metrics::metrics, RedisConnection},
+    ApiResult, CacheResult,
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportFut};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+/// Redis hash of the names of the keys with recorded usage, by key ID.
+const KEYS_KEY: &str = "usage:key_names";
+
+/// Prefix of the fields counting the requests of an endpoint.
+const REQUESTS_PREFIX: &str = "requests:";
+
+/// Counters of the work done for a request, local to its task.
+#[derive(Debug, Default)]
+struct RequestCounters {
+    simulated_calls: AtomicU64,
+    gas_used: AtomicU64,
+    rpc_calls: AtomicU64,
+}
+
+tokio::task_local! {
+    static REQUEST_COUNTERS: Arc<RequestCounters>;
+}
+
+/// Adds `value` to a counter of the current request, if it is metered.
+fn count(counter: impl FnOnce(&RequestCounters) -> &AtomicU64, value: u64) {
+    let _ =
+        REQUEST_COUNTERS.try_with(|counters| counter(counters).fetch_add(value, Ordering::Relaxed));
+}
+
+/// Records simulated calls and the gas they used for the current request.
+pub fn record_simulation(calls: usize, gas_used: u64) {
+    count(|counters| &counters.simulated_calls, calls as u64);
+    count(|counters| &counters.gas_used, gas_used);
+}
+
+/// Runs `future`, returning its output with the work it recorded.
+pub async fn metered<F: Future>(future: F) -> (F::Output, Usage) {
+    let counters = Arc::new(RequestCounters::default());
+    let output = REQUEST_COUNTERS.scope(counters.clone(), future).await;
+
+    let usage = Usage {
+        simulated_calls: counters.simulated_calls.load(Ordering::Relaxed),
+        gas_used: counters.gas_used.load(Ordering::Relaxed),
+        rpc_calls: counters.rpc_calls.load(Ordering::Relaxed),
+        ..Default::default()
+    };
+    (output, usage)
+}
+
+/// Transport layer counting the RPC calls of the metered requests. Every call of
+/// a batch counts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcCallCounterLayer;
+
+impl<S> Layer<S> for RpcCallCounterLayer {
+    type Service = RpcCallCounter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcCallCounter { inner }
+    }
+}
+
+/// Transport counting the RPC calls, see [`RpcCallCounterLayer`].
+#[derive(Debug, Clone)]
+pub struct RpcCallCounter<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for RpcCallCounter<S>
+where
+    S: Service<
+        RequestPacket,
+        Response = ResponsePacket,
+        Error = TransportError,
+        Future = TransportFut<'static>,
+    >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        count(|counters| &counters.rpc_calls, request.len() as u64);
+        self.inner.call(request)
+    }
+}
+
+/// Usage of an API key over a period.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    /// Number of requests, by endpoint (e.g. `/trace/tx`).
+    pub requests: BTreeMap<String, u64>,
+    /// Number of simulated calls.
+    pub simulated_calls: u64,
+    /// Total gas used by the simulated calls.
+    pub gas_used: u64,
+    /// Size of the returned traces, in bytes.
+    pub trace_bytes: u64,
+    /// Number of calls made to the upstream RPC.
+    pub rpc_calls: u64,
+}
+
+impl Usage {
+    /// Counters stored in Redis, as hash fields.
+    fn fields(&self) -> Vec<(String, u64)> {
+        let mut fields: Vec<_> = self
+            .requests
+            .iter()
+            .map(|(endpoint, count)| (format!("{}{}", REQUESTS_PREFIX, endpoint), *count))
+            .collect();
+        fields.extend([
+            ("simulated_calls".to_string(), self.simulated_calls),
+            ("gas_used".to_string(), self.gas_used),
+            ("trace_bytes".to_string(), self.trace_bytes),
+            ("rpc_calls".to_string(), self.rpc_calls),
+        ]);
+        fields.retain(|(_, value)| *value > 0);
+        fields
+    }
+
+    /// Reads the counters stored by [`Self::fields`].
+    fn from_fields(fields: HashMap<String, u64>) -> Self {
+        let mut usage = Self::default();
+        for (field, value) in fields {
+            match field.as_str() {
+                "simulated_calls" => usage.simulated_calls = value,
+                "gas_used" => usage.gas_used = value,
+                "trace_bytes" => usage.trace_bytes = value,
+                "rpc_calls" => usage.rpc_calls = value,
+                _ => {
+                    if let Some(endpoint) = field.strip_prefix(REQUESTS_PREFIX) {
+                        usage.requests.insert(endpoint.to_string(), value);
+                    }
+                }
+            }
+        }
+        usage
+    }
+
+    /// Adds the counters of `other`.
+    fn add(&mut self, other: &Self) {
+        for (endpoint, count) in &other.requests {
+            *self.requests.entry(endpoint.clone()).or_default() += count;
+        }
+        self.simulated_calls += other.simulated_calls;
+        self.gas_used += other.gas_used;
+        self.trace_bytes += other.trace_bytes;
+        self.rpc_calls += other.rpc_calls;
+    }
+}
+
+/// Period covered by a usage bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Hour,
+    #[default]
+    Day,
+}
+
+impl Granularity {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    /// Length of a bucket.
+    const fn period(&self) -> TimeDelta {
+        match self {
+            Self::Hour => TimeDelta::hours(1),
+            Self::Day => TimeDelta::days(1),
+        }
+    }
+
+    /// Number of buckets that can be queried, as long as they are kept.
+    const fn retention(&self) -> u32 {
+        match self {
+            // A week
+            Self::Hour => 168,
+            Self::Day => 90,
+        }
+    }
+
+    /// Number of buckets returned by default.
+    const fn default_periods(&self) -> u32 {
+        match self {
+            Self::Hour => 24,
+            Self::Day => 7,
+        }
+    }
+
+    /// Start of the bucket containing `time`.
+    fn bucket_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        time.duration_trunc(self.period()).unwrap_or(time)
+    }
+
+    /// Redis key of the bucket of the key `key_id` starting at `start`.
+    fn bucket_key(&self, key_id: &str, start: DateTime<Utc>) -> String {
+        let start = match self {
+            Self::Hour => start.format("%Y-%m-%dT%H"),
+            Self::Day => start.format("%Y-%m-%d"),
+        };
+        format!("usage:{}:{}:{}", key_id, self.as_str(), start)
+    }
+}
+
+/// Period of usage to return.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageQuery {
+    /// Period covered by each bucket, `hour` or `day` (default).
+    #[serde(default)]
+    pub granularity: Granularity,
+    /// Number of buckets to return, up to the current one: 24 hours or 7 days by
+    /// default, and at most 168 hours or 90 days.
+    pub periods: Option<u32>,
+}
+
+impl UsageQuery {
+    /// Number of buckets to return.
+    pub fn periods(&self) -> ApiResult<u32> {
+        let retention = self.granularity.retention();
+        match self
+            .periods
+            .unwrap_or_else(|| self.granularity.default_periods())
+        {
+            periods @ 1.. if periods <= retention => Ok(periods),
+            periods => Err(ApiError::bad_request(format!(
+                "periods must be between 1 and {} for a {} granularity, got {}",
+                retention,
+                self.granularity.as_str(),
+                periods
+            ))),
+        }
+    }
+}
+
+/// Usage of an API key during a period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// Start of the period.
+    pub start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// Usage of an API key over the requested periods.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyUsage {
+    /// Unique ID of the key.
+    pub id: String,
+    /// Name of the key.
+    pub name: String,
+    pub granularity: Granularity,
+    /// Usage over all the periods.
+    pub total: Usage,
+    /// Usage of each period, oldest first.
+    pub buckets: Vec<UsageBucket>,
+}
+
+impl KeyUsage {
+    fn new(id: String, name: String, granularity: Granularity, buckets: Vec<UsageBucket>) -> Self {
+        let mut total = Usage::default();
+        for bucket in &buckets {
+            total.add(&bucket.usage);
+        }
+        Self { id, name, granularity, total, buckets }
+    }
+}
+
+/// Usage counters of the API keys, stored in Redis.
+pub struct UsageMeter {
+    conn: RedisConnection,
+}
+
+impl UsageMeter {
+    pub const fn new(conn: RedisConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Creates the meter from the configuration, `None` if the Redis client
+    /// cannot be created (e.g. invalid URL).
+    pub(crate) fn from_config(redis: &RedisConfig) -> Option<Self> {
+        match RedisConnection::open(redis.url.as_str()) {
+            Ok(conn) => Some(Self::new(conn)),
+            Err(e) => {
+                warn!(target: "altitrace::api::usage", error = %e, "Invalid Redis configuration, usage metering disabled");
+                None
+            }
+        }
+    }
+
+    /// Adds `usage` to the current hourly and daily buckets of `key`.
+    pub async fn record(&self, key: &ApiKey, usage: &Usage) -> CacheResult<()> {
+        let fields = usage.fields();
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let mut pipe = redis::pipe();
+        for granularity in [Granularity::Hour, Granularity::Day] {
+            let start = granularity.bucket_start(now);
+            let bucket = granularity.bucket_key(&key.id, start);
+            for (field, value) in &fields {
+                pipe.hincr(&bucket, field, *value).ignore();
+            }
+            // Keep the bucket as long as it can be queried
+            let expires_at = start + granularity.period() * (granularity.retention() as i32 + 1);
+            pipe.expire_at(&bucket, expires_at.timestamp()).ignore();
+        }
+        pipe.hset(KEYS_KEY, &key.id, &key.name).ignore();
+
+        metrics()
+            .redis("usage_record", self.conn.run(async |conn| pipe.exec_async(conn).await))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the usage of the key `key_id`, named `name`, over the last
+    /// `periods` buckets.
+    pub async fn usage(
+        &self,
+        key_id: &str,
+        name: &str,
+        granularity: Granularity,
+        periods: u32,
+    ) -> CacheResult<KeyUsage> {
+        let current = granularity.bucket_start(Utc::now());
+        let starts: Vec<_> = (0..periods)
+            .rev()
+            .map(|ago| current - granularity.period() * ago as i32)
+            .collect();
+
+        let mut pipe = redis::pipe();
+        for start in &starts {
+            pipe.hgetall(granularity.bucket_key(key_id, *start));
+        }
+        let counters: Vec<HashMap<String, u64>> = self
+            .conn
+            .run(async |conn| pipe.query_async(conn).await)
+            .await?;
+
+        let buckets = starts
+            .into_iter()
+            .zip(counters)
+            .map(|(start, fields)| UsageBucket { start, usage: Usage::from_fields(fields) })
+            .collect();
+        Ok(KeyUsage::new(key_id.to_string(), name.to_string(), granularity, buckets))
+    }
+
+    /// Returns the usage of every key with recorded usage, sorted by name.
+    pub async fn all_usage(
+        &self,
+        granularity: Granularity,
+        periods: u32,
+    ) -> CacheResult<Vec<KeyUsage>> {
+        let names: HashMap<String, String> = self
+            .conn
+            .run(async |conn| conn.hgetall(KEYS_KEY).await)
+            .await?;
+        let mut keys: Vec<_> = names.into_iter().collect();
+        keys.sort_by(|(a_id, a_name), (b_id, b_name)| (a_name, a_id).cmp(&(b_name, b_id)));
+
+        let mut usage = Vec::with_capacity(keys.len());
+        for (id, name) in keys {
+            usage.push(self.usage(&id, &name, granularity, periods).await?);
+        }
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rpc_client::ClientBuilder;
+    use alloy_transport::mock::{Asserter, MockTransport};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_fields() {
+        let usage = Usage {
+            requests: BTreeMap::from([("/trace/tx".to_string(), 2), ("/simulate".to_string(), 1)]),
+            simulated_calls: 3,
+            gas_used: 21_000,
+            trace_bytes: 0,
+            rpc_calls: 4,
+        };
+
+        let fields = usage.fields();
+        assert!(fields.contains(&("requests:/trace/tx".to_string(), 2)));
+        assert!(!fields.iter().any(|(field, _)| field == "trace_bytes"));
+        assert_eq!(Usage::from_fields(fields.into_iter().collect()), usage);
+
+        let mut total = usage.clone();
+        total.add(&usage);
+        assert_eq!(total.requests["/trace/tx"], 4);
+        assert_eq!(total.gas_used, 42_000);
+    }
+
+    #[test]
+    fn test_buckets() {
+        let time = Utc.with_ymd_and_hms(2025, 3, 14, 15, 9, 26).unwrap();
+
+        let start = Granularity::Hour.bucket_start(time);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 3, 14, 15, 0, 0).unwrap());
+        assert_eq!(
+            Granularity::Hour.bucket_key("config:partner", start),
+            "usage:config:partner:hour:2025-03-14T15"
+        );
+
+        let start = Granularity::Day.bucket_start(time);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 3, 14, 0, 0, 0).unwrap());
+        assert_eq!(
+            Granularity::Day.bucket_key("config:partner", start),
+            "usage:config:partner:day:2025-03-14"
+        );
+    }
+
+    #[test]
+    fn test_query_periods() {
+        let query = |granularity, periods| UsageQuery { granularity, periods };
+        assert_eq!(query(Granularity::Day, None).periods().unwrap(), 7);
+        assert_eq!(query(Granularity::Hour, None).periods().unwrap(), 24);
+        assert_eq!(query(Granularity::Hour, Some(168)).periods().unwrap(), 168);
+        assert!(query(Granularity::Day, Some(91)).periods().is_err());
+        assert!(query(Granularity::Day, Some(0)).periods().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metered() {
+        let asserter = Asserter::new();
+        let client = ClientBuilder::default()
+            .layer(RpcCallCounterLayer)
+            .transport(MockTransport::new(asserter.clone()), true);
+
+        let request = || async {
+            asserter.push_success(&"0x1");
+            let _: String = client.request_noparams("eth_blockNumber").await.unwrap();
+            record_simulation(2, 42_000);
+        };
+
+        let ((), usage) = metered(async {
+            request().await;
+            request().await;
+        })
+        .await;
+        assert_eq!(usage.rpc_calls, 2);
+        assert_eq!(usage.simulated_calls, 4);
+        assert_eq!(usage.gas_used, 84_000);
+
+        // Nothing is recorded outside of a metered request
+        request().await;
+    }
+}
@@ -1,6 +1,14 @@
 use std::{fmt, path::PathBuf, str::FromStr};
 
+use crate::config::TelemetryConfig;
+
 use clap::{Args, ValueEnum};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
 use rolling_file::{RollingConditionBasic, RollingFileAppender as RollingFileAppenderLib};
 use tracing::{level_filters::LevelFilter, Subscriber};
 use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
@@ -179,38 +187,90 @@ fn create_file_layer<S: Subscriber + for<'a> LookupSpan<'a>>(
     (layer, guard)
 }
 
+fn create_otel_layer<S: Subscriber + Send + Sync + for<'a> LookupSpan<'a>>(
+    config: &TelemetryConfig,
+    endpoint: &str,
+    base_default_directive: Directive,
+) -> Result<(BoxedLayer<S>, SdkTracerProvider), ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    // Continue the sampling decision of the incoming `traceparent` headers
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio)));
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(sampler)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    let env_filter = build_common_env_filter(base_default_directive);
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(config.service_name.clone()))
+        .with_filter(env_filter)
+        .boxed();
+    Ok((layer, provider))
+}
+
+/// Guard of the tracing system, returned by [`init_tracing`].
+///
+/// It must be kept alive for the duration of the application. When it is dropped, the logs are
+/// flushed to the log file and the pending spans are exported.
+#[must_use]
+pub(crate) struct TracingGuard {
+    _file_guard: Option<FileWorkerGuard>,
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to export the pending spans: {e}");
+            }
+        }
+    }
+}
+
 /// Initializes the tracing system.
 ///
 /// If `file_log_config` is `Some`, sets up file logging according to `log_format` from `LogsArgs`
 /// and stdout logging according to `stdout_log_format`.
 /// If `file_log_config` is `None` (e.g. for `db` command), sets up stdout logging only,
 /// with `LogFormat::Text` and a default `RUST_LOG` level.
+/// If `telemetry` has an OTLP endpoint, the spans are also exported to it.
 ///
 /// # Returns
-/// A `Result` containing an `Option<FileWorkerGuard>` if successful, or an error.
-/// The guard must be kept alive for the duration of the application to ensure logs are flushed.
+/// A `Result` containing a [`TracingGuard`] if successful, or an error.
+/// The guard must be kept alive for the duration of the application to ensure logs and spans are
+/// flushed.
 pub(crate) fn init_tracing(
     stdout_log_format: LogFormat,
     file_log_config: Option<(LogFormat, LogFileConfig)>,
-) -> Result<Option<FileWorkerGuard>, tracing_subscriber::util::TryInitError> {
+    telemetry: &TelemetryConfig,
+) -> eyre::Result<TracingGuard> {
     let rust_log_env = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
     let base_default_directive =
         Directive::from_str(&rust_log_env).unwrap_or_else(|_| Directive::from_str("info").unwrap());
 
-    let stdout_layer = create_stdout_layer(stdout_log_format, base_default_directive);
-
-    if let Some((file_format, config)) = file_log_config {
-        let (file_layer, file_guard) = create_file_layer(file_format, &config);
-
-        tracing_subscriber::registry()
-            .with(stdout_layer)
-            .with(file_layer)
-            .try_init()?;
-        Ok(Some(file_guard))
-    } else {
-        tracing_subscriber::registry()
-            .with(stdout_layer)
-            .try_init()?;
-        Ok(None)
-    }
+    let stdout_layer = create_stdout_layer(stdout_log_format, base_default_directive.clone());
+    let (file_layer, file_guard) = file_log_config
+        .map(|(file_format, config)| create_file_layer(file_format, &config))
+        .unzip();
+    let (otel_layer, tracer_provider) = telemetry
+        .otlp_endpoint()
+        .map(|endpoint| create_otel_layer(telemetry, endpoint, base_default_directive))
+        .transpose()?
+        .unzip();
+
+    tracing_subscriber::registry()
+        .with(stdout_layer)
+        .with(file_layer)
+        .with(otel_layer)
+        .try_init()?;
+    Ok(TracingGuard { _file_guard: file_guard, tracer_provider })
 }
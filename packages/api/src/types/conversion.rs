@@ -70,6 +70,13 @@ impl ConversionService {
             tx.gas = Some(gas_u64);
         }
 
+        tx.nonce = call.nonce;
+
+        if let Some(max_fee) = &call.max_fee_per_gas {
+            tx.max_fee_per_gas =
+                Some(alloy_primitives::U256::from_hex_string(max_fee)?.saturating_to());
+        }
+
         Ok(tx)
     }
 
@@ -7,11 +7,13 @@
 pub mod conversion;
 pub mod primitives;
 pub mod shared;
+pub mod storage_layout;
 pub mod trace;
 pub mod transaction;
 
 pub use conversion::*;
 pub use primitives::*;
 pub use shared::*;
+pub use storage_layout::*;
 pub use trace::*;
 pub use transaction::*;
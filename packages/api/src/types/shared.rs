@@ -3,16 +3,27 @@
 //! This module contains common type definitions that are used by multiple
 //! parts of the application to avoid duplication and ensure consistency.
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
+use alloy_json_abi::JsonAbi;
+use alloy_primitives::B256;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use validator::Validate;
 
-use crate::handlers::validation::{validate_address, validate_bytes32, validate_uint256};
+use crate::{
+    handlers::{
+        simulation::response::DecodedEvent,
+        validation::{validate_address, validate_bytes32, validate_uint256},
+    },
+    types::storage_layout::{SlotWrite, StorageLayout, StorageLayoutError},
+};
 
 /// State override for simulation and tracing.
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StateOverride {
     /// Account balance override (hex-encoded wei).
@@ -53,6 +64,17 @@ pub struct StateOverride {
     #[serde(skip_serializing_if = "Option::is_none", rename = "movePrecompileToAddress")]
     #[validate(custom(function = "crate::handlers::validation::validate_address"))]
     pub move_precompile_to_address: Option<String>,
+    /// Solidity storage layout of the contract (`storageLayout` output of solc).
+    /// Required to resolve `storageVariables`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub storage_layout: Option<StorageLayout>,
+
+    /// Storage variable overrides keyed by variable path (e.g. `balances[0xabc...]`,
+    /// `config.fee`, `owners[2]`), resolved to storage slots with the storage layout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!({"balances[0x742d35Cc6634C0532925a3b844Bc9e7595f06e8c]": "1000000"}))]
+    pub storage_variables: Option<BTreeMap<String, String>>,
 }
 
 /// Block environment overrides for simulation.
@@ -148,6 +170,11 @@ pub struct StorageSlotAccess<T> {
     /// Gas cost for this access.
     pub gas_cost: u64,
 
+    /// Whether the slot was cold (first access in the transaction, EIP-2929).
+    /// Not set for transient storage, which has no warm/cold pricing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cold: Option<bool>,
+
     /// Operation that accessed this slot.
     #[schema(example = "SSTORE")]
     pub operation: String,
@@ -179,12 +206,15 @@ pub struct SimpleStorageValue {
     pub value: String,
 }
 
+/// User-supplied JSON ABIs keyed by contract address.
+pub type ContractAbis = HashMap<String, JsonAbi>;
+
 /// Type aliases for specific storage access types.
 pub type RegularStorageSlotAccess = StorageSlotAccess<StorageValue>;
 pub type TransientSlotAccess = StorageSlotAccess<SimpleStorageValue>;
 
 /// Generic gas breakdown structure that can work with different field types.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GasBreakdown<T = u64> {
     /// Intrinsic transaction cost (21,000 gas base cost).
@@ -213,10 +243,13 @@ pub struct GasBreakdown<T = u64> {
 
     /// Gas cost for access list (EIP-2930).
     pub access_list: T,
+
+    /// Gas used that the execution does not explain, e.g. the EIP-7623 calldata floor.
+    pub unexplained: T,
 }
 
 /// Storage operation gas usage breakdown with generic field types.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StorageGasBreakdown<T = u64> {
     /// Gas used for storage read operations (SLOAD).
@@ -249,6 +282,10 @@ pub struct LogEntry {
     /// Log data (hex-encoded).
     #[schema(example = "0x00000000000000000000000000000000000000000000000000000000000f4240")]
     pub data: String,
+
+    /// Human-readable decoded event information (if available).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedEvent>,
 }
 
 /// Extended log entry with additional trace context.
@@ -285,6 +322,7 @@ impl Default for GasBreakdown<u64> {
             creates: 0,
             refund: 0,
             access_list: 0,
+            unexplained: 0,
         }
     }
 }
@@ -309,9 +347,28 @@ impl GasBreakdown<u64> {
             creates: self.creates.to_string(),
             refund: self.refund.to_string(),
             access_list: self.access_list.to_string(),
+            unexplained: self.unexplained.to_string(),
         }
     }
 
+    /// Total gas used, net of the refund.
+    ///
+    /// The storage initialization, modifications and cleanup refund are subsets of
+    /// the writes and refund and are not counted.
+    pub const fn total(&self) -> u64 {
+        (self.intrinsic +
+            self.access_list +
+            self.computation +
+            self.memory +
+            self.logs +
+            self.calls +
+            self.creates +
+            self.storage.reads +
+            self.storage.writes +
+            self.unexplained)
+            .saturating_sub(self.refund)
+    }
+
     /// Convert to hex-based gas breakdown.
     pub fn to_hex_breakdown(&self) -> GasBreakdown<String> {
         GasBreakdown {
@@ -324,6 +381,7 @@ impl GasBreakdown<u64> {
             creates: format!("0x{:x}", self.creates),
             refund: format!("0x{:x}", self.refund),
             access_list: format!("0x{:x}", self.access_list),
+            unexplained: format!("0x{:x}", self.unexplained),
         }
     }
 }
@@ -361,4 +419,70 @@ impl StateOverride {
         }
         Ok(())
     }
+
+    /// Resolves the storage variables to the slot writes assigning them.
+    pub fn storage_variable_writes(&self) -> Result<Vec<SlotWrite>, StorageLayoutError> {
+        let Some(variables) = &self.storage_variables else {
+            return Ok(Vec::new());
+        };
+        let layout = self
+            .storage_layout
+            .as_ref()
+            .ok_or(StorageLayoutError::MissingLayout)?;
+
+        let mut writes = Vec::new();
+        for (path, value) in variables {
+            writes.extend(layout.resolve(path, value)?);
+        }
+        Ok(writes)
+    }
+
+    /// Value of a slot set by the storage overrides, zero for the slots left
+    /// unset by a complete storage override, `None` if the slot is not overridden.
+    pub fn overridden_slot(&self, slot: B256) -> Option<B256> {
+        let find = |key: &String, value: &String| {
+            (B256::from_str(key).ok() == Some(slot))
+                .then(|| B256::from_str(value).ok())
+                .flatten()
+        };
+
+        if let Some(state) = &self.state {
+            return Some(
+                state
+                    .iter()
+                    .find_map(|entry| find(&entry.slot, &entry.value))
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(storage) = &self.storage {
+            return Some(
+                storage
+                    .iter()
+                    .find_map(|(key, value)| find(key, value))
+                    .unwrap_or_default(),
+            );
+        }
+        self.state_diff
+            .as_ref()
+            .and_then(|state_diff| state_diff.iter().find_map(|(key, value)| find(key, value)))
+    }
+
+    /// Sets the value of a slot in the storage overrides, replacing the existing
+    /// value of the slot if any.
+    pub fn set_slot(&mut self, slot: B256, value: B256) {
+        let same_slot = |key: &str| B256::from_str(key).ok() == Some(slot);
+        let (slot, value) = (slot.to_string(), value.to_string());
+
+        if let Some(state) = &mut self.state {
+            state.retain(|entry| !same_slot(&entry.slot));
+            state.push(StorageSlot { slot, value });
+        } else if let Some(storage) = &mut self.storage {
+            storage.retain(|key, _| !same_slot(key));
+            storage.insert(slot, value);
+        } else {
+            let state_diff = self.state_diff.get_or_insert_default();
+            state_diff.retain(|key, _| !same_slot(key));
+            state_diff.insert(slot, value);
+        }
+    }
 }
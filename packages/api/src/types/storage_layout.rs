@@ -0,0 +1,768 @@
+//! Solidity storage layouts.
+//!
+//! Resolves storage variable paths such as `balances[0xabc...]`, `config.fee` or
+//! `owners[2]` to the storage slots they occupy, using the `storageLayout` output
+//! of solc, and encodes the values assigned to them. Variables smaller than a
+//! slot are packed with their neighbours, so a resolved write only covers a
+//! byte range of its slot (see [`SlotWrite`]).
+
+use std::{collections::HashMap, str::FromStr};
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, I256, U256};
+use serde::{Deserialize, Serialize};
+
+/// Error type for storage layout resolution.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum StorageLayoutError {
+    #[error("Invalid variable path '{0}'")]
+    InvalidPath(String),
+    #[error("Unknown variable '{0}'")]
+    UnknownVariable(String),
+    #[error("Unknown type '{0}'")]
+    UnknownType(String),
+    #[error("Cannot access '{access}' on type '{label}'")]
+    InvalidAccess { access: String, label: String },
+    #[error("Invalid value '{value}' for type '{label}'")]
+    InvalidValue { value: String, label: String },
+    #[error("Cannot assign a value to type '{0}'")]
+    NotAssignable(String),
+    #[error("Storage variables require a storage layout")]
+    MissingLayout,
+    #[error("Invalid storage layout: {0}")]
+    InvalidLayout(String),
+}
+
+/// Storage layout of a contract, as output by solc (`storageLayout`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageLayout {
+    /// State variables of the contract.
+    pub storage: Vec<StorageEntry>,
+
+    /// Types of the state variables, keyed by type identifier.
+    #[serde(default)]
+    pub types: Option<HashMap<String, StorageType>>,
+}
+
+/// State variable or struct member of a storage layout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageEntry {
+    /// Name of the variable.
+    pub label: String,
+
+    /// Byte offset of the variable in its slot, from the lowest-order byte.
+    pub offset: usize,
+
+    /// Slot of the variable (decimal), relative to its struct for members.
+    pub slot: String,
+
+    /// Type identifier of the variable.
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+/// Type of a storage layout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    /// How the type is stored.
+    pub encoding: StorageEncoding,
+
+    /// Canonical name of the type (e.g. `uint256`, `struct Config`).
+    pub label: String,
+
+    /// Number of bytes used by the type (decimal).
+    pub number_of_bytes: String,
+
+    /// Key type of a mapping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Value type of a mapping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+
+    /// Element type of an array.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+
+    /// Members of a struct.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<StorageEntry>>,
+}
+
+/// Storage encoding of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageEncoding {
+    /// Laid out contiguously from the slot of the variable.
+    Inplace,
+    /// Values stored at `keccak256(key . slot)`.
+    Mapping,
+    /// Length at the slot, elements from `keccak256(slot)`.
+    DynamicArray,
+    /// `bytes` and `string`, stored in the slot if shorter than 32 bytes.
+    Bytes,
+}
+
+/// Write of a value to a byte range of a storage slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotWrite {
+    /// Slot written to.
+    pub slot: B256,
+
+    /// Byte offset of the value in the slot, from the lowest-order byte.
+    pub offset: usize,
+
+    /// Big-endian value, `offset + data.len()` is at most 32 bytes.
+    pub data: Vec<u8>,
+}
+
+impl SlotWrite {
+    /// Returns whether the write replaces the whole slot.
+    pub const fn is_full_slot(&self) -> bool {
+        self.data.len() == 32
+    }
+
+    /// Applies the write to the current value of the slot.
+    pub fn apply(&self, mut word: B256) -> B256 {
+        let end = 32 - self.offset;
+        word[end - self.data.len()..end].copy_from_slice(&self.data);
+        word
+    }
+}
+
+/// Step of a variable path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access<'a> {
+    /// `.member` of a struct.
+    Member(&'a str),
+    /// `[key]` of a mapping or `[index]` of an array.
+    Index(&'a str),
+}
+
+/// Storage location of a variable.
+struct Location<'a> {
+    slot: U256,
+    offset: usize,
+    ty: &'a StorageType,
+}
+
+impl StorageLayout {
+    /// Resolves the variable at `path` and encodes `value` into the slot writes
+    /// assigning it.
+    ///
+    /// Only value types, `bytes` and `string` can be assigned. Array lengths are
+    /// left untouched, so elements past the end of a dynamic array must be
+    /// assigned along with its length.
+    pub fn resolve(&self, path: &str, value: &str) -> Result<Vec<SlotWrite>, StorageLayoutError> {
+        self.validate()?;
+        let location = self.locate(path)?;
+        let ty = location.ty;
+
+        match ty.encoding {
+            StorageEncoding::Inplace if ty.is_value() => {
+                let size = ty.size()?;
+                let word = encode_word(&ty.label, value, size)?;
+                let data = if is_fixed_bytes(&ty.label) {
+                    word[..size].to_vec()
+                } else {
+                    word[32 - size..].to_vec()
+                };
+                Ok(vec![SlotWrite {
+                    slot: B256::from(location.slot),
+                    offset: location.offset,
+                    data,
+                }])
+            }
+            StorageEncoding::Bytes => Ok(encode_bytes(location.slot, &ty.label, value)?),
+            _ => Err(StorageLayoutError::NotAssignable(ty.label.clone())),
+        }
+    }
+
+    /// Checks that the value types fit in a slot, at the offset of every variable
+    /// and struct member of these types.
+    pub fn validate(&self) -> Result<(), StorageLayoutError> {
+        let types = self.types.iter().flat_map(HashMap::values);
+        for ty in types.clone().filter(|ty| ty.is_value()) {
+            let size = ty.size()?;
+            if size > 32 {
+                return Err(StorageLayoutError::InvalidLayout(format!(
+                    "type '{}' of {} bytes does not fit in a slot",
+                    ty.label, size
+                )));
+            }
+        }
+
+        let members = types.flat_map(|ty| ty.members.iter().flatten());
+        for entry in self.storage.iter().chain(members) {
+            // Unknown types are reported when the variable is accessed
+            let Ok(ty) = self.ty(&entry.type_id) else {
+                continue;
+            };
+            if ty.is_value() && entry.offset > 32 - ty.size()? {
+                return Err(StorageLayoutError::InvalidLayout(format!(
+                    "'{}' at offset {} overflows its slot",
+                    entry.label, entry.offset
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the type with the given identifier.
+    fn ty(&self, type_id: &str) -> Result<&StorageType, StorageLayoutError> {
+        self.types
+            .as_ref()
+            .and_then(|types| types.get(type_id))
+            .ok_or_else(|| StorageLayoutError::UnknownType(type_id.to_string()))
+    }
+
+    /// Walks the variable path down to the location of the variable.
+    fn locate(&self, path: &str) -> Result<Location<'_>, StorageLayoutError> {
+        let (name, accesses) = parse_path(path)?;
+        let entry = self
+            .storage
+            .iter()
+            .find(|entry| entry.label == name)
+            .ok_or_else(|| StorageLayoutError::UnknownVariable(name.to_string()))?;
+
+        let mut location =
+            Location { slot: entry.slot()?, offset: entry.offset, ty: self.ty(&entry.type_id)? };
+
+        for access in accesses {
+            let ty = location.ty;
+            let invalid_access = || StorageLayoutError::InvalidAccess {
+                access: match access {
+                    Access::Member(member) => format!(".{}", member),
+                    Access::Index(key) => format!("[{}]", key),
+                },
+                label: ty.label.clone(),
+            };
+
+            location = match (access, ty.encoding) {
+                (Access::Member(member), StorageEncoding::Inplace) => {
+                    let member = ty
+                        .members
+                        .as_ref()
+                        .and_then(|members| members.iter().find(|entry| entry.label == member))
+                        .ok_or_else(invalid_access)?;
+                    Location {
+                        slot: location.slot.wrapping_add(member.slot()?),
+                        offset: member.offset,
+                        ty: self.ty(&member.type_id)?,
+                    }
+                }
+                (Access::Index(key), StorageEncoding::Mapping) => {
+                    let (Some(key_type), Some(value_type)) = (&ty.key, &ty.value) else {
+                        return Err(invalid_access());
+                    };
+                    let mut preimage = self.encode_key(key_type, key)?;
+                    preimage.extend_from_slice(&location.slot.to_be_bytes::<32>());
+                    Location {
+                        slot: keccak256(preimage).into(),
+                        offset: 0,
+                        ty: self.ty(value_type)?,
+                    }
+                }
+                (Access::Index(index), StorageEncoding::DynamicArray) => {
+                    let start = keccak256(location.slot.to_be_bytes::<32>()).into();
+                    self.element(start, ty, index, None)?
+                }
+                (Access::Index(index), StorageEncoding::Inplace) if ty.base.is_some() => {
+                    let length = static_array_length(&ty.label).ok_or_else(invalid_access)?;
+                    self.element(location.slot, ty, index, Some(length))?
+                }
+                _ => return Err(invalid_access()),
+            };
+        }
+
+        Ok(location)
+    }
+
+    /// Location of the element at `index` of an array whose elements start at
+    /// `start`. Elements are packed when several of them fit in a slot.
+    fn element<'a>(
+        &'a self,
+        start: U256,
+        array: &StorageType,
+        index: &str,
+        length: Option<U256>,
+    ) -> Result<Location<'a>, StorageLayoutError> {
+        let invalid_index = || StorageLayoutError::InvalidAccess {
+            access: format!("[{}]", index),
+            label: array.label.clone(),
+        };
+
+        let index = U256::from_str(index).map_err(|_| invalid_index())?;
+        if length.is_some_and(|length| index >= length) {
+            return Err(invalid_index());
+        }
+
+        let base = self.ty(array.base.as_deref().unwrap_or_default())?;
+        let size = base.size()?;
+        if size >= 32 {
+            // Elements start on a new slot and span whole slots
+            let slots = U256::from(size.div_ceil(32));
+            Ok(Location {
+                slot: start.wrapping_add(index.wrapping_mul(slots)),
+                offset: 0,
+                ty: base,
+            })
+        } else {
+            let per_slot = U256::from(32 / size);
+            let position: usize = (index % per_slot).to();
+            Ok(Location {
+                slot: start.wrapping_add(index / per_slot),
+                offset: position * size,
+                ty: base,
+            })
+        }
+    }
+
+    /// Encodes a mapping key as hashed with the slot of the mapping.
+    fn encode_key(&self, key_type: &str, key: &str) -> Result<Vec<u8>, StorageLayoutError> {
+        let ty = self.ty(key_type)?;
+        match ty.encoding {
+            StorageEncoding::Bytes => bytes_data(&ty.label, unquote(key)),
+            StorageEncoding::Inplace => Ok(encode_word(&ty.label, key, ty.size()?)?.to_vec()),
+            _ => Err(StorageLayoutError::InvalidValue {
+                value: key.to_string(),
+                label: ty.label.clone(),
+            }),
+        }
+    }
+}
+
+impl StorageEntry {
+    fn slot(&self) -> Result<U256, StorageLayoutError> {
+        U256::from_str(&self.slot).map_err(|_| {
+            StorageLayoutError::InvalidPath(format!("{} (slot {})", self.label, self.slot))
+        })
+    }
+}
+
+impl StorageType {
+    /// Returns whether the type is a value type, stored in a single slot.
+    fn is_value(&self) -> bool {
+        self.encoding == StorageEncoding::Inplace && self.members.is_none() && self.base.is_none()
+    }
+
+    fn size(&self) -> Result<usize, StorageLayoutError> {
+        self.number_of_bytes
+            .parse::<usize>()
+            .ok()
+            .filter(|size| *size > 0)
+            .ok_or_else(|| StorageLayoutError::UnknownType(self.label.clone()))
+    }
+}
+
+/// Splits a variable path into the variable name and the accesses applied to it.
+fn parse_path(path: &str) -> Result<(&str, Vec<Access<'_>>), StorageLayoutError> {
+    let invalid = || StorageLayoutError::InvalidPath(path.to_string());
+    let is_identifier = |name: &str| {
+        !name.is_empty() &&
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    };
+
+    let path = path.trim();
+    let name_end = path.find(['.', '[']).unwrap_or(path.len());
+    let (name, mut rest) = path.split_at(name_end);
+    if !is_identifier(name) {
+        return Err(invalid());
+    }
+
+    let mut accesses = Vec::new();
+    while !rest.is_empty() {
+        if let Some(member) = rest.strip_prefix('.') {
+            let end = member.find(['.', '[']).unwrap_or(member.len());
+            if !is_identifier(&member[..end]) {
+                return Err(invalid());
+            }
+            accesses.push(Access::Member(&member[..end]));
+            rest = &member[end..];
+        } else if let Some(key) = rest.strip_prefix('[') {
+            let end = key.find(']').ok_or_else(invalid)?;
+            let key = key[..end].trim();
+            if key.is_empty() {
+                return Err(invalid());
+            }
+            accesses.push(Access::Index(key));
+            rest = &rest[end + 2..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok((name, accesses))
+}
+
+/// Encodes a value type as a 32-byte ABI word: `bytesN` are left-aligned,
+/// signed integers are sign-extended and other types are right-aligned.
+fn encode_word(label: &str, value: &str, size: usize) -> Result<B256, StorageLayoutError> {
+    let invalid =
+        || StorageLayoutError::InvalidValue { value: value.to_string(), label: label.to_string() };
+    let value = value.trim();
+
+    let word = if label == "bool" {
+        match value {
+            "true" => B256::with_last_byte(1),
+            "false" => B256::ZERO,
+            _ => return Err(invalid()),
+        }
+    } else if label.starts_with("address") || label.starts_with("contract ") {
+        Address::from_str(value).map_err(|_| invalid())?.into_word()
+    } else if is_fixed_bytes(label) {
+        let data = Bytes::from_str(value).map_err(|_| invalid())?;
+        if data.len() != size {
+            return Err(invalid());
+        }
+        B256::right_padding_from(&data)
+    } else if label.starts_with("int") {
+        let int = match value.strip_prefix("0x") {
+            Some(_) => I256::from_raw(U256::from_str(value).map_err(|_| invalid())?),
+            None => I256::from_dec_str(value).map_err(|_| invalid())?,
+        };
+        // The value must survive a round trip through `size` bytes
+        let word = B256::from(int.into_raw());
+        let fill = if word[32 - size] & 0x80 == 0 { 0x00 } else { 0xff };
+        if word[..32 - size].iter().any(|byte| *byte != fill) {
+            return Err(invalid());
+        }
+        word
+    } else if label.starts_with("uint") || label.starts_with("enum ") {
+        let word = B256::from(U256::from_str(value).map_err(|_| invalid())?);
+        if word[..32 - size].iter().any(|byte| *byte != 0) {
+            return Err(invalid());
+        }
+        word
+    } else {
+        return Err(StorageLayoutError::NotAssignable(label.to_string()));
+    };
+
+    Ok(word)
+}
+
+/// Encodes a `bytes` or `string` value stored at `slot`.
+///
+/// Values shorter than 32 bytes are stored in the slot along with twice their
+/// length. Longer values store `2 * length + 1` in the slot and their data from
+/// `keccak256(slot)`.
+fn encode_bytes(
+    slot: U256,
+    label: &str,
+    value: &str,
+) -> Result<Vec<SlotWrite>, StorageLayoutError> {
+    let data = bytes_data(label, value)?;
+    let length = U256::from(data.len());
+
+    if data.len() < 32 {
+        let mut word = B256::right_padding_from(&data);
+        word[31] = (data.len() * 2) as u8;
+        return Ok(vec![SlotWrite { slot: B256::from(slot), offset: 0, data: word.to_vec() }]);
+    }
+
+    let start: U256 = keccak256(slot.to_be_bytes::<32>()).into();
+    let header = SlotWrite {
+        slot: B256::from(slot),
+        offset: 0,
+        data: B256::from(length * U256::from(2) + U256::from(1)).to_vec(),
+    };
+    let chunks = data.chunks(32).enumerate().map(|(index, chunk)| SlotWrite {
+        slot: B256::from(start.wrapping_add(U256::from(index))),
+        offset: 0,
+        data: B256::right_padding_from(chunk).to_vec(),
+    });
+
+    Ok(std::iter::once(header).chain(chunks).collect())
+}
+
+/// Raw data of a `string` (UTF-8) or `bytes` (hex encoded) value.
+fn bytes_data(label: &str, value: &str) -> Result<Vec<u8>, StorageLayoutError> {
+    if label == "string" {
+        Ok(value.as_bytes().to_vec())
+    } else {
+        Bytes::from_str(value)
+            .map(|bytes| bytes.to_vec())
+            .map_err(|_| StorageLayoutError::InvalidValue {
+                value: value.to_string(),
+                label: label.to_string(),
+            })
+    }
+}
+
+/// Returns whether the type is a fixed-size byte array (`bytes1` to `bytes32`).
+fn is_fixed_bytes(label: &str) -> bool {
+    label
+        .strip_prefix("bytes")
+        .is_some_and(|size| !size.is_empty() && size.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Length of a static array type (e.g. `uint256[3]`).
+fn static_array_length(label: &str) -> Option<U256> {
+    let (_, length) = label.rsplit_once('[')?;
+    U256::from_str(length.strip_suffix(']')?).ok()
+}
+
+/// Strips the quotes around a string mapping key.
+fn unquote(key: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| {
+            key.strip_prefix(*quote)
+                .and_then(|key| key.strip_suffix(*quote))
+        })
+        .unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256};
+    use serde_json::json;
+
+    /// Layout of:
+    /// ```solidity
+    /// struct Config { uint128 fee; address owner; bool paused; }
+    /// mapping(address => uint256) balances;
+    /// mapping(address => mapping(address => uint256)) allowances;
+    /// Config config;
+    /// address[] owners;
+    /// uint64[3] checkpoints;
+    /// string name;
+    /// mapping(string => int16) scores;
+    /// ```
+    fn layout() -> StorageLayout {
+        serde_json::from_value(json!({
+            "storage": [
+                {"astId": 1, "contract": "Token.sol:Token", "label": "balances", "offset": 0, "slot": "0", "type": "t_mapping(t_address,t_uint256)"},
+                {"astId": 2, "contract": "Token.sol:Token", "label": "allowances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_mapping(t_address,t_uint256))"},
+                {"astId": 3, "contract": "Token.sol:Token", "label": "config", "offset": 0, "slot": "2", "type": "t_struct(Config)1_storage"},
+                {"astId": 4, "contract": "Token.sol:Token", "label": "owners", "offset": 0, "slot": "4", "type": "t_array(t_address)dyn_storage"},
+                {"astId": 5, "contract": "Token.sol:Token", "label": "checkpoints", "offset": 0, "slot": "5", "type": "t_array(t_uint64)3_storage"},
+                {"astId": 6, "contract": "Token.sol:Token", "label": "name", "offset": 0, "slot": "6", "type": "t_string_storage"},
+                {"astId": 7, "contract": "Token.sol:Token", "label": "scores", "offset": 0, "slot": "7", "type": "t_mapping(t_string_memory_ptr,t_int16)"}
+            ],
+            "types": {
+                "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+                "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+                "t_int16": {"encoding": "inplace", "label": "int16", "numberOfBytes": "2"},
+                "t_uint64": {"encoding": "inplace", "label": "uint64", "numberOfBytes": "8"},
+                "t_uint128": {"encoding": "inplace", "label": "uint128", "numberOfBytes": "16"},
+                "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+                "t_string_storage": {"encoding": "bytes", "label": "string", "numberOfBytes": "32"},
+                "t_string_memory_ptr": {"encoding": "bytes", "label": "string", "numberOfBytes": "32"},
+                "t_array(t_address)dyn_storage": {"encoding": "dynamic_array", "label": "address[]", "numberOfBytes": "32", "base": "t_address"},
+                "t_array(t_uint64)3_storage": {"encoding": "inplace", "label": "uint64[3]", "numberOfBytes": "32", "base": "t_uint64"},
+                "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "label": "mapping(address => uint256)", "numberOfBytes": "32", "key": "t_address", "value": "t_uint256"},
+                "t_mapping(t_address,t_mapping(t_address,t_uint256))": {"encoding": "mapping", "label": "mapping(address => mapping(address => uint256))", "numberOfBytes": "32", "key": "t_address", "value": "t_mapping(t_address,t_uint256)"},
+                "t_mapping(t_string_memory_ptr,t_int16)": {"encoding": "mapping", "label": "mapping(string => int16)", "numberOfBytes": "32", "key": "t_string_memory_ptr", "value": "t_int16"},
+                "t_struct(Config)1_storage": {"encoding": "inplace", "label": "struct Token.Config", "numberOfBytes": "64", "members": [
+                    {"astId": 8, "contract": "Token.sol:Token", "label": "fee", "offset": 0, "slot": "0", "type": "t_uint128"},
+                    {"astId": 9, "contract": "Token.sol:Token", "label": "owner", "offset": 0, "slot": "1", "type": "t_address"},
+                    {"astId": 10, "contract": "Token.sol:Token", "label": "paused", "offset": 20, "slot": "1", "type": "t_bool"}
+                ]}
+            }
+        }))
+        .unwrap()
+    }
+
+    fn mapping_slot(key: &[u8], slot: B256) -> B256 {
+        keccak256([key, slot.as_slice()].concat())
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("allowances[0xabc][ 0xdef ].inner").unwrap(),
+            (
+                "allowances",
+                vec![Access::Index("0xabc"), Access::Index("0xdef"), Access::Member("inner")]
+            )
+        );
+        assert_eq!(parse_path("fee").unwrap(), ("fee", vec![]));
+        assert!(parse_path("balances[").is_err());
+        assert!(parse_path("balances[]").is_err());
+        assert!(parse_path("config..fee").is_err());
+        assert!(parse_path("[0]").is_err());
+    }
+
+    #[test]
+    fn test_resolve_mappings() {
+        let layout = layout();
+        let owner = address!("0x1111111111111111111111111111111111111111");
+        let spender = address!("0x2222222222222222222222222222222222222222");
+
+        let writes = layout
+            .resolve(&format!("balances[{}]", owner), "1000")
+            .unwrap();
+        assert_eq!(
+            writes,
+            vec![SlotWrite {
+                slot: mapping_slot(owner.into_word().as_slice(), B256::ZERO),
+                offset: 0,
+                data: B256::from(U256::from(1000)).to_vec(),
+            }]
+        );
+
+        let writes = layout
+            .resolve(&format!("allowances[{}][{}]", owner, spender), "0xff")
+            .unwrap();
+        let inner = mapping_slot(owner.into_word().as_slice(), B256::with_last_byte(1));
+        assert_eq!(writes[0].slot, mapping_slot(spender.into_word().as_slice(), inner));
+        assert!(writes[0].is_full_slot());
+
+        let writes = layout.resolve("scores[\"alice\"]", "-2").unwrap();
+        assert_eq!(writes[0].slot, mapping_slot(b"alice", B256::with_last_byte(7)));
+        assert_eq!(writes[0].data, vec![0xff, 0xfe]);
+    }
+
+    #[test]
+    fn test_resolve_packed_struct() {
+        let layout = layout();
+
+        let fee = layout.resolve("config.fee", "0x64").unwrap();
+        assert_eq!(fee[0].slot, B256::with_last_byte(2));
+        assert_eq!((fee[0].offset, fee[0].data.len()), (0, 16));
+
+        let paused = layout.resolve("config.paused", "true").unwrap();
+        assert_eq!(paused[0].slot, B256::with_last_byte(3));
+        assert_eq!((paused[0].offset, paused[0].data.clone()), (20, vec![1]));
+
+        let owner = address!("0x1111111111111111111111111111111111111111");
+        let owner_write = layout.resolve("config.owner", &owner.to_string()).unwrap();
+        let word = paused[0].apply(owner_write[0].apply(B256::ZERO));
+        assert_eq!(
+            word,
+            b256!("0x0000000000000000000000011111111111111111111111111111111111111111")
+        );
+    }
+
+    #[test]
+    fn test_resolve_arrays() {
+        let layout = layout();
+
+        // keccak256(uint256(4)) + 2
+        let owners = layout
+            .resolve("owners[2]", "0x1111111111111111111111111111111111111111")
+            .unwrap();
+        let start: U256 = keccak256(B256::with_last_byte(4)).into();
+        assert_eq!(owners[0].slot, B256::from(start + U256::from(2)));
+
+        // Four uint64 per slot
+        let checkpoint = layout.resolve("checkpoints[2]", "7").unwrap();
+        assert_eq!(checkpoint[0].slot, B256::with_last_byte(5));
+        assert_eq!(checkpoint[0].offset, 16);
+        assert!(layout.resolve("checkpoints[3]", "7").is_err());
+    }
+
+    #[test]
+    fn test_resolve_strings() {
+        let layout = layout();
+
+        let short = layout.resolve("name", "Altitrace").unwrap();
+        assert_eq!(short.len(), 1);
+        assert_eq!(&short[0].data[..9], b"Altitrace");
+        assert_eq!(short[0].data[31], 18);
+
+        let long_name = "a".repeat(40);
+        let long = layout.resolve("name", &long_name).unwrap();
+        assert_eq!(long.len(), 3);
+        assert_eq!(long[0].data, B256::from(U256::from(81)).to_vec());
+        assert_eq!(long[1].slot, keccak256(B256::with_last_byte(6)));
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let layout = layout();
+
+        assert_eq!(
+            layout.resolve("supply", "1"),
+            Err(StorageLayoutError::UnknownVariable("supply".to_string()))
+        );
+        assert!(matches!(
+            layout.resolve("config.unknown", "1"),
+            Err(StorageLayoutError::InvalidAccess { .. })
+        ));
+        assert!(matches!(layout.resolve("config", "1"), Err(StorageLayoutError::NotAssignable(_))));
+        assert!(matches!(
+            layout.resolve("config.fee", &U256::MAX.to_string()),
+            Err(StorageLayoutError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            layout.resolve("scores[\"bob\"]", "40000"),
+            Err(StorageLayoutError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_invalid_layouts() {
+        assert_eq!(layout().validate(), Ok(()));
+
+        // Value type larger than a slot
+        let mut oversized = layout();
+        if let Some(types) = &mut oversized.types {
+            types.get_mut("t_uint128").unwrap().number_of_bytes = "48".to_string();
+        }
+        assert!(matches!(
+            oversized.resolve("config.fee", "1"),
+            Err(StorageLayoutError::InvalidLayout(_))
+        ));
+        // Also rejected for the types only used as mapping keys
+        let mut oversized_key = layout();
+        if let Some(types) = &mut oversized_key.types {
+            types.get_mut("t_address").unwrap().number_of_bytes = "33".to_string();
+        }
+        assert!(matches!(
+            oversized_key.resolve("config.paused", "true"),
+            Err(StorageLayoutError::InvalidLayout(_))
+        ));
+
+        // Struct member past the end of its slot
+        let mut overflowing = layout();
+        if let Some(types) = &mut overflowing.types {
+            let config = types.get_mut("t_struct(Config)1_storage").unwrap();
+            config.members.as_mut().unwrap()[2].offset = 32;
+        }
+        assert!(matches!(
+            overflowing.resolve("config.paused", "true"),
+            Err(StorageLayoutError::InvalidLayout(_))
+        ));
+
+        // Variable whose offset and size exceed the slot
+        let mut overflowing = layout();
+        overflowing.storage[6].type_id = "t_uint128".to_string();
+        overflowing.storage[6].offset = 17;
+        assert!(matches!(
+            overflowing.resolve("balances[0x1111111111111111111111111111111111111111]", "1"),
+            Err(StorageLayoutError::InvalidLayout(_))
+        ));
+    }
+
+    #[test]
+    fn test_state_override_storage_variables() {
+        let mut state_override: crate::types::StateOverride = serde_json::from_value(json!({
+            "address": "0x1111111111111111111111111111111111111111",
+            "stateDiff": {
+                "0x0000000000000000000000000000000000000000000000000000000000000002": "0x00000000000000000000000000000000000000000000000000000000000000ff"
+            },
+            "storageVariables": {"config.fee": "0x64"}
+        }))
+        .unwrap();
+        assert_eq!(
+            state_override.storage_variable_writes(),
+            Err(StorageLayoutError::MissingLayout)
+        );
+
+        state_override.storage_layout = Some(layout());
+        let writes = state_override.storage_variable_writes().unwrap();
+        let slot = B256::with_last_byte(2);
+        let current = state_override.overridden_slot(slot).unwrap();
+        state_override.set_slot(slot, writes[0].apply(current));
+
+        let state_diff = state_override.state_diff.unwrap();
+        assert_eq!(state_diff.len(), 1);
+        assert_eq!(state_diff[&slot.to_string()], B256::with_last_byte(0x64).to_string());
+    }
+}
@@ -5,12 +5,15 @@
 
 use crate::{
     handlers::{trace::TracingResult, validation::validate_block_number_or_tag},
+    services::hyperevm::gas_breakdown::IntrinsicGas,
     types::TransactionReceiptInfo,
     utils::default_latest,
 };
+use alloy_primitives::Address;
 use alloy_rpc_types::{
     BlockId, StateContext as AlloyStateContext, TransactionIndex as AlloyTransactionIndex,
 };
+use alloy_rpc_types_eth::AccessList;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use utoipa::ToSchema;
@@ -24,16 +27,57 @@ pub struct TraceResponse {
 
     /// The transaction receipt.
     pub receipt: Option<TransactionReceiptInfo>,
+
+    /// Intrinsic cost of the traced transaction, used for the gas breakdown.
+    pub intrinsic_gas: Option<IntrinsicGas>,
+
+    /// Contract executed by the traced transaction (its recipient or the created
+    /// contract), used for the storage accesses.
+    pub target: Option<Address>,
+
+    /// Access list of the traced transaction, whose slots are warm from its start,
+    /// used for the storage accesses.
+    pub access_list: Option<AccessList>,
+
+    /// Block of the traced transaction, used to cache the traces of finalized
+    /// transactions.
+    pub block_number: Option<u64>,
 }
 
 impl TraceResponse {
     /// Create a new [`TraceResponse`].
     pub const fn new(trace_result: TracingResult) -> Self {
-        Self { trace_result, receipt: None }
+        Self {
+            trace_result,
+            receipt: None,
+            intrinsic_gas: None,
+            target: None,
+            access_list: None,
+            block_number: None,
+        }
     }
 
     pub fn with_receipt(self, receipt: TransactionReceiptInfo) -> Self {
-        Self { trace_result: self.trace_result, receipt: Some(receipt) }
+        Self { receipt: Some(receipt), ..self }
+    }
+
+    pub const fn with_intrinsic_gas(mut self, intrinsic_gas: Option<IntrinsicGas>) -> Self {
+        self.intrinsic_gas = intrinsic_gas;
+        self
+    }
+
+    pub const fn with_target(mut self, target: Option<Address>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_access_list(self, access_list: Option<AccessList>) -> Self {
+        Self { access_list, ..self }
+    }
+
+    pub const fn with_block_number(mut self, block_number: Option<u64>) -> Self {
+        self.block_number = block_number;
+        self
     }
 }
 
@@ -57,6 +57,19 @@ pub struct TransactionCall {
     /// Access list to use for the transaction.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_list: Option<AccessList>,
+
+    /// Nonce of the transaction.
+    /// If not specified, the current nonce of the sender will be used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 7)]
+    pub nonce: Option<u64>,
+
+    /// Maximum fee per gas the sender is willing to pay (hex encoded wei).
+    /// If not specified, the base fee of the block will be used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_uint256"))]
+    #[schema(example = "0x3b9aca00", pattern = "^0x[a-fA-F0-9]*$")]
+    pub max_fee_per_gas: Option<String>,
 }
 
 /// Transaction receipt information.